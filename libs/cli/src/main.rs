@@ -0,0 +1,288 @@
+//! Renders files with the same modes as the web version, and saves them as PNGs.
+
+use player::{render, BitOrder, Palette, RenderModeKind, RenderOptions};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process;
+
+mod png;
+
+const USAGE: &str = "\
+Usage: arbitrary-bytes render [OPTIONS] <INPUT> -o <OUTPUT>
+
+Draws INPUT with one of the modes, and saves it to OUTPUT as a PNG.
+
+Options:
+  --mode <MODE>            quadrilateral, triangle, line, circle, appearance,
+                           3-bits-per-pixel, text-encoding or comparison. Picked
+                           from the file type if left out.
+  --offset <N>             The byte to put the cursor on, which the pixel based
+                           modes scroll to. Decimal, or hex starting with 0x.
+  --bit-order <ORDER>      msb or lsb.
+  --palette <PALETTE>      default, greyscale or heat.
+  --frames <N>             How many frames to run the mode for. By default it
+                           runs until every byte has been drawn.
+  --width <N>              The width of the screen in pixels. 256 by default.
+  --height <N>             The height of the screen in pixels. 256 by default.
+  --scale <N>              Scales the screen up by N, from 1 to 16, as long as
+                           the result is no more pixels than 8192 by 8192.
+  --no-hud                 Leaves out the status bar.
+  -o, --output <OUTPUT>    Where to write the PNG.
+  -h, --help               Prints this.";
+
+const MAX_SCALE: usize = 16;
+// Wide enough for an overview of a few megabytes at once, but small enough that a typo
+// doesn't try to allocate gigabytes.
+const MAX_SIZE: usize = 8192;
+// The same goes for the scaled up image, which is the biggest thing allocated, so a big
+// screen can't be multiplied past this by the scale as well.
+const MAX_PIXELS: usize = MAX_SIZE * MAX_SIZE;
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    mode: Option<RenderModeKind>,
+    options: RenderOptions,
+    scale: usize,
+}
+
+// What the command line calls a name like "3 bits per pixel".
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+fn pick<T: Copy>(all: &[T], names: fn(T) -> Vec<&'static str>, value: &str) -> Result<T, String> {
+    all.iter()
+        .cloned()
+        .find(|&t| names(t).iter().any(|&name| slug(name) == value))
+        .ok_or_else(|| {
+            let known: Vec<String> = all.iter().map(|&t| slug(names(t)[0])).collect();
+            format!(
+                "unknown value \"{}\", expected one of {}",
+                value,
+                known.join(", ")
+            )
+        })
+}
+
+fn number(value: &str) -> Result<usize, String> {
+    let parsed = if value.starts_with("0x") || value.starts_with("0X") {
+        usize::from_str_radix(&value[2..], 16)
+    } else {
+        value.parse()
+    };
+    parsed.map_err(|_| format!("\"{}\" is not a number", value))
+}
+
+fn size(arg: &str, value: &str) -> Result<usize, String> {
+    match number(value)? {
+        n @ 1..=MAX_SIZE => Ok(n),
+        _ => Err(format!("{} must be from 1 to {}", arg, MAX_SIZE)),
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    match args.next().as_deref() {
+        Some("render") => {}
+        Some("-h") | Some("--help") => return Err(String::new()),
+        Some(other) => return Err(format!("unknown command \"{}\"", other)),
+        None => return Err("expected a command".to_string()),
+    }
+
+    let mut input = None;
+    let mut output = None;
+    let mut mode = None;
+    let mut options = RenderOptions::default();
+    let mut scale = 1;
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Err(String::new());
+        }
+        if arg == "--no-hud" {
+            options.settings.show_hud = false;
+            continue;
+        }
+        if !arg.starts_with('-') {
+            if input.is_some() {
+                return Err(format!("unexpected argument \"{}\"", arg));
+            }
+            input = Some(PathBuf::from(arg));
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--mode" => {
+                mode = Some(pick(&RenderModeKind::ALL, |m| vec![m.name()], &value)?);
+            }
+            "--offset" => options.offset = number(&value)?,
+            "--bit-order" => {
+                options.settings.bit_order =
+                    pick(&BitOrder::ALL, |b| vec![b.short_name(), b.name()], &value)?;
+            }
+            "--palette" => {
+                options.settings.palette = pick(&Palette::ALL, |p| vec![p.name()], &value)?;
+            }
+            "--frames" => options.frames = Some(number(&value)?),
+            "--width" => options.settings.screen_width = size(&arg, &value)?,
+            "--height" => options.settings.screen_height = size(&arg, &value)?,
+            "--scale" => {
+                scale = number(&value)?;
+                if scale == 0 || scale > MAX_SCALE {
+                    return Err(format!("--scale must be from 1 to {}", MAX_SCALE));
+                }
+            }
+            "-o" | "--output" => output = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option \"{}\"", arg)),
+        }
+    }
+
+    let settings = &options.settings;
+    if settings.screen_width * settings.screen_height * scale * scale > MAX_PIXELS {
+        return Err(format!(
+            "{}x{} scaled by {} is more than {} pixels",
+            settings.screen_width, settings.screen_height, scale, MAX_PIXELS
+        ));
+    }
+
+    Ok(Args {
+        input: input.ok_or("expected an input file")?,
+        output: output.ok_or("expected an output file, given with -o")?,
+        mode,
+        options,
+        scale,
+    })
+}
+
+// Makes each pixel a `scale` by `scale` square.
+fn scale_up(pixels: &[u32], width: usize, scale: usize) -> Vec<u32> {
+    let mut scaled = Vec::with_capacity(pixels.len() * scale * scale);
+    for row in pixels.chunks(width) {
+        let mut scaled_row = Vec::with_capacity(row.len() * scale);
+        for &pixel in row {
+            for _ in 0..scale {
+                scaled_row.push(pixel);
+            }
+        }
+        for _ in 0..scale {
+            scaled.extend_from_slice(&scaled_row);
+        }
+    }
+    scaled
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let bytes = std::fs::read(&args.input)
+        .map_err(|e| format!("could not read {}: {}", args.input.display(), e))?;
+
+    let mode = args.mode.unwrap_or_else(|| {
+        signature::detect(&bytes)
+            .map(player::suggested_mode)
+            .unwrap_or(RenderModeKind::ThreeBitsPerPixel)
+    });
+    let framebuffer = render(&bytes, mode, &args.options);
+    let pixels = scale_up(&framebuffer.buffer, framebuffer.width, args.scale);
+
+    let write_error = |e| format!("could not write {}: {}", args.output.display(), e);
+    let file = File::create(&args.output).map_err(write_error)?;
+    png::write(
+        &mut BufWriter::new(file),
+        framebuffer.width * args.scale,
+        framebuffer.height * args.scale,
+        &pixels,
+    )
+    .map_err(write_error)
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            if message.is_empty() {
+                println!("{}", USAGE);
+                return;
+            }
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(message) = run(args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse_args_reads_every_flag() {
+        let args = parse(
+            "render --mode 3-bits-per-pixel --offset 0x400 --bit-order lsb --palette heat \
+             --frames 10 --width 1024 --height 0x200 --scale 2 --no-hud in.bin -o out.png",
+        )
+        .unwrap();
+
+        assert_eq!(args.input, PathBuf::from("in.bin"));
+        assert_eq!(args.output, PathBuf::from("out.png"));
+        assert_eq!(args.mode, Some(RenderModeKind::ThreeBitsPerPixel));
+        assert_eq!(args.options.offset, 0x400);
+        assert_eq!(args.options.settings.bit_order, BitOrder::LsbFirst);
+        assert_eq!(args.options.settings.palette, Palette::Heat);
+        assert_eq!(args.options.frames, Some(10));
+        assert_eq!(args.options.settings.screen_width, 1024);
+        assert_eq!(args.options.settings.screen_height, 0x200);
+        assert!(!args.options.settings.show_hud);
+        assert_eq!(args.scale, 2);
+    }
+
+    #[test]
+    fn parse_args_explains_what_is_wrong() {
+        assert_eq!(
+            parse("render --mode hilbert in.bin -o out.png").unwrap_err(),
+            "unknown value \"hilbert\", expected one of quadrilateral, triangle, line, \
+             circle, appearance, 3-bits-per-pixel, text-encoding, comparison"
+        );
+        assert_eq!(
+            parse("render in.bin").unwrap_err(),
+            "expected an output file, given with -o"
+        );
+        assert_eq!(
+            parse("render --scale 0 in.bin -o out.png").unwrap_err(),
+            "--scale must be from 1 to 16"
+        );
+        assert_eq!(
+            parse("render --width 100000 in.bin -o out.png").unwrap_err(),
+            "--width must be from 1 to 8192"
+        );
+        assert_eq!(
+            parse("render --width 8192 --height 8192 --scale 16 in.bin -o out.png").unwrap_err(),
+            "8192x8192 scaled by 16 is more than 67108864 pixels"
+        );
+        assert!(parse("render --width 8192 --height 8192 in.bin -o out.png").is_ok());
+        assert!(parse("render --width 512 --height 512 --scale 16 in.bin -o out.png").is_ok());
+        assert_eq!(
+            parse("render in.bin --frames").unwrap_err(),
+            "--frames needs a value"
+        );
+    }
+
+    #[test]
+    fn scale_up_repeats_pixels_and_rows() {
+        assert_eq!(
+            scale_up(&[1, 2, 3, 4], 2, 2),
+            [1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]
+        );
+    }
+}
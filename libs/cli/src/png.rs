@@ -0,0 +1,287 @@
+//! Just enough of a PNG encoder to save screenshots: 8 bit RGB, one IDAT chunk, and
+//! DEFLATE with the fixed Huffman codes. Screenshots are mostly flat colour, so finding
+//! repeats matters much more than picking the best codes for them.
+
+use std::io::{self, Write};
+
+/// Writes `pixels`, which are `width` by `height` and in the framebuffer's `0xAABBGGRR`
+/// layout, as a PNG. Alpha is dropped.
+pub fn write<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height);
+
+    // Every row starts with a filter type byte, and 0 means no filter.
+    let mut raw = Vec::with_capacity(height * (1 + width * 3));
+    for row in pixels.chunks(width) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&[pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8]);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth 8, colour type 2 (RGB), then the default compression, filter and
+    // interlace methods.
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    out.write_all(b"\x89PNG\r\n\x1A\n")?;
+    write_chunk(out, b"IHDR", &ihdr)?;
+    write_chunk(out, b"IDAT", &zlib(&raw))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&crc.finish().to_be_bytes())
+}
+
+fn zlib(bytes: &[u8]) -> Vec<u8> {
+    // A 32K window, and the check bits for that with no preset dictionary.
+    let mut output = vec![0x78, 0x01];
+    output.extend(deflate(bytes));
+    output.extend_from_slice(&adler32(bytes).to_be_bytes());
+    output
+}
+
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Crc32(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u32::from(byte);
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = self.0 >> 1 ^ 0xEDB8_8320 & mask;
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    // DEFLATE packs bits starting from the least significant bit of each byte.
+    fn bits(&mut self, value: u32, n: u32) {
+        self.buffer |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are the exception, and go most significant bit first.
+    fn code(&mut self, code: u32, n: u32) {
+        self.bits(code.reverse_bits() >> (32 - n), n);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const WINDOW: usize = 32 * 1024;
+const HASH_BITS: u32 = 15;
+// How many earlier positions with the same hash to try before settling for the best so far.
+const MAX_CHAIN: usize = 32;
+
+// Writes the symbol with the fixed literal/length code.
+fn write_symbol(writer: &mut BitWriter, symbol: u16) {
+    let symbol = u32::from(symbol);
+    match symbol {
+        0..=143 => writer.code(0x30 + symbol, 8),
+        144..=255 => writer.code(0x190 + symbol - 144, 9),
+        256..=279 => writer.code(symbol - 256, 7),
+        _ => writer.code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, len: usize, distance: usize) {
+    let i = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= len)
+        .unwrap();
+    write_symbol(writer, 257 + i as u16);
+    writer.bits(
+        (len - LENGTH_BASE[i] as usize) as u32,
+        u32::from(LENGTH_EXTRA[i]),
+    );
+
+    let i = DISTANCE_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap();
+    writer.code(i as u32, 5);
+    writer.bits(
+        (distance - DISTANCE_BASE[i] as usize) as u32,
+        u32::from(DISTANCE_EXTRA[i]),
+    );
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let n = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (n.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+fn insert(head: &mut [usize], previous: &mut [usize], bytes: &[u8], i: usize) {
+    if i + MIN_MATCH <= bytes.len() {
+        let h = hash(&bytes[i..]);
+        previous[i] = head[h];
+        head[h] = i + 1;
+    }
+}
+
+/// Compresses `bytes` as one DEFLATE block with the fixed Huffman codes.
+fn deflate(bytes: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        bytes: Vec::with_capacity(bytes.len() / 4),
+        buffer: 0,
+        count: 0,
+    };
+    // The last block, with the fixed codes.
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    // The most recent position with each hash, and for each position the one before it
+    // with the same hash, offset by one so zero can mean none.
+    let mut head = vec![0usize; 1 << HASH_BITS];
+    let mut previous = vec![0usize; bytes.len()];
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= bytes.len() {
+            let max_len = std::cmp::min(MAX_MATCH, bytes.len() - i);
+            let mut candidate = head[hash(&bytes[i..])];
+            for _ in 0..MAX_CHAIN {
+                if candidate == 0 || i - (candidate - 1) > WINDOW {
+                    break;
+                }
+                let start = candidate - 1;
+                let len = bytes[start..]
+                    .iter()
+                    .zip(&bytes[i..i + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = i - start;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = previous[start];
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            write_match(&mut writer, best_len, best_distance);
+            for j in i..i + best_len {
+                insert(&mut head, &mut previous, bytes, j);
+            }
+            i += best_len;
+        } else {
+            write_symbol(&mut writer, u16::from(bytes[i]));
+            insert(&mut head, &mut previous, bytes, i);
+            i += 1;
+        }
+    }
+
+    write_symbol(&mut writer, 256);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deflate_round_trips_through_inflate() {
+        let mut bytes = b"abcabcabcabc, and then something else entirely.".to_vec();
+        bytes.extend(vec![7; 1000]);
+        bytes.extend((0..=255).cycle().take(5000));
+
+        let compressed = deflate(&bytes);
+
+        assert!(compressed.len() < bytes.len() / 4);
+        assert_eq!(inflate::inflate(&compressed), Ok((bytes, compressed.len())));
+        assert_eq!(inflate::inflate(&deflate(&[])), Ok((Vec::new(), 2)));
+    }
+
+    #[test]
+    fn write_produces_the_chunks_in_order_with_valid_image_data() {
+        let pixels = [0xFF00_00FF, 0xFF00_FF00, 0xFFFF_0000, 0xFF12_3456];
+        let mut png = Vec::new();
+        write(&mut png, 2, 2, &pixels).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], [0, 0, 0, 2, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let idat = &png[41..41 + idat_len];
+        assert_eq!(
+            inflate::decompress(idat, 0, inflate::Format::Zlib),
+            Ok(vec![
+                0, 0xFF, 0, 0, 0, 0xFF, 0, //
+                0, 0, 0, 0xFF, 0x56, 0x34, 0x12,
+            ])
+        );
+    }
+}
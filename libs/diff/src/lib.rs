@@ -0,0 +1,372 @@
+//! Lines two byte buffers up with each other, finding what was inserted, deleted or
+//! changed between them, even when everything after an insertion has moved.
+//!
+//! Blocks of the first buffer that only occur once in it are looked for in the second one
+//! with a rolling hash, and each one found is grown into the longest run of matching bytes
+//! around it. These runs anchor the alignment, and the gaps between them are diffed byte by
+//! byte when they are small enough.
+
+use std::cmp::max;
+use std::collections::HashMap;
+
+/// How many bytes of the first buffer are hashed together to find anchors. Shorter blocks
+/// find more anchors, but more of them are in the wrong place.
+pub const BLOCK_SIZE: usize = 32;
+
+// Gaps between anchors with more than this many pairs of bytes to compare are reported as
+// replaced, instead of diffed byte by byte.
+const MAX_GAP_CELLS: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Equal,
+    /// Bytes of the first buffer that are not in the second.
+    Delete,
+    /// Bytes of the second buffer that are not in the first.
+    Insert,
+    /// Bytes of the first buffer that became different bytes in the second.
+    Replace,
+}
+
+/// A run of bytes in each buffer that `op` turns one into the other. Deletions have no bytes
+/// in the second buffer, and insertions none in the first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub op: Op,
+    pub a_start: usize,
+    pub a_len: usize,
+    pub b_start: usize,
+    pub b_len: usize,
+}
+
+impl Hunk {
+    pub fn a_end(&self) -> usize {
+        self.a_start + self.a_len
+    }
+
+    pub fn b_end(&self) -> usize {
+        self.b_start + self.b_len
+    }
+}
+
+/// Returns the hunks that turn `a` into `b`, in order. They cover every byte of both, with
+/// no equal or changed hunks next to each other.
+pub fn diff(a: &[u8], b: &[u8]) -> Vec<Hunk> {
+    let mut hunks = Hunks::default();
+    let (mut a_pos, mut b_pos) = (0, 0);
+    for anchor in anchors(a, b) {
+        diff_gap(&mut hunks, a, b, (a_pos, anchor.a), (b_pos, anchor.b));
+        hunks.push(true, anchor.a, anchor.len, anchor.b, anchor.len);
+        a_pos = anchor.a + anchor.len;
+        b_pos = anchor.b + anchor.len;
+    }
+    diff_gap(&mut hunks, a, b, (a_pos, a.len()), (b_pos, b.len()));
+
+    hunks.0
+}
+
+#[derive(Default)]
+struct Hunks(Vec<Hunk>);
+
+impl Hunks {
+    // Adds a hunk after the last one, merging it in if they are both equal or both changes.
+    fn push(&mut self, equal: bool, a_start: usize, a_len: usize, b_start: usize, b_len: usize) {
+        if a_len == 0 && b_len == 0 {
+            return;
+        }
+
+        let op = |a_len, b_len| match (equal, a_len, b_len) {
+            (true, _, _) => Op::Equal,
+            (false, _, 0) => Op::Delete,
+            (false, 0, _) => Op::Insert,
+            (false, _, _) => Op::Replace,
+        };
+        if let Some(last) = self.0.last_mut() {
+            if (last.op == Op::Equal) == equal {
+                last.a_len += a_len;
+                last.b_len += b_len;
+                last.op = op(last.a_len, last.b_len);
+                return;
+            }
+        }
+        self.0.push(Hunk {
+            op: op(a_len, b_len),
+            a_start,
+            a_len,
+            b_start,
+            b_len,
+        });
+    }
+}
+
+// A run of `len` bytes that are the same at `a` in the first buffer and `b` in the second.
+#[derive(Debug, PartialEq)]
+struct Anchor {
+    a: usize,
+    b: usize,
+    len: usize,
+}
+
+// Finds matching runs that go forwards through both buffers, taking the first one found
+// each time.
+fn anchors(a: &[u8], b: &[u8]) -> Vec<Anchor> {
+    let mut anchors = Vec::new();
+    if a.len() < BLOCK_SIZE || b.len() < BLOCK_SIZE {
+        return anchors;
+    }
+
+    // The hash of each block of `a` to its offset, or to `None` if there is more than one
+    // block with that hash. Repeated blocks, like runs of zeroes, would match all over the
+    // place.
+    let mut blocks: HashMap<u64, Option<usize>> = HashMap::new();
+    for start in (0..=a.len() - BLOCK_SIZE).step_by(BLOCK_SIZE) {
+        blocks
+            .entry(RollingHash::new(&a[start..start + BLOCK_SIZE]).0)
+            .and_modify(|offset| *offset = None)
+            .or_insert(Some(start));
+    }
+
+    // The ends of the last anchor, which the next one has to start after.
+    let (mut a_end, mut b_end) = (0, 0);
+    let mut i = 0;
+    let mut hash = RollingHash::new(&b[..BLOCK_SIZE]);
+    loop {
+        if let Some(&Some(a_start)) = blocks.get(&hash.0) {
+            if a_start >= a_end && a[a_start..a_start + BLOCK_SIZE] == b[i..i + BLOCK_SIZE] {
+                let before = common_suffix_len(&a[a_end..a_start], &b[b_end..i]);
+                let after = common_prefix_len(&a[a_start..], &b[i..]);
+                anchors.push(Anchor {
+                    a: a_start - before,
+                    b: i - before,
+                    len: before + after,
+                });
+                a_end = a_start + after;
+                b_end = i + after;
+
+                if b_end + BLOCK_SIZE > b.len() {
+                    break;
+                }
+                i = b_end;
+                hash = RollingHash::new(&b[i..i + BLOCK_SIZE]);
+                continue;
+            }
+        }
+
+        if i + BLOCK_SIZE >= b.len() {
+            break;
+        }
+        hash.roll(b[i], b[i + BLOCK_SIZE]);
+        i += 1;
+    }
+
+    anchors
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn common_suffix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+// A polynomial hash of the last `BLOCK_SIZE` bytes, which can move along one byte at a time.
+struct RollingHash(u64);
+
+const BASE: u64 = 0x0100_0000_01B3;
+
+impl RollingHash {
+    fn new(block: &[u8]) -> Self {
+        RollingHash(block.iter().fold(0, |hash: u64, &byte| {
+            hash.wrapping_mul(BASE).wrapping_add(byte.into())
+        }))
+    }
+
+    // Takes `out` off the start of the block and adds `into` to the end.
+    fn roll(&mut self, out: u8, into: u8) {
+        let out_weight = BASE.wrapping_pow(BLOCK_SIZE as u32 - 1);
+        self.0 = self
+            .0
+            .wrapping_sub(u64::from(out).wrapping_mul(out_weight))
+            .wrapping_mul(BASE)
+            .wrapping_add(into.into());
+    }
+}
+
+// Diffs the bytes between two anchors, from the longest common subsequence of the two
+// gaps, or calls the whole thing replaced if that would take too long.
+fn diff_gap(
+    hunks: &mut Hunks,
+    a: &[u8],
+    b: &[u8],
+    (a_start, a_end): (usize, usize),
+    (b_start, b_end): (usize, usize),
+) {
+    let (a_gap, b_gap) = (&a[a_start..a_end], &b[b_start..b_end]);
+    let (n, m) = (a_gap.len(), b_gap.len());
+    if n == 0 || m == 0 || n.saturating_mul(m) > MAX_GAP_CELLS {
+        hunks.push(false, a_start, n, b_start, m);
+        return;
+    }
+
+    // `lengths[i * width + j]` is the length of the longest common subsequence of
+    // `a_gap[i..]` and `b_gap[j..]`.
+    let width = m + 1;
+    let mut lengths = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * width + j] = if a_gap[i] == b_gap[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                max(lengths[(i + 1) * width + j], lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let (a_at, b_at) = (a_start + i, b_start + j);
+        if i < n && j < m && a_gap[i] == b_gap[j] {
+            hunks.push(true, a_at, 1, b_at, 1);
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1]) {
+            hunks.push(false, a_at, 1, b_at, 0);
+            i += 1;
+        } else {
+            hunks.push(false, a_at, 0, b_at, 1);
+            j += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bytes that don't repeat, so every block is unique.
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(2_654_435_761) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn hunk(op: Op, a_start: usize, a_len: usize, b_start: usize, b_len: usize) -> Hunk {
+        Hunk {
+            op,
+            a_start,
+            a_len,
+            b_start,
+            b_len,
+        }
+    }
+
+    // Checks the hunks cover both buffers in order, and that the equal ones are.
+    fn assert_consistent(a: &[u8], b: &[u8], hunks: &[Hunk]) {
+        let (mut a_pos, mut b_pos) = (0, 0);
+        for h in hunks {
+            assert_eq!((h.a_start, h.b_start), (a_pos, b_pos), "{:?}", hunks);
+            if h.op == Op::Equal {
+                assert_eq!(a[h.a_start..h.a_end()], b[h.b_start..h.b_end()]);
+            }
+            a_pos = h.a_end();
+            b_pos = h.b_end();
+        }
+        assert_eq!((a_pos, b_pos), (a.len(), b.len()));
+    }
+
+    #[test]
+    fn an_insertion_is_found_even_though_everything_after_it_moved() {
+        let a = noise(4096, 1);
+        let mut b = a.clone();
+        let inserted = noise(100, 2);
+        b.splice(1000..1000, inserted.iter().cloned());
+
+        let hunks = diff(&a, &b);
+
+        assert_eq!(
+            hunks,
+            [
+                hunk(Op::Equal, 0, 1000, 0, 1000),
+                hunk(Op::Insert, 1000, 0, 1000, 100),
+                hunk(Op::Equal, 1000, 3096, 1100, 3096),
+            ]
+        );
+    }
+
+    #[test]
+    fn deletions_and_changed_bytes_are_told_apart() {
+        let a = noise(4096, 3);
+        let mut b = a.clone();
+        b.drain(2000..2500);
+        b[100] ^= 0xFF;
+
+        let hunks = diff(&a, &b);
+
+        assert_eq!(
+            hunks,
+            [
+                hunk(Op::Equal, 0, 100, 0, 100),
+                hunk(Op::Replace, 100, 1, 100, 1),
+                hunk(Op::Equal, 101, 1899, 101, 1899),
+                hunk(Op::Delete, 2000, 500, 2000, 0),
+                hunk(Op::Equal, 2500, 1596, 2000, 1596),
+            ]
+        );
+    }
+
+    #[test]
+    fn short_buffers_are_diffed_byte_by_byte() {
+        let hunks = diff(b"abcdef", b"abXdefg");
+
+        assert_eq!(
+            hunks,
+            [
+                hunk(Op::Equal, 0, 2, 0, 2),
+                hunk(Op::Replace, 2, 1, 2, 1),
+                hunk(Op::Equal, 3, 3, 3, 3),
+                hunk(Op::Insert, 6, 0, 6, 1),
+            ]
+        );
+        assert_eq!(diff(b"", b""), []);
+        assert_eq!(diff(b"ab", b""), [hunk(Op::Delete, 0, 2, 0, 0)]);
+    }
+
+    #[test]
+    fn hunks_cover_both_buffers_whatever_was_changed() {
+        for seed in 0..20 {
+            let a = noise(3000, seed);
+            let mut b = a.clone();
+            let edits = noise(12, seed + 100);
+            for edit in edits.chunks(3) {
+                let at = usize::from(edit[0]) * b.len() / 256;
+                let len = usize::from(edit[1]);
+                match edit[2] % 3 {
+                    0 => {
+                        b.splice(at..at, noise(len, seed));
+                    }
+                    1 => {
+                        b.drain(at..std::cmp::min(at + len, b.len()));
+                    }
+                    _ => {
+                        // Repeated bytes, which aren't unique blocks.
+                        b.splice(at..at, vec![0; len * 4]);
+                    }
+                }
+            }
+
+            assert_consistent(&a, &b, &diff(&a, &b));
+            assert_consistent(&b, &a, &diff(&b, &a));
+        }
+    }
+}
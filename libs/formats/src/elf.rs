@@ -0,0 +1,235 @@
+//! Executable and Linkable Format files, as used by Linux and most other Unixes. See the
+//! `elf(5)` man page.
+
+use std::convert::TryFrom;
+
+use crate::{add, entry, name_from, Error, Reader, Region, RegionKind};
+
+// Section types which don't take up any space in the file.
+const SHT_NULL: usize = 0;
+const SHT_NOBITS: usize = 8;
+
+const PT_NULL: usize = 0;
+
+// Where each field is, for the 32 and 64 bit versions of the format respectively.
+struct Layout {
+    word: usize,
+    phoff: usize,
+    shoff: usize,
+    phentsize: usize,
+    phnum: usize,
+    shentsize: usize,
+    shnum: usize,
+    shstrndx: usize,
+    sh_offset: usize,
+    sh_size: usize,
+    p_offset: usize,
+    p_filesz: usize,
+}
+
+const LAYOUT_32: Layout = Layout {
+    word: 4,
+    phoff: 0x1C,
+    shoff: 0x20,
+    phentsize: 0x2A,
+    phnum: 0x2C,
+    shentsize: 0x2E,
+    shnum: 0x30,
+    shstrndx: 0x32,
+    sh_offset: 0x10,
+    sh_size: 0x14,
+    p_offset: 0x4,
+    p_filesz: 0x10,
+};
+
+const LAYOUT_64: Layout = Layout {
+    word: 8,
+    phoff: 0x20,
+    shoff: 0x28,
+    phentsize: 0x36,
+    phnum: 0x38,
+    shentsize: 0x3A,
+    shnum: 0x3C,
+    shstrndx: 0x3E,
+    sh_offset: 0x18,
+    sh_size: 0x20,
+    p_offset: 0x8,
+    p_filesz: 0x20,
+};
+
+/// Returns the segments of an ELF file followed by its sections. Sections like `.bss`
+/// which take up no space in the file are left out.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    let ident = Reader::new(bytes, false);
+    let layout = match ident.u8(4)? {
+        1 => &LAYOUT_32,
+        2 => &LAYOUT_64,
+        _ => return Err(Error::Malformed("unknown ELF class")),
+    };
+    let r = match ident.u8(5)? {
+        1 => Reader::new(bytes, false),
+        2 => Reader::new(bytes, true),
+        _ => return Err(Error::Malformed("unknown ELF byte order")),
+    };
+    // On 32 bit targets a 64 bit offset or size may not fit, which can only point past
+    // the end of the file.
+    let word = |offset| {
+        r.uint(offset, layout.word)
+            .and_then(|n| usize::try_from(n).map_err(|_| Error::Truncated { offset }))
+    };
+
+    let mut regions = Vec::new();
+
+    let phoff = word(layout.phoff)?;
+    let phentsize = r.u16(layout.phentsize)?;
+    for i in 0..r.u16(layout.phnum)? {
+        let header = entry(phoff, i, phentsize)?;
+        let p_type = r.u32(header)?;
+        if p_type == PT_NULL {
+            continue;
+        }
+
+        regions.push(Region {
+            name: segment_name(p_type),
+            kind: RegionKind::Segment,
+            start: word(add(header, layout.p_offset)?)?,
+            len: word(add(header, layout.p_filesz)?)?,
+        });
+    }
+
+    let shoff = word(layout.shoff)?;
+    let shentsize = r.u16(layout.shentsize)?;
+    let shnum = r.u16(layout.shnum)?;
+    if shnum == 0 {
+        return Ok(regions);
+    }
+
+    let names_header = entry(shoff, r.u16(layout.shstrndx)?, shentsize)?;
+    let names = word(add(names_header, layout.sh_offset)?)?;
+    let name_at = |offset: usize| -> Result<String, Error> {
+        let start = add(names, offset)?;
+        Ok(name_from(
+            r.slice(start, bytes.len().saturating_sub(start))?,
+        ))
+    };
+
+    for i in 0..shnum {
+        let header = entry(shoff, i, shentsize)?;
+        let sh_type = r.u32(add(header, 4)?)?;
+        if sh_type == SHT_NULL || sh_type == SHT_NOBITS {
+            continue;
+        }
+
+        regions.push(Region {
+            name: name_at(r.u32(header)?)?,
+            kind: RegionKind::Section,
+            start: word(add(header, layout.sh_offset)?)?,
+            len: word(add(header, layout.sh_size)?)?,
+        });
+    }
+
+    Ok(regions)
+}
+
+fn segment_name(p_type: usize) -> String {
+    match p_type {
+        1 => "LOAD",
+        2 => "DYNAMIC",
+        3 => "INTERP",
+        4 => "NOTE",
+        6 => "PHDR",
+        7 => "TLS",
+        0x6474_E550 => "GNU_EH_FRAME",
+        0x6474_E551 => "GNU_STACK",
+        0x6474_E552 => "GNU_RELRO",
+        0x6474_E553 => "GNU_PROPERTY",
+        _ => return format!("{:#X}", p_type),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A little endian 64 bit file with one LOAD segment covering two sections: `.text`,
+    // and a `.shstrtab` holding the names.
+    fn minimal_elf_64() -> Vec<u8> {
+        let names = b"\0.text\0.shstrtab\0";
+        let text = 0x40;
+        let shstrtab = 0x48;
+        let phoff = shstrtab + names.len();
+        let shoff = phoff + 0x38;
+
+        let mut bytes = vec![0; shoff + 3 * 0x40];
+        let mut put = |offset: usize, value: u64, len: usize| {
+            bytes[offset..offset + len].copy_from_slice(&value.to_le_bytes()[..len]);
+        };
+
+        put(0, 0x464C_457F, 4);
+        put(4, 2, 1);
+        put(5, 1, 1);
+        put(0x20, phoff as u64, 8);
+        put(0x28, shoff as u64, 8);
+        put(0x36, 0x38, 2);
+        put(0x38, 1, 2);
+        put(0x3A, 0x40, 2);
+        put(0x3C, 3, 2);
+        put(0x3E, 2, 2);
+
+        put(phoff, 1, 4);
+        put(phoff + 0x8, text as u64, 8);
+        put(phoff + 0x20, (phoff - text) as u64, 8);
+
+        // Section 0 is the null section, which is left as zeroes.
+        let text_header = shoff + 0x40;
+        put(text_header, 1, 4);
+        put(text_header + 4, 1, 4);
+        put(text_header + 0x18, text as u64, 8);
+        put(text_header + 0x20, 8, 8);
+
+        let names_header = shoff + 0x80;
+        put(names_header, 7, 4);
+        put(names_header + 4, 3, 4);
+        put(names_header + 0x18, shstrtab as u64, 8);
+        put(names_header + 0x20, names.len() as u64, 8);
+
+        bytes[shstrtab..shstrtab + names.len()].copy_from_slice(names);
+
+        bytes
+    }
+
+    #[test]
+    fn parse_finds_segments_and_named_sections() {
+        let regions = parse(&minimal_elf_64()).unwrap();
+
+        let summary: Vec<_> = regions
+            .iter()
+            .map(|r| (r.name.as_str(), r.kind, r.start, r.len))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("LOAD", RegionKind::Segment, 0x40, 0x19),
+                (".text", RegionKind::Section, 0x40, 8),
+                (".shstrtab", RegionKind::Section, 0x48, 0x11),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_an_unknown_class() {
+        let mut bytes = minimal_elf_64();
+        bytes[4] = 3;
+
+        assert_eq!(parse(&bytes), Err(Error::Malformed("unknown ELF class")));
+    }
+
+    #[test]
+    fn parse_reports_offsets_too_big_to_add_up_as_truncation() {
+        let mut bytes = minimal_elf_64();
+        bytes[0x28..0x30].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+
+        assert!(matches!(parse(&bytes), Err(Error::Truncated { .. })));
+    }
+}
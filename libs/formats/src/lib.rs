@@ -0,0 +1,182 @@
+use signature::FileType;
+use std::fmt;
+
+pub mod elf;
+pub mod pe;
+pub mod png;
+pub mod riff;
+pub mod zip;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /// A part of the file the linker cares about, like `.text` or `.data`.
+    Section,
+    /// A part of the file the loader cares about, like an ELF `LOAD` segment. These
+    /// usually contain several sections.
+    Segment,
+    /// One of the pieces a container format is made of, like a PNG chunk or a file in a
+    /// ZIP archive. These don't overlap each other.
+    Chunk,
+}
+
+/// A named range of bytes within a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub kind: RegionKind,
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Region {
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Something we needed to read was past the end of the file.
+    Truncated {
+        offset: usize,
+    },
+    Malformed(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Truncated { offset } => write!(f, "truncated at offset {:#X}", offset),
+            Error::Malformed(reason) => write!(f, "malformed: {}", reason),
+        }
+    }
+}
+
+/// Returns the regions of `bytes`, in the order they appear in the file's own tables, if
+/// it is a format we know how to parse. Regions running past the end of the file are cut
+/// short, and ones starting after it are left out.
+pub fn regions(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    let mut regions = match signature::detect(bytes) {
+        Some(FileType::Elf) => elf::parse(bytes)?,
+        Some(FileType::Pe) => pe::parse(bytes)?,
+        Some(FileType::Png) => png::parse(bytes)?,
+        Some(FileType::Wav) | Some(FileType::Riff) => riff::parse(bytes)?,
+        Some(FileType::Zip) => zip::parse(bytes)?,
+        _ => Vec::new(),
+    };
+
+    regions.retain(|r| r.start < bytes.len() && r.len > 0);
+    for region in regions.iter_mut() {
+        region.len = std::cmp::min(region.len, bytes.len() - region.start);
+    }
+
+    Ok(regions)
+}
+
+// Reads fixed size integers out of a file header, in either byte order.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], big_endian: bool) -> Self {
+        Reader { bytes, big_endian }
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or(Error::Truncated { offset })
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u64, Error> {
+        let bytes = self.slice(offset, len)?;
+        let fold = |acc: u64, &b: &u8| acc << 8 | u64::from(b);
+        Ok(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, Error> {
+        self.uint(offset, 1).map(|n| n as u8)
+    }
+
+    fn u16(&self, offset: usize) -> Result<usize, Error> {
+        self.uint(offset, 2).map(|n| n as usize)
+    }
+
+    fn u32(&self, offset: usize) -> Result<usize, Error> {
+        self.uint(offset, 4).map(|n| n as usize)
+    }
+}
+
+// Adds an offset and a length read from a file, which can be anything. A sum too big to
+// hold is certainly past the end of the file.
+fn add(offset: usize, len: usize) -> Result<usize, Error> {
+    offset.checked_add(len).ok_or(Error::Truncated { offset })
+}
+
+// The offset of entry `index` in a table of entries `size` bytes long at `table`.
+fn entry(table: usize, index: usize, size: usize) -> Result<usize, Error> {
+    index
+        .checked_mul(size)
+        .ok_or(Error::Truncated { offset: table })
+        .and_then(|offset| add(table, offset))
+}
+
+// Container formats often have junk appended to them, which is worth pointing out.
+fn trailing_data(bytes: &[u8], end: usize) -> Option<Region> {
+    if end < bytes.len() {
+        Some(Region {
+            name: "trailing".to_string(),
+            kind: RegionKind::Chunk,
+            start: end,
+            len: bytes.len() - end,
+        })
+    } else {
+        None
+    }
+}
+
+// Reads a NUL terminated name, or the whole slice if there's no NUL.
+fn name_from(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_handles_both_byte_orders() {
+        let bytes = [1, 2, 3, 4];
+
+        assert_eq!(Reader::new(&bytes, false).u32(0), Ok(0x0403_0201));
+        assert_eq!(Reader::new(&bytes, true).u32(0), Ok(0x0102_0304));
+        assert_eq!(
+            Reader::new(&bytes, true).u16(3),
+            Err(Error::Truncated { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn regions_are_clipped_to_the_file() {
+        let mut bytes = pe::tests::minimal_pe(&[(b".text", 0x200, 0x1000), (b".data", 0x9000, 4)]);
+        bytes.resize(0x300, 0);
+
+        assert_eq!(
+            regions(&bytes),
+            Ok(vec![Region {
+                name: ".text".to_string(),
+                kind: RegionKind::Section,
+                start: 0x200,
+                len: 0x100,
+            }])
+        );
+    }
+}
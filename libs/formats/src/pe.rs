@@ -0,0 +1,84 @@
+//! Windows executables. See the PE Format page of the Microsoft docs.
+
+use crate::{add, entry, name_from, Error, Reader, Region, RegionKind};
+
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Returns the sections of a PE file, by where their raw data is in the file.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    let r = Reader::new(bytes, false);
+
+    let pe = r.u32(0x3C)?;
+    if r.slice(pe, 4)? != b"PE\0\0" {
+        return Err(Error::Malformed("missing PE signature"));
+    }
+
+    let coff = add(pe, 4)?;
+    let section_count = r.u16(add(coff, 2)?)?;
+    let optional_header_size = r.u16(add(coff, 16)?)?;
+    let section_table = add(add(coff, 20)?, optional_header_size)?;
+
+    let mut regions = Vec::with_capacity(section_count);
+    for i in 0..section_count {
+        let header = entry(section_table, i, SECTION_HEADER_SIZE)?;
+        let header_bytes = r.slice(header, SECTION_HEADER_SIZE)?;
+
+        regions.push(Region {
+            name: name_from(&header_bytes[..8]),
+            kind: RegionKind::Section,
+            start: r.u32(header + 20)?,
+            len: r.u32(header + 16)?,
+        });
+    }
+
+    Ok(regions)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Builds the headers of a PE file with the given sections, as (name, offset, size).
+    pub fn minimal_pe(sections: &[(&[u8], u32, u32)]) -> Vec<u8> {
+        let pe = 0x40;
+        let optional_header_size = 0xE0;
+        let section_table = pe + 4 + 20 + optional_header_size;
+
+        let mut bytes = vec![0; section_table + sections.len() * SECTION_HEADER_SIZE];
+        bytes[..2].copy_from_slice(b"MZ");
+        bytes[0x3C] = pe as u8;
+        bytes[pe..pe + 4].copy_from_slice(b"PE\0\0");
+        bytes[pe + 6] = sections.len() as u8;
+        bytes[pe + 20] = optional_header_size as u8;
+
+        for (i, &(name, offset, size)) in sections.iter().enumerate() {
+            let header = section_table + i * SECTION_HEADER_SIZE;
+            bytes[header..header + name.len()].copy_from_slice(name);
+            bytes[header + 16..header + 20].copy_from_slice(&size.to_le_bytes());
+            bytes[header + 20..header + 24].copy_from_slice(&offset.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn parse_reads_the_section_table() {
+        let bytes = minimal_pe(&[(b".text", 0x400, 0x200), (b".rdata\0\0", 0x600, 0x80)]);
+
+        let regions = parse(&bytes).unwrap();
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].name, ".text");
+        assert_eq!((regions[0].start, regions[0].len), (0x400, 0x200));
+        assert_eq!(regions[1].name, ".rdata");
+        assert_eq!((regions[1].start, regions[1].len), (0x600, 0x80));
+    }
+
+    #[test]
+    fn parse_reports_a_truncated_section_table() {
+        let mut bytes = minimal_pe(&[(b".text", 0x400, 0x200)]);
+        bytes.truncate(bytes.len() - 1);
+
+        assert!(matches!(parse(&bytes), Err(Error::Truncated { .. })));
+    }
+}
@@ -0,0 +1,97 @@
+//! Portable Network Graphics images. See section 5 of the PNG specification.
+
+use crate::{add, name_from, trailing_data, Error, Reader, Region, RegionKind};
+
+const SIGNATURE_LEN: usize = 8;
+
+/// Returns the signature followed by every chunk up to and including `IEND`, and anything
+/// after that. A chunk that runs past the end of the file ends the list early.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    let r = Reader::new(bytes, true);
+    let mut regions = vec![Region {
+        name: "signature".to_string(),
+        kind: RegionKind::Chunk,
+        start: 0,
+        len: SIGNATURE_LEN,
+    }];
+
+    let mut offset = SIGNATURE_LEN;
+    while offset < bytes.len() {
+        let (data_len, chunk_type) = match (r.u32(offset), r.slice(offset + 4, 4)) {
+            (Ok(data_len), Ok(chunk_type)) => (data_len, chunk_type),
+            _ => break,
+        };
+
+        // Length, type, data, then CRC. On 32 bit targets a huge length can't be added up,
+        // which runs past the end of the file all the same.
+        let end = match add(offset, data_len).and_then(|end| add(end, 4 + 4 + 4)) {
+            Ok(end) => end,
+            Err(_) => break,
+        };
+        regions.push(Region {
+            name: name_from(chunk_type),
+            kind: RegionKind::Chunk,
+            start: offset,
+            len: end - offset,
+        });
+        offset = end;
+
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+
+    regions.extend(trailing_data(bytes, offset));
+
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = (data.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(chunk_type);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&[0; 4]);
+        bytes
+    }
+
+    #[test]
+    fn parse_lists_chunks_and_trailing_data() {
+        let mut bytes = b"\x89PNG\r\n\x1A\n".to_vec();
+        bytes.extend(chunk(b"IHDR", &[0; 13]));
+        bytes.extend(chunk(b"IDAT", &[1, 2, 3]));
+        bytes.extend(chunk(b"IEND", &[]));
+        bytes.extend_from_slice(b"hidden");
+
+        let summary: Vec<_> = parse(&bytes)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.start, r.len))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("signature".to_string(), 0, 8),
+                ("IHDR".to_string(), 8, 25),
+                ("IDAT".to_string(), 33, 15),
+                ("IEND".to_string(), 48, 12),
+                ("trailing".to_string(), 60, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_keeps_the_chunks_before_a_huge_length() {
+        let mut bytes = b"\x89PNG\r\n\x1A\n".to_vec();
+        bytes.extend(chunk(b"IHDR", &[0; 13]));
+        bytes.extend_from_slice(b"\xFF\xFF\xFF\xF0IDAT");
+
+        let regions = parse(&bytes).unwrap();
+        assert_eq!(regions[1].name, "IHDR");
+        assert_eq!(regions[1].len, 25);
+    }
+}
@@ -0,0 +1,99 @@
+//! Resource Interchange File Format containers, like WAV, AVI and WebP.
+
+use crate::{add, name_from, trailing_data, Error, Reader, Region, RegionKind};
+
+const HEADER_LEN: usize = 12;
+
+/// Returns the RIFF header followed by each top level chunk, and anything after the end
+/// of the RIFF chunk. `LIST` chunks are named along with their list type, like `LIST INFO`,
+/// but what they contain isn't listed separately.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    let r = Reader::new(bytes, false);
+
+    let form_type = name_from(r.slice(8, 4)?);
+    // A length too big to add up on 32 bit targets runs to the end of the file anyway.
+    let end = r.u32(4)?.saturating_add(8);
+    let mut regions = vec![Region {
+        name: format!("RIFF {}", form_type),
+        kind: RegionKind::Chunk,
+        start: 0,
+        len: HEADER_LEN,
+    }];
+
+    let mut offset = HEADER_LEN;
+    while offset < end {
+        let (id, data_len) = match (r.slice(offset, 4), r.u32(offset + 4)) {
+            (Ok(id), Ok(data_len)) => (id, data_len),
+            _ => break,
+        };
+
+        let name = if id == b"LIST" {
+            match r.slice(offset + 8, 4) {
+                Ok(list_type) => format!("LIST {}", name_from(list_type)),
+                Err(_) => "LIST".to_string(),
+            }
+        } else {
+            name_from(id)
+        };
+
+        // Chunks are padded to an even length. On 32 bit targets a huge length can't be
+        // added up.
+        let chunk_end = match add(offset, data_len).and_then(|end| add(end, 8 + (data_len & 1))) {
+            Ok(end) => end,
+            Err(_) => break,
+        };
+        regions.push(Region {
+            name,
+            kind: RegionKind::Chunk,
+            start: offset,
+            len: chunk_end - offset,
+        });
+        offset = chunk_end;
+    }
+
+    regions.extend(trailing_data(bytes, offset));
+
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pads_odd_chunks_and_names_lists() {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend_from_slice(b"fmt \x03\0\0\0abc\0");
+        bytes.extend_from_slice(b"LIST\x04\0\0\0INFO");
+        bytes.extend_from_slice(b"data\x02\0\0\0xy");
+        let riff_len = (bytes.len() - 8) as u32;
+        bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+        let summary: Vec<_> = parse(&bytes)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.name, r.start, r.len))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("RIFF WAVE".to_string(), 0, 12),
+                ("fmt ".to_string(), 12, 12),
+                ("LIST INFO".to_string(), 24, 12),
+                ("data".to_string(), 36, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_keeps_the_chunks_before_a_huge_length() {
+        let mut bytes = b"RIFF\xFF\xFF\xFF\xFFWAVE".to_vec();
+        bytes.extend_from_slice(b"fmt \x02\0\0\0ab");
+        bytes.extend_from_slice(b"data\xF0\xFF\xFF\xFF");
+
+        let regions = parse(&bytes).unwrap();
+        assert_eq!(regions[1].name, "fmt ");
+        assert_eq!(regions[1].len, 10);
+    }
+}
@@ -0,0 +1,204 @@
+//! ZIP archives, and the many formats built on them. See PKWARE's APPNOTE.TXT.
+
+use crate::{add, Error, Reader, Region, RegionKind};
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_RECORD_LEN: usize = 22;
+
+// The end of central directory record can be followed by a comment of up to this length.
+const MAX_COMMENT_LEN: usize = 0xFFFF;
+
+// Set in the flags when the sizes come after the data instead of in the local header.
+const DATA_DESCRIPTOR_FLAG: usize = 1 << 3;
+
+/// Returns each file in the archive, from its local header to the end of its data, then
+/// the central directory and the end of central directory record.
+///
+/// The central directory is the authoritative list of files, so it is used when it can be
+/// found. Otherwise the local headers are followed from the start of the file for as long
+/// as their sizes are known.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Region>, Error> {
+    match find_end_record(bytes) {
+        Some(end_record) => parse_central_directory(bytes, end_record),
+        None => Ok(parse_local_headers(bytes)),
+    }
+}
+
+fn find_end_record(bytes: &[u8]) -> Option<usize> {
+    let last = bytes.len().checked_sub(END_RECORD_LEN)?;
+    let first = last.saturating_sub(MAX_COMMENT_LEN);
+
+    (first..=last)
+        .rev()
+        .find(|&i| bytes[i..].starts_with(b"PK\x05\x06"))
+}
+
+fn parse_central_directory(bytes: &[u8], end_record: usize) -> Result<Vec<Region>, Error> {
+    let r = Reader::new(bytes, false);
+
+    let entry_count = r.u16(end_record + 10)?;
+    let directory_len = r.u32(end_record + 12)?;
+    let directory = r.u32(end_record + 16)?;
+
+    let mut regions = Vec::with_capacity(entry_count + 2);
+    let mut header = directory;
+    for _ in 0..entry_count {
+        if r.slice(header, 4)? != b"PK\x01\x02" {
+            return Err(Error::Malformed("bad central directory header"));
+        }
+
+        let compressed_len = r.u32(header + 20)?;
+        let name_len = r.u16(header + 28)?;
+        let extra_len = r.u16(header + 30)?;
+        let comment_len = r.u16(header + 32)?;
+        let local_header = r.u32(header + 42)?;
+        let name = r.slice(header + CENTRAL_HEADER_LEN, name_len)?;
+
+        // The local header's extra field can differ from the central directory's.
+        let local_len = local_header_len(&r, local_header)?;
+        let end = add(local_header + local_len, compressed_len)?;
+        regions.push(Region {
+            name: String::from_utf8_lossy(name).into_owned(),
+            kind: RegionKind::Chunk,
+            start: local_header,
+            len: end - local_header,
+        });
+
+        header += CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+    }
+
+    regions.push(Region {
+        name: "central directory".to_string(),
+        kind: RegionKind::Chunk,
+        start: directory,
+        len: directory_len,
+    });
+    regions.push(Region {
+        name: "end of central directory".to_string(),
+        kind: RegionKind::Chunk,
+        start: end_record,
+        len: END_RECORD_LEN + r.u16(end_record + 20)?,
+    });
+
+    Ok(regions)
+}
+
+fn local_header_len(r: &Reader, offset: usize) -> Result<usize, Error> {
+    if r.slice(offset, 4)? != b"PK\x03\x04" {
+        return Err(Error::Malformed("bad local file header"));
+    }
+
+    Ok(LOCAL_HEADER_LEN + r.u16(offset + 26)? + r.u16(offset + 28)?)
+}
+
+fn parse_local_headers(bytes: &[u8]) -> Vec<Region> {
+    let r = Reader::new(bytes, false);
+    let mut regions = Vec::new();
+
+    let mut offset = 0;
+    while let Ok(header_len) = local_header_len(&r, offset) {
+        // `local_header_len` has already checked the fixed size part of the header is there.
+        let flags = r.u16(offset + 6).unwrap_or(0);
+        let compressed_len = r.u32(offset + 18).unwrap_or(0);
+        let name_len = r.u16(offset + 26).unwrap_or(0);
+        let name = r
+            .slice(offset + LOCAL_HEADER_LEN, name_len)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .unwrap_or_default();
+
+        let end = match add(offset + header_len, compressed_len) {
+            Ok(end) => end,
+            Err(_) => break,
+        };
+        regions.push(Region {
+            name,
+            kind: RegionKind::Chunk,
+            start: offset,
+            len: end - offset,
+        });
+
+        // Without the central directory there's no way to tell where the data ends.
+        if flags & DATA_DESCRIPTOR_FLAG != 0 && compressed_len == 0 {
+            break;
+        }
+        offset = end;
+    }
+
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An archive holding two stored files, with the central directory if `finished`.
+    fn archive(finished: bool) -> Vec<u8> {
+        let files: [(&[u8], &[u8]); 2] = [(b"a.txt", b"hello"), (b"dir/b", b"!")];
+
+        let mut bytes = Vec::new();
+        let mut offsets = Vec::new();
+        for &(name, data) in files.iter() {
+            offsets.push(bytes.len() as u32);
+            let mut header = vec![0; LOCAL_HEADER_LEN];
+            header[..4].copy_from_slice(b"PK\x03\x04");
+            header[18..22].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            header[26] = name.len() as u8;
+            bytes.extend(header);
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(data);
+        }
+        if !finished {
+            return bytes;
+        }
+
+        let directory = bytes.len() as u32;
+        for (&(name, data), &offset) in files.iter().zip(offsets.iter()) {
+            let mut header = vec![0; CENTRAL_HEADER_LEN];
+            header[..4].copy_from_slice(b"PK\x01\x02");
+            header[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            header[28] = name.len() as u8;
+            header[42..46].copy_from_slice(&offset.to_le_bytes());
+            bytes.extend(header);
+            bytes.extend_from_slice(name);
+        }
+        let directory_len = bytes.len() as u32 - directory;
+
+        let mut end_record = vec![0; END_RECORD_LEN];
+        end_record[..4].copy_from_slice(b"PK\x05\x06");
+        end_record[10] = files.len() as u8;
+        end_record[12..16].copy_from_slice(&directory_len.to_le_bytes());
+        end_record[16..20].copy_from_slice(&directory.to_le_bytes());
+        bytes.extend(end_record);
+
+        bytes
+    }
+
+    fn summary(regions: Vec<Region>) -> Vec<(String, usize, usize)> {
+        regions
+            .into_iter()
+            .map(|r| (r.name, r.start, r.len))
+            .collect()
+    }
+
+    #[test]
+    fn parse_uses_the_central_directory() {
+        assert_eq!(
+            summary(parse(&archive(true)).unwrap()),
+            vec![
+                ("a.txt".to_string(), 0, 40),
+                ("dir/b".to_string(), 40, 36),
+                ("central directory".to_string(), 76, 102),
+                ("end of central directory".to_string(), 178, 22),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_falls_back_to_the_local_headers() {
+        assert_eq!(
+            summary(parse(&archive(false)).unwrap()),
+            vec![("a.txt".to_string(), 0, 40), ("dir/b".to_string(), 40, 36)]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Raw DEFLATE streams, as described in RFC 1951. This follows the structure of Mark
+//! Adler's `puff.c`, which favours being easy to check over being fast.
+
+use crate::{Error, MAX_OUTPUT};
+
+const MAX_BITS: usize = 15;
+
+// The base lengths and extra bits of length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+// The base distances and extra bits of distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// The order the code length code lengths are sent in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    // DEFLATE packs bits starting from the least significant bit of each byte.
+    fn bits(&mut self, n: u32) -> Result<u32, Error> {
+        while self.count < n {
+            let byte = *self.bytes.get(self.position).ok_or(Error::Truncated)?;
+            self.position += 1;
+            self.buffer |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    // Stored blocks start on a byte boundary.
+    fn skip_to_byte(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code, as the number of codes of each length and the symbols in
+// code order.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        // Reject codes with more codes of some length than there is room for. Incomplete
+        // codes are allowed, since a single distance code is.
+        let mut left: i32 = 1;
+        for &count in counts.iter().skip(1) {
+            left = left * 2 - i32::from(count);
+            if left < 0 {
+                return Err(Error::InvalidCode);
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, Error> {
+        // The first code of each length, and the index of its symbol.
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(Error::InvalidCode)
+    }
+}
+
+/// Decompresses a raw DEFLATE stream from the start of `bytes`. Returns the decompressed
+/// bytes and how many bytes of `bytes` the stream took up, so a trailer after it can be
+/// found.
+pub fn inflate(bytes: &[u8]) -> Result<(Vec<u8>, usize), Error> {
+    let mut reader = BitReader {
+        bytes,
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut output = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored(&mut reader, &mut output)?,
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(&mut reader, &mut output, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(&mut reader)?;
+                codes(&mut reader, &mut output, &lengths, &distances)?;
+            }
+            _ => return Err(Error::InvalidBlockType),
+        }
+
+        if last {
+            return Ok((output, reader.position));
+        }
+    }
+}
+
+fn stored(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), Error> {
+    reader.skip_to_byte();
+
+    let header = reader
+        .bytes
+        .get(reader.position..reader.position + 4)
+        .ok_or(Error::Truncated)?;
+    let len = usize::from(header[0]) | usize::from(header[1]) << 8;
+    let complement = usize::from(header[2]) | usize::from(header[3]) << 8;
+    if len != !complement & 0xFFFF {
+        return Err(Error::StoredLengthMismatch);
+    }
+    reader.position += 4;
+
+    let data = reader
+        .bytes
+        .get(reader.position..reader.position + len)
+        .ok_or(Error::Truncated)?;
+    if output.len() + len > MAX_OUTPUT {
+        return Err(Error::TooLarge);
+    }
+    output.extend_from_slice(data);
+    reader.position += len;
+
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), Error> {
+    let mut lengths = [0; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), Error> {
+    let length_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if length_count > 286 || distance_count > 30 {
+        return Err(Error::InvalidCode);
+    }
+
+    let mut code_lengths = [0; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // The literal/length and distance code lengths are sent as one run, so repeats can
+    // cross from one to the other.
+    let mut lengths = vec![0; length_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => match i.checked_sub(1) {
+                Some(previous) => (lengths[previous], 3 + reader.bits(2)? as usize),
+                None => return Err(Error::InvalidCode),
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+
+        if i + repeat > lengths.len() {
+            return Err(Error::InvalidCode);
+        }
+        for length_slot in &mut lengths[i..i + repeat] {
+            *length_slot = length;
+        }
+        i += repeat;
+    }
+
+    // Without an end of block code the block could never finish.
+    if lengths[256] == 0 {
+        return Err(Error::InvalidCode);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..length_count])?,
+        Huffman::new(&lengths[length_count..])?,
+    ))
+}
+
+fn codes(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = lengths.decode(reader)? as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+        } else if symbol == 256 {
+            return Ok(());
+        } else {
+            let i = symbol - 257;
+            if i >= LENGTH_BASE.len() {
+                return Err(Error::InvalidCode);
+            }
+            let len = LENGTH_BASE[i] as usize + reader.bits(u32::from(LENGTH_EXTRA[i]))? as usize;
+
+            let i = distances.decode(reader)? as usize;
+            if i >= DISTANCE_BASE.len() {
+                return Err(Error::InvalidCode);
+            }
+            let distance =
+                DISTANCE_BASE[i] as usize + reader.bits(u32::from(DISTANCE_EXTRA[i]))? as usize;
+            if distance > output.len() {
+                return Err(Error::DistanceTooFar);
+            }
+
+            // The copy can overlap what it is copying, so go a byte at a time.
+            let start = output.len() - distance;
+            for j in 0..len {
+                let byte = output[start + j];
+                output.push(byte);
+            }
+        }
+
+        if output.len() > MAX_OUTPUT {
+            return Err(Error::TooLarge);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inflate_handles_each_block_type() {
+        let stored = [1, 6, 0, 249, 255, b's', b't', b'o', b'r', b'e', b'd'];
+        assert_eq!(inflate(&stored), Ok((b"stored".to_vec(), stored.len())));
+
+        let fixed = [75, 76, 74, 78, 36, 29, 1, 0, 0xAA];
+        assert_eq!(inflate(&fixed), Ok((b"abc".repeat(18), 8)));
+
+        let dynamic = [
+            181, 141, 217, 21, 64, 48, 20, 5, 91, 185, 26, 112, 236, 75, 23, 62, 52, 16, 4, 177,
+            61, 66, 130, 84, 239, 53, 225, 123, 102, 206, 212, 163, 196, 97, 84, 59, 163, 209, 116,
+            111, 232, 233, 193, 100, 214, 253, 4, 89, 169, 113, 49, 94, 132, 123, 209, 209, 224,
+            163, 18, 236, 173, 47, 26, 150, 110, 117, 141, 232, 149, 149, 140, 156, 220, 176, 168,
+            195, 144, 230, 118, 56, 61, 4, 97, 20, 39, 105, 150, 23, 37, 234, 191, 7, 31,
+        ];
+        let text = b"The quick brown fox jumps over the lazy dog. \
+                     Pack my box with five dozen liquor jugs! 0123456789 "
+            .repeat(2);
+        assert_eq!(inflate(&dynamic), Ok((text, dynamic.len())));
+    }
+
+    #[test]
+    fn inflate_rejects_bad_streams() {
+        assert_eq!(inflate(&[0b111]), Err(Error::InvalidBlockType));
+        assert_eq!(inflate(&[1, 6, 0, 0, 0]), Err(Error::StoredLengthMismatch));
+        assert_eq!(inflate(&[75, 76, 74]), Err(Error::Truncated));
+        // A fixed block whose first code is a length, with nothing to copy yet.
+        assert_eq!(inflate(&[0x03, 0x02]), Err(Error::DistanceTooFar));
+    }
+}
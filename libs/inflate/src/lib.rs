@@ -0,0 +1,261 @@
+//! Decompresses DEFLATE streams, on their own or wrapped in zlib or gzip headers.
+
+use std::fmt;
+
+mod deflate;
+pub use deflate::inflate;
+
+/// Streams that would decompress to more than this are given up on, so a small file can't
+/// fill up memory.
+pub const MAX_OUTPUT: usize = 64 * 1024 * 1024;
+
+// Finding more than this many streams probably means most of them are false positives.
+const MAX_STREAMS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The stream ended before its last block or its trailer did.
+    Truncated,
+    InvalidBlockType,
+    StoredLengthMismatch,
+    /// A Huffman code, or a symbol decoded with one, was impossible.
+    InvalidCode,
+    /// A back reference pointed to before the start of the output.
+    DistanceTooFar,
+    TooLarge,
+    BadHeader(&'static str),
+    ChecksumMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Truncated => write!(f, "stream is truncated"),
+            Error::InvalidBlockType => write!(f, "invalid block type"),
+            Error::StoredLengthMismatch => write!(f, "stored block length mismatch"),
+            Error::InvalidCode => write!(f, "invalid Huffman code"),
+            Error::DistanceTooFar => write!(f, "back reference before the start"),
+            Error::TooLarge => write!(f, "more than {} bytes of output", MAX_OUTPUT),
+            Error::BadHeader(reason) => write!(f, "bad header: {}", reason),
+            Error::ChecksumMismatch => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// DEFLATE data with no header, like the files in a ZIP archive.
+    Deflate,
+    /// RFC 1950, as used in PNG and PDF.
+    Zlib,
+    /// RFC 1952, as in `.gz` files.
+    Gzip,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Deflate, Format::Zlib, Format::Gzip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Deflate => "deflate",
+            Format::Zlib => "zlib",
+            Format::Gzip => "gzip",
+        }
+    }
+}
+
+/// Where a stream that looks compressed starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stream {
+    pub offset: usize,
+    pub format: Format,
+}
+
+/// Returns the offsets of what look like zlib and gzip headers. This only checks the
+/// headers, so some of them may not decompress.
+pub fn find_streams(bytes: &[u8]) -> Vec<Stream> {
+    let mut streams = Vec::new();
+
+    for (offset, window) in bytes.windows(3).enumerate() {
+        let format = if window == [0x1F, 0x8B, 8] {
+            Format::Gzip
+        } else if is_zlib_header(window[0], window[1]) {
+            Format::Zlib
+        } else {
+            continue;
+        };
+
+        streams.push(Stream { offset, format });
+        if streams.len() >= MAX_STREAMS {
+            break;
+        }
+    }
+
+    streams
+}
+
+// Only the headers zlib itself writes, with a 32K window and one of the four compression
+// level hints, are looked for. Anything that passes the header check would find far too
+// many streams in text and random data.
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf == 0x78 && [0x01, 0x5E, 0x9C, 0xDA].contains(&flg)
+}
+
+/// Decompresses the stream of the given format starting at `offset`.
+pub fn decompress(bytes: &[u8], offset: usize, format: Format) -> Result<Vec<u8>, Error> {
+    let bytes = bytes.get(offset..).ok_or(Error::Truncated)?;
+    match format {
+        Format::Deflate => inflate(bytes).map(|(output, _)| output),
+        Format::Zlib => zlib(bytes),
+        Format::Gzip => gzip(bytes),
+    }
+}
+
+fn zlib(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    if bytes.len() < 2 {
+        return Err(Error::Truncated);
+    }
+    let (cmf, flg) = (bytes[0], bytes[1]);
+    if cmf & 0x0F != 8 || cmf >> 4 > 7 {
+        return Err(Error::BadHeader("not DEFLATE"));
+    }
+    if (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(Error::BadHeader("bad check bits"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(Error::BadHeader("needs a preset dictionary"));
+    }
+
+    let (output, len) = inflate(&bytes[2..])?;
+
+    let trailer = bytes.get(2 + len..2 + len + 4).ok_or(Error::Truncated)?;
+    let expected = trailer.iter().fold(0, |acc, &b| acc << 8 | u32::from(b));
+    if adler32(&output) != expected {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    Ok(output)
+}
+
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    if bytes.len() < 10 {
+        return Err(Error::Truncated);
+    }
+    if bytes[..3] != [0x1F, 0x8B, 8] {
+        return Err(Error::BadHeader("not gzip"));
+    }
+    let flags = bytes[3];
+
+    let mut position = 10;
+    if flags & FEXTRA != 0 {
+        let len = bytes.get(position..position + 2).ok_or(Error::Truncated)?;
+        position += 2 + (usize::from(len[0]) | usize::from(len[1]) << 8);
+    }
+    for &flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = bytes.get(position..).ok_or(Error::Truncated)?;
+            let nul = rest.iter().position(|&b| b == 0).ok_or(Error::Truncated)?;
+            position += nul + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        position += 2;
+    }
+
+    let (output, len) = inflate(bytes.get(position..).ok_or(Error::Truncated)?)?;
+    position += len;
+
+    let trailer = bytes.get(position..position + 8).ok_or(Error::Truncated)?;
+    let little_endian = |b: &[u8]| b.iter().rev().fold(0, |acc, &b| acc << 8 | u32::from(b));
+    if crc32(&output) != little_endian(&trailer[..4])
+        || output.len() as u32 != little_endian(&trailer[4..])
+    {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    Ok(output)
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` could overflow.
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = crc >> 1 ^ 0xEDB8_8320 & mask;
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_the_usual_check_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn decompress_unwraps_zlib_and_gzip() {
+        let zlib = [
+            120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177,
+        ];
+        let gzip = [
+            31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 243, 200, 84, 228, 2, 0, 156, 163, 168, 84, 4, 0, 0, 0,
+        ];
+        let mut file = b"junk".to_vec();
+        file.extend_from_slice(&zlib);
+        file.extend_from_slice(&gzip);
+
+        assert_eq!(
+            find_streams(&file),
+            vec![
+                Stream {
+                    offset: 4,
+                    format: Format::Zlib
+                },
+                Stream {
+                    offset: 4 + zlib.len(),
+                    format: Format::Gzip
+                },
+            ]
+        );
+        assert_eq!(
+            decompress(&file, 4, Format::Zlib),
+            Ok(b"hello hello hello hello".to_vec())
+        );
+        assert_eq!(
+            decompress(&file, 4 + zlib.len(), Format::Gzip),
+            Ok(b"Hi!\n".to_vec())
+        );
+
+        let last = file.len() - 1;
+        file[last] ^= 1;
+        assert_eq!(
+            decompress(&file, 4 + zlib.len(), Format::Gzip),
+            Err(Error::ChecksumMismatch)
+        );
+    }
+}
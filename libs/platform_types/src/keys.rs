@@ -0,0 +1,179 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::Button;
+
+/// Something a key can be bound to: either one of the buttons, or an action that has no
+/// button of its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    A,
+    B,
+    Select,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+    NextMode,
+    PreviousMode,
+    GoToOffset,
+    Search,
+    Template,
+    SetBookmark,
+    JumpToBookmark,
+    /// Saves the viewing session as a file. Frontends do this themselves, since only they
+    /// can write files.
+    Export,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::A,
+        Action::B,
+        Action::Select,
+        Action::Start,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::NextMode,
+        Action::PreviousMode,
+        Action::GoToOffset,
+        Action::Search,
+        Action::Template,
+        Action::SetBookmark,
+        Action::JumpToBookmark,
+        Action::Export,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::A => "A",
+            Action::B => "B",
+            Action::Select => "Select",
+            Action::Start => "Start",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::NextMode => "Next mode",
+            Action::PreviousMode => "Previous mode",
+            Action::GoToOffset => "Go to offset",
+            Action::Search => "Search",
+            Action::Template => "Template",
+            Action::SetBookmark => "Set bookmark",
+            Action::JumpToBookmark => "Jump to bookmark",
+            Action::Export => "Export session",
+        }
+    }
+
+    /// The button this action presses, if it is one of the buttons.
+    pub fn button(self) -> Option<Button::Ty> {
+        Some(match self {
+            Action::A => Button::A,
+            Action::B => Button::B,
+            Action::Select => Button::Select,
+            Action::Start => Button::Start,
+            Action::Up => Button::Up,
+            Action::Down => Button::Down,
+            Action::Left => Button::Left,
+            Action::Right => Button::Right,
+            _ => return None,
+        })
+    }
+}
+
+/// Which action each key does. Keys are named the way the DOM's `KeyboardEvent.code` names
+/// them, like `KeyZ` or `ArrowUp`, so they go by where the key is rather than what is
+/// printed on it. Each key does at most one action, but an action can have many keys.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    bindings: Vec<(String, Action)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = [
+            ("Enter", Action::Start),
+            ("ShiftRight", Action::Select),
+            // Terminals don't report Shift on its own.
+            ("Tab", Action::Select),
+            ("ArrowUp", Action::Up),
+            ("ArrowDown", Action::Down),
+            ("ArrowLeft", Action::Left),
+            ("ArrowRight", Action::Right),
+            ("KeyZ", Action::A),
+            ("KeyX", Action::B),
+            ("KeyN", Action::NextMode),
+            ("KeyP", Action::PreviousMode),
+            ("KeyG", Action::GoToOffset),
+            ("Slash", Action::Search),
+            ("KeyT", Action::Template),
+            ("KeyM", Action::SetBookmark),
+            ("Quote", Action::JumpToBookmark),
+            ("KeyE", Action::Export),
+        ];
+        KeyBindings {
+            bindings: bindings
+                .iter()
+                .map(|&(key, action)| (key.to_string(), action))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn lookup(&self, key: &str) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound == key)
+            .map(|&(_, action)| action)
+    }
+
+    /// The keys bound to `action`, in the order they were bound.
+    pub fn keys_for(&self, action: Action) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == action)
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Binds `key` to `action`, taking it away from whatever it did before.
+    pub fn bind(&mut self, key: &str, action: Action) {
+        self.unbind_key(key);
+        self.bindings.push((key.to_string(), action));
+    }
+
+    pub fn unbind_key(&mut self, key: &str) {
+        self.bindings.retain(|(bound, _)| bound != key);
+    }
+
+    /// Takes all of its keys away from `action`.
+    pub fn unbind_action(&mut self, action: Action) {
+        self.bindings.retain(|&(_, bound)| bound != action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_key_takes_it_away_from_its_old_action() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(bindings.lookup("KeyZ"), Some(Action::A));
+        assert_eq!(bindings.lookup("KeyW"), None);
+
+        bindings.bind("KeyZ", Action::Search);
+        assert_eq!(bindings.lookup("KeyZ"), Some(Action::Search));
+        assert_eq!(bindings.keys_for(Action::A).count(), 0);
+        assert_eq!(
+            bindings.keys_for(Action::Search).collect::<Vec<_>>(),
+            ["Slash", "KeyZ"]
+        );
+
+        bindings.unbind_action(Action::Search);
+        assert_eq!(bindings.lookup("KeyZ"), None);
+        assert_eq!(bindings.lookup("Slash"), None);
+    }
+}
@@ -1,6 +1,9 @@
 #[macro_use]
 extern crate bitflags;
 
+mod keys;
+pub use keys::{Action, KeyBindings};
+
 #[macro_export]
 macro_rules! w {
     () => {
@@ -21,7 +24,8 @@ macro_rules! h {
     };
 }
 
-//in pixels
+// The default size of the screen, in pixels. Frontends can pick another size at runtime
+// with `State::resize`.
 pub const SCREEN_WIDTH: usize = w!();
 pub const SCREEN_HEIGHT: usize = h!();
 pub const SCREEN_LENGTH: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
@@ -30,6 +34,7 @@ pub const SCREEN_LENGTH: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
 pub struct Input {
     pub gamepad: Button::Ty,
     pub previous_gamepad: Button::Ty,
+    pub pointer: Pointer,
 }
 
 impl Input {
@@ -37,6 +42,7 @@ impl Input {
         Input {
             gamepad: Button::Ty::empty(),
             previous_gamepad: Button::Ty::empty(),
+            pointer: Pointer::default(),
         }
     }
 
@@ -49,6 +55,27 @@ impl Input {
     }
 }
 
+/// The mouse, or whatever else is pointing at the screen.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Pointer {
+    /// Where it is in framebuffer pixels, or `None` if it is off the screen.
+    pub position: Option<(usize, usize)>,
+    pub buttons: PointerButton::Ty,
+    pub previous_buttons: PointerButton::Ty,
+    /// How many lines the wheel has scrolled since the last frame. Positive is down.
+    pub wheel: i32,
+}
+
+impl Pointer {
+    pub fn pressed_this_frame(&self, buttons: PointerButton::Ty) -> bool {
+        !self.previous_buttons.contains(buttons) && self.buttons.contains(buttons)
+    }
+
+    pub fn released_this_frame(&self, buttons: PointerButton::Ty) -> bool {
+        self.previous_buttons.contains(buttons) && !self.buttons.contains(buttons)
+    }
+}
+
 //TODO more meaningful names for these?
 #[derive(Clone, Copy, Debug)]
 pub enum SFX {
@@ -103,6 +130,32 @@ pub mod Button {
     }
 }
 
+// These match the bits of the DOM's `MouseEvent.buttons`.
+#[allow(non_snake_case)]
+pub mod PointerButton {
+    bitflags! {
+        #[derive(Default)]
+        pub flags Ty: u8 {
+            const Primary    = 1 << 0,
+            const Secondary  = 1 << 1,
+            const Middle     = 1 << 2
+        }
+    }
+}
+
+/// The keys that move around in or finish off a line of text, rather than typing into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKey {
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Enter,
+    Escape,
+}
+
 pub type Logger = Option<fn(&str) -> ()>;
 
 pub type StateParams = ([u8; 16], Logger, Logger);
@@ -117,4 +170,58 @@ pub trait State {
     fn get_frame_buffer(&self) -> &[u32];
 
     fn update_bytes(&mut self, bytes: Vec<u8>);
+
+    /// Loads a second file to compare the bytes with. Empty bytes stop the comparison.
+    fn update_comparison_bytes(&mut self, bytes: Vec<u8>);
+
+    /// Changes the size of the frame buffer, in pixels. Everything is redrawn at the new size.
+    fn resize(&mut self, width: usize, height: usize);
+
+    /// Moves the pointer to `position`, in framebuffer pixels, or off the screen if it is
+    /// `None`.
+    fn pointer_moved(&mut self, position: Option<(usize, usize)>);
+
+    fn pointer_pressed(&mut self, button: PointerButton::Ty);
+
+    fn pointer_released(&mut self, button: PointerButton::Ty);
+
+    /// Scrolls the wheel by `lines`. Positive is down.
+    fn wheel(&mut self, lines: i32);
+
+    /// Whether a text field is open. While it is, frontends should pass the keyboard to
+    /// `text_input` and `edit_key` instead of pressing buttons with it.
+    fn wants_text_input(&self) -> bool;
+
+    /// Types `ch` into the open text field, if there is one.
+    fn text_input(&mut self, ch: char);
+
+    fn edit_key(&mut self, key: EditKey);
+
+    /// What the keys do. Frontends look each key up here, then press the button it is bound
+    /// to or pass its action to `act`.
+    fn key_bindings(&self) -> &KeyBindings;
+
+    /// Replaces the key bindings, for example with ones saved by an earlier run.
+    fn set_key_bindings(&mut self, bindings: KeyBindings);
+
+    /// Does an action that isn't a button, like switching to the next mode.
+    fn act(&mut self, action: Action);
+
+    /// Whether the key settings are waiting for a key to bind. While they are, frontends
+    /// should pass the name of the next key pressed to `bind_key` instead of looking it up.
+    fn binding_key(&self) -> bool;
+
+    fn bind_key(&mut self, key: &str);
+
+    /// The viewing session as JSON, to be saved and restored later against the same file.
+    fn session(&self) -> String;
+
+    /// Goes back to the session in `json`, or says why it can't.
+    fn restore_session(&mut self, json: &str) -> Result<(), String>;
+
+    /// A short piece of text saying what is being shown, for frontends to put in a link.
+    fn link(&self) -> String;
+
+    /// Shows what a `link` points to, or says why it can't.
+    fn follow_link(&mut self, link: &str) -> Result<(), String>;
 }
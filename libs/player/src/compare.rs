@@ -0,0 +1,592 @@
+use diff::{Hunk, Op};
+use platform_types::{Button, Input};
+use rendering::{Framebuffer, BLACK, CURSOR, GREEN, GREY, HIGHLIGHT, RED};
+use std::cmp::{max, min};
+
+use crate::{pixel_grid_size, ComparisonOptions, ComparisonView, ModeContext, Settings};
+
+/// A second file to compare the bytes with, and the places where the two differ, cached so
+/// the renderers and the navigation can ask about them without comparing again.
+#[derive(Default)]
+pub struct Comparison {
+    bytes: Vec<u8>,
+    // The runs of differing bytes, as sorted, non-overlapping start and end offsets. Bytes
+    // past the end of the shorter file count as different.
+    differences: Vec<(usize, usize)>,
+    // How to turn the bytes into the second file, lining up what they have in common.
+    hunks: Vec<Hunk>,
+}
+
+impl Comparison {
+    pub fn set_bytes(&mut self, bytes: Vec<u8>, against: &[u8]) {
+        self.bytes = bytes;
+        self.refresh(against);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether there is a second file to compare with.
+    pub fn is_active(&self) -> bool {
+        !self.bytes.is_empty()
+    }
+
+    /// The second file's bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Call this whenever the bytes being compared with the second file change.
+    pub fn refresh(&mut self, against: &[u8]) {
+        self.differences.clear();
+        self.hunks.clear();
+        if !self.is_active() {
+            return;
+        }
+        self.hunks = diff::diff(against, &self.bytes);
+
+        let mut start = None;
+        for i in 0..max(self.bytes.len(), against.len()) {
+            match (start, self.bytes.get(i) != against.get(i)) {
+                (None, true) => start = Some(i),
+                (Some(s), false) => {
+                    self.differences.push((s, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            self.differences
+                .push((s, max(self.bytes.len(), against.len())));
+        }
+    }
+
+    pub fn differences(&self) -> &[(usize, usize)] {
+        &self.differences
+    }
+
+    pub fn differs(&self, offset: usize) -> bool {
+        let after = self
+            .differences
+            .partition_point(|&(start, _)| start <= offset);
+        after > 0 && offset < self.differences[after - 1].1
+    }
+
+    /// The start of the first difference after `offset`, wrapping around to the first one.
+    pub fn next_difference(&self, offset: usize) -> Option<usize> {
+        next_start(self.differences.iter().map(|&(start, _)| start), offset)
+    }
+
+    /// The start of the last difference before `offset`, wrapping around to the last one.
+    pub fn previous_difference(&self, offset: usize) -> Option<usize> {
+        previous_start(self.differences.iter().map(|&(start, _)| start), offset)
+    }
+
+    /// The hunks that turn the bytes into the second file, in order.
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// The hunk with the byte at `offset` in the bytes being compared, if there is one.
+    pub fn hunk_at(&self, offset: usize) -> Option<&Hunk> {
+        let i = self.hunks.partition_point(|h| h.a_end() <= offset);
+        self.hunks.get(i)
+    }
+
+    /// The hunk with the byte at `offset` in the second file, if there is one.
+    pub fn other_hunk_at(&self, offset: usize) -> Option<&Hunk> {
+        let i = self.hunks.partition_point(|h| h.b_end() <= offset);
+        self.hunks.get(i)
+    }
+
+    /// Where the byte at `offset` ended up in the second file, or where it would have been
+    /// if it was deleted or changed.
+    pub fn aligned_offset(&self, offset: usize) -> usize {
+        match self.hunk_at(offset) {
+            Some(h) => h.b_start + min(offset - h.a_start, h.b_len.saturating_sub(1)),
+            None => self.hunks.last().map_or(offset, Hunk::b_end),
+        }
+    }
+
+    /// Whether the byte at `offset` was deleted or changed in the second file, once the two
+    /// are lined up.
+    pub fn changed(&self, offset: usize) -> bool {
+        self.hunk_at(offset).is_some_and(|h| h.op != Op::Equal)
+    }
+
+    /// Where the first insertion, deletion or change after `offset` is, wrapping around to
+    /// the first one.
+    pub fn next_change(&self, offset: usize) -> Option<usize> {
+        next_start(self.change_starts(), offset)
+    }
+
+    /// Where the last insertion, deletion or change before `offset` is, wrapping around to
+    /// the last one.
+    pub fn previous_change(&self, offset: usize) -> Option<usize> {
+        previous_start(self.change_starts(), offset)
+    }
+
+    fn change_starts(&self) -> impl DoubleEndedIterator<Item = usize> + Clone + '_ {
+        self.hunks
+            .iter()
+            .filter(|h| h.op != Op::Equal)
+            .map(|h| h.a_start)
+    }
+}
+
+// The first of `starts`, which are in order, after `offset`, wrapping around to the first.
+fn next_start(starts: impl Iterator<Item = usize> + Clone, offset: usize) -> Option<usize> {
+    starts
+        .clone()
+        .find(|&start| start > offset)
+        .or_else(|| starts.clone().next())
+}
+
+// The last of `starts`, which are in order, before `offset`, wrapping around to the last.
+fn previous_start(
+    starts: impl DoubleEndedIterator<Item = usize> + Clone,
+    offset: usize,
+) -> Option<usize> {
+    starts
+        .clone()
+        .rev()
+        .find(|&start| start < offset)
+        .or_else(|| starts.clone().next_back())
+}
+
+/// Shows the bytes against the second file one pixel per byte, in one of the
+/// `ComparisonView`s. Wherever the two differ stands out in the highlight colour, and A and
+/// B jump to the next and previous difference.
+#[derive(Default)]
+pub struct ComparisonState {
+    pub byte_index: usize,
+    /// Set this to false to have the screen redrawn on the next frame.
+    pub rendered: bool,
+    // The view from the options as of the last frame, which decides how many bytes fit
+    // on each row.
+    view: ComparisonView,
+}
+
+// How many bytes of each file go on each row. Side by side, each file gets half the width,
+// and lined up, each one gets a third, with the bands between them in the middle third.
+fn bytes_per_row(view: ComparisonView, settings: &Settings) -> usize {
+    let width = pixel_grid_size(settings).0;
+    let per_row = match view {
+        ComparisonView::SideBySide => width / 2,
+        ComparisonView::Xor | ComparisonView::Differences => width,
+        ComparisonView::Aligned => width / 3,
+    };
+    max(per_row, 1)
+}
+
+fn grey(byte: u8) -> u32 {
+    0xFF00_0000 | (u32::from(byte) * 0x01_01_01)
+}
+
+// Halfway between `colour` and `with`.
+fn mix(colour: u32, with: u32) -> u32 {
+    0xFF00_0000 | (((colour & 0xFE_FE_FE) >> 1) + ((with & 0xFE_FE_FE) >> 1))
+}
+
+fn highlighted(colour: u32) -> u32 {
+    mix(colour, HIGHLIGHT)
+}
+
+fn op_colour(op: Op) -> u32 {
+    match op {
+        Op::Equal => GREY,
+        Op::Delete => RED,
+        Op::Insert => GREEN,
+        Op::Replace => HIGHLIGHT,
+    }
+}
+
+impl ComparisonState {
+    pub fn visible_byte_count(&self, settings: &Settings) -> usize {
+        bytes_per_row(self.view, settings) * pixel_grid_size(settings).1
+    }
+
+    pub(crate) fn bytes_per_row(&self, settings: &Settings) -> usize {
+        bytes_per_row(self.view, settings)
+    }
+
+    // The offset of the byte drawn in the given column and row, in either file. Lined up,
+    // the second file's bytes have different offsets, so only the first column counts.
+    pub(crate) fn offset_at(
+        &self,
+        column: usize,
+        row: usize,
+        settings: &Settings,
+    ) -> Option<usize> {
+        let per_row = bytes_per_row(self.view, settings);
+        let column = match self.view {
+            ComparisonView::SideBySide if column < per_row * 2 => column % per_row,
+            ComparisonView::SideBySide => return None,
+            _ if column < per_row => column,
+            _ => return None,
+        };
+        Some(self.byte_index + row * per_row + column)
+    }
+
+    /// Scrolls so that `offset` is on screen, if it isn't already.
+    pub fn show(&mut self, offset: usize, settings: &Settings) {
+        if offset < self.byte_index || offset >= self.byte_index + self.visible_byte_count(settings)
+        {
+            let bytes_per_row = bytes_per_row(self.view, settings);
+            self.byte_index = offset / bytes_per_row * bytes_per_row;
+        }
+        self.rendered = false;
+    }
+}
+
+pub fn update_and_render_comparison(
+    framebuffer: &mut Framebuffer,
+    state: &mut ComparisonState,
+    input: Input,
+    context: &ModeContext,
+    comparison: &Comparison,
+    cursor: &mut usize,
+    options: &ComparisonOptions,
+) {
+    let ModeContext {
+        bytes, settings, ..
+    } = *context;
+    let bytes_per_row = bytes_per_row(options.view, settings);
+    if state.view != options.view {
+        state.view = options.view;
+        state.byte_index = state.byte_index / bytes_per_row * bytes_per_row;
+        state.show(*cursor, settings);
+    }
+
+    let previous_cursor = *cursor;
+    if input.pressed_this_frame(Button::Up) {
+        *cursor = cursor.saturating_sub(bytes_per_row);
+    } else if input.pressed_this_frame(Button::Down) {
+        *cursor += bytes_per_row;
+    } else if input.pressed_this_frame(Button::Left) {
+        *cursor = cursor.saturating_sub(1);
+    } else if input.pressed_this_frame(Button::Right) {
+        *cursor += 1;
+    } else if input.pressed_this_frame(Button::A) {
+        let next = if options.view == ComparisonView::Aligned {
+            comparison.next_change(*cursor)
+        } else {
+            comparison.next_difference(*cursor)
+        };
+        *cursor = next.unwrap_or(*cursor);
+    } else if input.pressed_this_frame(Button::B) {
+        let previous = if options.view == ComparisonView::Aligned {
+            comparison.previous_change(*cursor)
+        } else {
+            comparison.previous_difference(*cursor)
+        };
+        *cursor = previous.unwrap_or(*cursor);
+    }
+    *cursor = min(*cursor, bytes.len().saturating_sub(1));
+
+    if *cursor != previous_cursor {
+        state.show(*cursor, settings);
+    }
+
+    if state.rendered {
+        return;
+    }
+
+    if options.view == ComparisonView::Aligned {
+        render_aligned(framebuffer, state, bytes, comparison, *cursor, settings);
+        state.rendered = true;
+        return;
+    }
+
+    let zoom = settings.zoom as usize;
+    let rows = pixel_grid_size(settings).1;
+    let other = comparison.bytes();
+    let mut draw = |column: usize, row: usize, colour: u32| {
+        framebuffer.draw_filled_rect(column * zoom, row * zoom, zoom, zoom, colour);
+    };
+
+    for i in 0..bytes_per_row * rows {
+        let offset = state.byte_index + i;
+        let (column, row) = (i % bytes_per_row, i / bytes_per_row);
+        let (a, b) = (bytes.get(offset).cloned(), other.get(offset).cloned());
+        let differs = a != b;
+
+        match options.view {
+            ComparisonView::SideBySide => {
+                for (byte, column) in [(a, column), (b, column + bytes_per_row)].iter().cloned() {
+                    let colour = match byte {
+                        Some(byte) if differs => highlighted(grey(byte)),
+                        Some(byte) => grey(byte),
+                        None => BLACK,
+                    };
+                    draw(column, row, colour);
+                }
+            }
+            ComparisonView::Xor => {
+                let colour = match (a, b) {
+                    _ if !differs => BLACK,
+                    (Some(a), Some(b)) => highlighted(grey(a ^ b)),
+                    (Some(byte), None) | (None, Some(byte)) => highlighted(grey(byte)),
+                    (None, None) => BLACK,
+                };
+                draw(column, row, colour);
+            }
+            ComparisonView::Differences => {
+                let colour = match (a, b) {
+                    (None, None) => BLACK,
+                    _ if differs => HIGHLIGHT,
+                    _ => GREY,
+                };
+                draw(column, row, colour);
+            }
+            ComparisonView::Aligned => {}
+        }
+    }
+
+    if *cursor >= state.byte_index {
+        let i = *cursor - state.byte_index;
+        let (column, row) = (i % bytes_per_row, i / bytes_per_row);
+        if row < rows {
+            outline_pixel(framebuffer, column, row, zoom);
+            if options.view == ComparisonView::SideBySide {
+                outline_pixel(framebuffer, column + bytes_per_row, row, zoom);
+            }
+        }
+    }
+
+    state.rendered = true;
+}
+
+// Draws the bytes down the left, the second file down the right, and bands between them
+// joining up the runs of bytes the two have in common. Insertions and deletions get
+// coloured wedges instead, which narrow to a point on the side that doesn't have them.
+fn render_aligned(
+    framebuffer: &mut Framebuffer,
+    state: &ComparisonState,
+    bytes: &[u8],
+    comparison: &Comparison,
+    cursor: usize,
+    settings: &Settings,
+) {
+    let zoom = settings.zoom as usize;
+    let (width, rows) = pixel_grid_size(settings);
+    let per_row = bytes_per_row(ComparisonView::Aligned, settings);
+    let right_column = width.saturating_sub(per_row);
+    let (a_start, b_start) = (
+        state.byte_index,
+        comparison.aligned_offset(state.byte_index),
+    );
+    let other = comparison.bytes();
+
+    let colour = |byte: Option<&u8>, hunk: Option<&Hunk>| match (byte, hunk) {
+        (None, _) => BLACK,
+        (Some(&byte), Some(hunk)) if hunk.op != Op::Equal => mix(grey(byte), op_colour(hunk.op)),
+        (Some(&byte), _) => grey(byte),
+    };
+    for i in 0..per_row * rows {
+        let (x, y) = (i % per_row * zoom, i / per_row * zoom);
+        let (a, b) = (a_start + i, b_start + i);
+        let left = colour(bytes.get(a), comparison.hunk_at(a));
+        let right = colour(other.get(b), comparison.other_hunk_at(b));
+        framebuffer.draw_filled_rect(x, y, zoom, zoom, left);
+        framebuffer.draw_filled_rect(x + right_column * zoom, y, zoom, zoom, right);
+    }
+
+    let band_left = per_row * zoom;
+    let band_right = (right_column * zoom).saturating_sub(1);
+    framebuffer.draw_filled_rect(band_left, 0, band_right + 1 - band_left, rows * zoom, BLACK);
+
+    // The top and bottom of the rows that bytes `start` to `end` are on, in a column that
+    // starts at `column_start`, clamped to the screen.
+    let span = |start: usize, end: usize, column_start: usize| {
+        let row = |offset: usize| min(offset.saturating_sub(column_start), per_row * rows);
+        let top = row(start) / per_row;
+        let bottom = row(end).div_ceil(per_row);
+        (top * zoom, max(bottom * zoom, top * zoom + 1) - 1)
+    };
+    let (a_visible_end, b_visible_end) = (a_start + per_row * rows, b_start + per_row * rows);
+    for hunk in comparison.hunks() {
+        if hunk.a_start >= a_visible_end && hunk.b_start >= b_visible_end {
+            break;
+        }
+        if hunk.a_end() < a_start && hunk.b_end() < b_start {
+            continue;
+        }
+
+        let (a_top, a_bottom) = span(hunk.a_start, hunk.a_end(), a_start);
+        let (b_top, b_bottom) = span(hunk.b_start, hunk.b_end(), b_start);
+        let (a_bottom, b_bottom) = match hunk.op {
+            Op::Insert => (a_top, b_bottom),
+            Op::Delete => (a_bottom, b_top),
+            Op::Equal | Op::Replace => (a_bottom, b_bottom),
+        };
+        framebuffer.draw_filled_quad(
+            band_left,
+            a_top,
+            band_left,
+            a_bottom,
+            band_right,
+            b_top,
+            band_right,
+            b_bottom,
+            op_colour(hunk.op),
+        );
+    }
+
+    let mut outline = |offset: usize, column_start: usize, x_offset: usize| {
+        if offset >= column_start && offset - column_start < per_row * rows {
+            let i = offset - column_start;
+            outline_pixel(framebuffer, i % per_row + x_offset, i / per_row, zoom);
+        }
+    };
+    outline(cursor, a_start, 0);
+    outline(comparison.aligned_offset(cursor), b_start, right_column);
+}
+
+fn outline_pixel(framebuffer: &mut Framebuffer, column: usize, row: usize, zoom: usize) {
+    let x = (column * zoom).saturating_sub(1);
+    let y = (row * zoom).saturating_sub(1);
+    framebuffer.draw_rect(
+        x,
+        y,
+        (column + 1) * zoom + 1 - x,
+        (row + 1) * zoom + 1 - y,
+        CURSOR,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fields, Search};
+
+    fn comparison(a: &[u8], b: &[u8]) -> Comparison {
+        let mut comparison = Comparison::default();
+        comparison.set_bytes(b.to_vec(), a);
+        comparison
+    }
+
+    #[test]
+    fn differences_are_runs_including_the_end_of_the_shorter_file() {
+        let comparison = comparison(&[1, 2, 3, 4, 5, 6], &[1, 0, 0, 4, 5]);
+
+        assert_eq!(comparison.differences(), [(1, 3), (5, 6)]);
+        assert!(comparison.differs(2));
+        assert!(!comparison.differs(3));
+        assert!(comparison.differs(5));
+    }
+
+    #[test]
+    fn next_and_previous_difference_wrap_around() {
+        let comparison = comparison(&[0; 10], &[0, 1, 0, 0, 1, 1, 0, 0, 0, 1]);
+
+        assert_eq!(comparison.next_difference(0), Some(1));
+        assert_eq!(comparison.next_difference(1), Some(4));
+        assert_eq!(comparison.next_difference(9), Some(1));
+        assert_eq!(comparison.previous_difference(5), Some(4));
+        assert_eq!(comparison.previous_difference(4), Some(1));
+        assert_eq!(comparison.previous_difference(1), Some(9));
+        assert_eq!(Comparison::default().next_difference(0), None);
+    }
+
+    #[test]
+    fn each_view_highlights_the_differing_bytes() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80];
+        let comparison = comparison(&bytes, &[0x10, 0x21, 0x30]);
+        let settings = Settings {
+            show_hud: false,
+            ..Settings::default()
+        };
+        let render = |view| {
+            let mut framebuffer = Framebuffer::new();
+            // Out of the way of the pixels checked below.
+            let mut cursor = bytes.len() - 1;
+            update_and_render_comparison(
+                &mut framebuffer,
+                &mut ComparisonState::default(),
+                Input::new(),
+                &ModeContext {
+                    bytes: &bytes,
+                    search: &Search::default(),
+                    regions: &[],
+                    fields: &Fields::default(),
+                    selection: None,
+                    settings: &settings,
+                },
+                &comparison,
+                &mut cursor,
+                &ComparisonOptions { view },
+            );
+            framebuffer.buffer
+        };
+
+        let differences = render(ComparisonView::Differences);
+        assert_eq!(differences[..2], [GREY, HIGHLIGHT]);
+        // Only the first file has a fourth byte.
+        assert_eq!(differences[3], HIGHLIGHT);
+
+        let xor = render(ComparisonView::Xor);
+        assert_eq!(xor[..2], [BLACK, highlighted(grey(0x01))]);
+
+        let side_by_side = render(ComparisonView::SideBySide);
+        let half = pixel_grid_size(&settings).0 / 2;
+        assert_eq!(side_by_side[1], highlighted(grey(0x20)));
+        assert_eq!(side_by_side[half + 1], highlighted(grey(0x21)));
+        assert_eq!(side_by_side[half], grey(0x10));
+    }
+
+    #[test]
+    fn lining_the_files_up_skips_over_inserted_bytes() {
+        let comparison = comparison(&[1, 2, 3, 4, 5, 6], &[1, 2, 9, 9, 3, 4, 5, 6]);
+
+        assert_eq!(comparison.aligned_offset(1), 1);
+        assert_eq!(comparison.aligned_offset(2), 4);
+        assert!(!comparison.changed(2));
+        // Every byte after the insertion is in a different place, but none of them changed.
+        assert_eq!(comparison.next_change(0), Some(2));
+        assert_eq!(comparison.next_change(2), Some(2));
+        assert_eq!(comparison.differences(), [(2, 8)]);
+    }
+
+    #[test]
+    fn the_aligned_view_colours_inserted_bytes_and_joins_up_the_rest() {
+        let bytes = [1, 2, 3, 4, 5];
+        let comparison = comparison(&bytes, &[1, 2, 9, 3, 4, 5]);
+        let settings = Settings {
+            show_hud: false,
+            ..Settings::default()
+        };
+        let mut framebuffer = Framebuffer::new();
+        let mut cursor = bytes.len() - 1;
+        update_and_render_comparison(
+            &mut framebuffer,
+            &mut ComparisonState::default(),
+            Input::new(),
+            &ModeContext {
+                bytes: &bytes,
+                search: &Search::default(),
+                regions: &[],
+                fields: &Fields::default(),
+                selection: None,
+                settings: &settings,
+            },
+            &comparison,
+            &mut cursor,
+            &ComparisonOptions {
+                view: ComparisonView::Aligned,
+            },
+        );
+
+        let pixels = framebuffer.buffer;
+        let width = pixel_grid_size(&settings).0;
+        let right = width - width / 3;
+        assert_eq!(pixels[..3], [grey(1), grey(2), grey(3)]);
+        assert_eq!(
+            pixels[right..right + 4],
+            [grey(1), grey(2), mix(grey(9), GREEN), grey(3)]
+        );
+        assert_eq!(pixels[width / 3], GREY);
+    }
+}
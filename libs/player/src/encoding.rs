@@ -0,0 +1,245 @@
+use platform_types::{Button, Input};
+use rendering::{
+    Framebuffer, BLACK, BLUE, CURRENT_HIGHLIGHT, CURSOR, GREEN, GREY, HIGHLIGHT, PURPLE, RED,
+    WHITE, YELLOW,
+};
+use std::cmp::min;
+use text::{byte_roles, ByteRole};
+
+use crate::{
+    fields, outline_pixel_span, pixel_grid_size, sections, EncodingOptions, ModeContext, Settings,
+};
+
+fn role_colour(role: ByteRole) -> u32 {
+    match role {
+        ByteRole::Ascii => GREY,
+        ByteRole::Multibyte => GREEN,
+        ByteRole::Lead => YELLOW,
+        ByteRole::Continuation => PURPLE,
+        ByteRole::Invalid => RED,
+        ByteRole::Surrogate => BLUE,
+    }
+}
+
+/// Shows one pixel per byte, coloured by the byte's role when the bytes are decoded as
+/// text. Runs of text show up as grey and green among the red and purple of other data,
+/// and the first byte that isn't grey or green is where a text's encoding breaks.
+#[derive(Default)]
+pub struct EncodingState {
+    pub byte_index: usize,
+    /// Set this to false to have the screen redrawn on the next frame.
+    pub rendered: bool,
+    // Decoded the first time the mode is drawn. The mode is reset whenever the bytes or the
+    // encoding change, so these are never stale.
+    roles: Option<Vec<ByteRole>>,
+}
+
+impl EncodingState {
+    pub(crate) fn visible_byte_count(settings: &Settings) -> usize {
+        let (width, height) = pixel_grid_size(settings);
+        width * height
+    }
+
+    /// Scrolls so that `offset` is on screen, if it isn't already.
+    pub fn show(&mut self, offset: usize, settings: &Settings) {
+        if offset < self.byte_index
+            || offset >= self.byte_index + Self::visible_byte_count(settings)
+        {
+            let bytes_per_row = pixel_grid_size(settings).0;
+            self.byte_index = offset / bytes_per_row * bytes_per_row;
+        }
+        self.rendered = false;
+    }
+
+    /// The role of the byte at `offset`, once the mode has been drawn.
+    pub fn role(&self, offset: usize) -> Option<ByteRole> {
+        self.roles
+            .as_ref()
+            .and_then(|roles| roles.get(offset).cloned())
+    }
+}
+
+pub fn update_and_render_encoding(
+    framebuffer: &mut Framebuffer,
+    state: &mut EncodingState,
+    input: Input,
+    context: &ModeContext,
+    cursor: &mut usize,
+    options: &EncodingOptions,
+) {
+    let ModeContext {
+        bytes,
+        search,
+        regions,
+        fields,
+        selection,
+        settings,
+    } = *context;
+    let previous_cursor = *cursor;
+    let (width, height) = pixel_grid_size(settings);
+    if input.pressed_this_frame(Button::Up) {
+        *cursor = cursor.saturating_sub(width);
+    } else if input.pressed_this_frame(Button::Down) {
+        *cursor += width;
+    } else if input.pressed_this_frame(Button::Left) {
+        *cursor = cursor.saturating_sub(1);
+    } else if input.pressed_this_frame(Button::Right) {
+        *cursor += 1;
+    }
+    *cursor = min(*cursor, bytes.len().saturating_sub(1));
+
+    if *cursor != previous_cursor {
+        state.show(*cursor, settings);
+    }
+
+    if state.rendered {
+        return;
+    }
+
+    let roles = state
+        .roles
+        .get_or_insert_with(|| byte_roles(bytes, options.encoding));
+
+    let zoom = settings.zoom as usize;
+    for i in 0..width * height {
+        let colour = roles
+            .get(state.byte_index + i)
+            .map(|&role| role_colour(role))
+            .unwrap_or(BLACK);
+        framebuffer.draw_filled_rect((i % width) * zoom, (i / width) * zoom, zoom, zoom, colour);
+    }
+
+    let visible_end = state.byte_index + width * height;
+    let match_len = search.match_len();
+    for &match_start in search.matches() {
+        if match_start + match_len <= state.byte_index {
+            continue;
+        }
+        if match_start >= visible_end {
+            break;
+        }
+
+        let colour = if search.is_current(match_start) {
+            CURRENT_HIGHLIGHT
+        } else {
+            HIGHLIGHT
+        };
+        outline_pixel_span(
+            framebuffer,
+            match_start.saturating_sub(state.byte_index),
+            min(match_start + match_len, visible_end) - 1 - state.byte_index,
+            settings,
+            colour,
+        );
+    }
+
+    sections::render_over_pixels(framebuffer, regions, state.byte_index, 8, settings);
+    fields::render_over_pixels(framebuffer, fields, state.byte_index, 8, settings);
+
+    if let Some((start, end)) = selection {
+        if end > state.byte_index && start < visible_end {
+            outline_pixel_span(
+                framebuffer,
+                start.saturating_sub(state.byte_index),
+                min(end, visible_end) - 1 - state.byte_index,
+                settings,
+                WHITE,
+            );
+        }
+    }
+
+    if *cursor >= state.byte_index {
+        let pixel = *cursor - state.byte_index;
+        outline_pixel_span(framebuffer, pixel, pixel, settings, CURSOR);
+    }
+
+    state.rendered = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fields, Search, SectionMap};
+    use formats::{Region, RegionKind};
+    use text::Encoding;
+
+    #[test]
+    fn each_byte_is_one_pixel_coloured_by_its_role() {
+        let mut framebuffer = Framebuffer::new();
+        let mut state = EncodingState::default();
+        let mut cursor = 100;
+        let settings = Settings {
+            show_hud: false,
+            ..Settings::default()
+        };
+
+        update_and_render_encoding(
+            &mut framebuffer,
+            &mut state,
+            Input::new(),
+            &ModeContext {
+                bytes: &[b'a', 0xC3, 0xA9, 0x80],
+                search: &Search::default(),
+                regions: &[],
+                fields: &Fields::default(),
+                selection: None,
+                settings: &settings,
+            },
+            &mut cursor,
+            &EncodingOptions {
+                encoding: Encoding::Utf8,
+            },
+        );
+
+        assert_eq!(cursor, 3);
+        assert_eq!(state.role(3), Some(ByteRole::Continuation));
+        assert_eq!(framebuffer.buffer[0], GREY);
+        assert_eq!(framebuffer.buffer[1], GREEN);
+        // The cursor's outline covers the pixels either side of the last byte.
+        assert_eq!(framebuffer.buffer[4], CURSOR);
+        assert_eq!(framebuffer.buffer[5], BLACK);
+    }
+
+    #[test]
+    fn chunks_are_drawn_over_their_own_bytes() {
+        let mut framebuffer = Framebuffer::new();
+        let mut state = EncodingState::default();
+        let mut cursor = 0;
+        let settings = Settings {
+            show_hud: false,
+            section_map: SectionMap::Outlines,
+            zoom: 1,
+            ..Settings::default()
+        };
+        let chunk = Region {
+            name: "IDAT".to_string(),
+            kind: RegionKind::Chunk,
+            start: 16,
+            len: 8,
+        };
+
+        update_and_render_encoding(
+            &mut framebuffer,
+            &mut state,
+            Input::new(),
+            &ModeContext {
+                bytes: &[b'a'; 64],
+                search: &Search::default(),
+                regions: &[chunk],
+                fields: &Fields::default(),
+                selection: None,
+                settings: &settings,
+            },
+            &mut cursor,
+            &EncodingOptions {
+                encoding: Encoding::Utf8,
+            },
+        );
+
+        // One byte to a pixel, so the band covers pixels 16 to 23 and nothing else.
+        assert_eq!(framebuffer.buffer[15], GREY);
+        assert_ne!(framebuffer.buffer[16], GREY);
+        assert_ne!(framebuffer.buffer[23], GREY);
+        assert_eq!(framebuffer.buffer[24], GREY);
+    }
+}
@@ -0,0 +1,345 @@
+use platform_types::{Button, Input};
+use rendering::{
+    Framebuffer, FONT_ADVANCE, FONT_SIZE, GREEN, PURPLE, RED_INDEX, SPRITE_SIZE, WHITE,
+    WHITE_INDEX, YELLOW_INDEX,
+};
+use std::cmp::{max, min};
+use template::{Field, Template, Value};
+
+use crate::{
+    outline_pixel_span, tint_pixel_span, visible_pixel_span, FieldPanel, GameState, RenderMode,
+    Settings,
+};
+
+const PANEL_CHARS: usize = 30;
+const PANEL_W: usize = SPRITE_SIZE as usize * 2 + PANEL_CHARS * FONT_ADVANCE as usize;
+const MAX_INDENT: usize = 8;
+
+// Neighbouring fields alternate between these outlines.
+const FIELD_COLOURS: [u32; 2] = [GREEN, PURPLE];
+
+// Brightens the selected field without hiding its bytes.
+const SELECTED_TINT: u32 = (WHITE & 0x00FF_FFFF) | 0x80 << 24;
+
+/// One line of the field panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub depth: usize,
+    pub text: String,
+    pub start: usize,
+    pub len: usize,
+    // Whether to outline the field's bytes. Array elements aren't, since a long array of
+    // bytes would be nothing but outlines. The array as a whole is outlined instead.
+    outlined: bool,
+}
+
+/// A template applied to the bytes, and the state of the panel listing the fields it
+/// decoded.
+#[derive(Default)]
+pub struct Fields {
+    template: Option<Template>,
+    offset: usize,
+    rows: Vec<Row>,
+    /// Why the template couldn't be applied to the current bytes, if it couldn't.
+    error: Option<template::Error>,
+    selected: usize,
+    scroll: usize,
+}
+
+impl Fields {
+    pub fn set_template(&mut self, template: Template, offset: usize, bytes: &[u8]) {
+        self.template = Some(template);
+        self.offset = offset;
+        self.refresh(bytes);
+    }
+
+    pub fn clear(&mut self) {
+        *self = d!();
+    }
+
+    /// Applies the template again, for example because the bytes changed.
+    pub fn refresh(&mut self, bytes: &[u8]) {
+        self.rows.clear();
+        self.error = None;
+        self.selected = 0;
+        self.scroll = 0;
+
+        if let Some(ref template) = self.template {
+            match template.apply(bytes, self.offset) {
+                Ok(root) => flatten(&root, 0, false, &mut self.rows),
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.template.is_some()
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn error(&self) -> Option<&template::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn selected(&self) -> Option<&Row> {
+        self.rows.get(self.selected)
+    }
+
+    // Selects the innermost field containing `offset`, if any do, and returns whether the
+    // selection changed.
+    fn select_containing(&mut self, offset: usize) -> bool {
+        // Fields come before the fields inside them, so the last match is the innermost.
+        let innermost = self
+            .rows
+            .iter()
+            .rposition(|row| row.start <= offset && offset < row.start + row.len);
+
+        match innermost {
+            Some(i) if i != self.selected => {
+                self.selected = i;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn flatten(field: &Field, depth: usize, in_array: bool, rows: &mut Vec<Row>) {
+    let text = match field.value {
+        Value::Unsigned(n) if n < 10 => format!("{} = {}", field.name, n),
+        Value::Unsigned(n) => format!("{} = {} ({:#X})", field.name, n, n),
+        Value::Signed(n) => format!("{} = {}", field.name, n),
+        Value::F32(x) => format!("{} = {}", field.name, x),
+        Value::F64(x) => format!("{} = {}", field.name, x),
+        Value::Array(ref elements) => format!("{}[{}]", field.name, elements.len()),
+        Value::Struct(_) => field.name.clone(),
+    };
+
+    let is_array = matches!(field.value, Value::Array(_));
+    let is_number = field.value.children().is_empty() && !is_array;
+
+    rows.push(Row {
+        depth,
+        text,
+        start: field.start,
+        len: field.len,
+        outlined: is_array || (is_number && !in_array),
+    });
+
+    for child in field.value.children() {
+        flatten(child, depth + 1, is_array, rows);
+    }
+}
+
+fn visible_rows(settings: &Settings) -> usize {
+    settings
+        .view_height()
+        .saturating_sub(SPRITE_SIZE as usize * 2)
+        / FONT_SIZE as usize
+}
+
+/// While browsing the fields, the D-pad moves through them instead of controlling the
+/// mode, and the cursor follows along. Returns the input the mode should see.
+pub fn browse(state: &mut GameState, input: Input) -> Input {
+    if state.settings.field_panel != FieldPanel::Browse || state.fields.rows.is_empty() {
+        return input;
+    }
+
+    let page = visible_rows(&state.settings) as isize;
+    let step = if input.pressed_this_frame(Button::Up) {
+        -1
+    } else if input.pressed_this_frame(Button::Down) {
+        1
+    } else if input.pressed_this_frame(Button::Left) {
+        -page
+    } else if input.pressed_this_frame(Button::Right) {
+        page
+    } else {
+        0
+    };
+
+    if step != 0 {
+        let fields = &mut state.fields;
+        let last = fields.rows.len() as isize - 1;
+        fields.selected = max(0, min(fields.selected as isize + step, last)) as usize;
+
+        state.cursor = fields.rows[fields.selected].start;
+        state.show_cursor();
+    }
+
+    let mut input = input;
+    input
+        .gamepad
+        .remove(Button::Up | Button::Down | Button::Left | Button::Right);
+    input
+}
+
+/// Selects the field under the cursor after it has been moved some other way than by
+/// browsing.
+pub fn follow_cursor(state: &mut GameState) {
+    if state.fields.select_containing(state.cursor) {
+        for (mode, _, _) in state.panes_mut() {
+            if let RenderMode::ThreeBitsPerPixel(ref mut tbbp_state) = *mode {
+                tbbp_state.rendered = false;
+            }
+        }
+    }
+}
+
+/// Lists the fields down the left side of the screen, scrolled so the selected one is
+/// shown.
+pub fn render_panel(framebuffer: &mut Framebuffer, fields: &mut Fields, settings: &Settings) {
+    if settings.field_panel == FieldPanel::Off || !fields.is_active() {
+        return;
+    }
+
+    let max_rows = visible_rows(settings);
+    if fields.selected < fields.scroll {
+        fields.scroll = fields.selected;
+    } else if fields.selected >= fields.scroll + max_rows {
+        fields.scroll = fields.selected + 1 - max_rows;
+    }
+
+    let mut lines: Vec<(String, u8)> = Vec::with_capacity(max_rows);
+    for (i, row) in fields
+        .rows
+        .iter()
+        .enumerate()
+        .skip(fields.scroll)
+        .take(max_rows)
+    {
+        let indent = min(row.depth, MAX_INDENT);
+        let colour_index = if i == fields.selected {
+            YELLOW_INDEX
+        } else {
+            WHITE_INDEX
+        };
+        lines.push((
+            format!("{:indent$}{}", "", row.text, indent = indent),
+            colour_index,
+        ));
+    }
+    if let Some(ref error) = fields.error {
+        let message = error.to_string();
+        let chars: Vec<char> = message.chars().collect();
+        for chunk in chars.chunks(PANEL_CHARS) {
+            lines.push((chunk.iter().collect(), RED_INDEX));
+        }
+    }
+    lines.truncate(max_rows);
+
+    let margin = SPRITE_SIZE as usize;
+    let row_h = FONT_SIZE as usize;
+    framebuffer.window(0, 0, PANEL_W, max(lines.len(), 1) * row_h + margin * 2);
+
+    for (row, (line, colour_index)) in lines.iter_mut().enumerate() {
+        line.truncate(PANEL_CHARS);
+        let y = margin + row * row_h;
+        framebuffer.print_line(line.as_bytes(), margin, y, *colour_index);
+    }
+}
+
+/// Outlines the fields' bytes over a pixel based mode, which is showing the bytes from
+/// `byte_index` onwards, `bits_per_pixel` bits to a pixel, and tints the selected field.
+pub fn render_over_pixels(
+    framebuffer: &mut Framebuffer,
+    fields: &Fields,
+    byte_index: usize,
+    bits_per_pixel: usize,
+    settings: &Settings,
+) {
+    if settings.field_panel == FieldPanel::Off {
+        return;
+    }
+
+    let outlined = fields.rows.iter().filter(|row| row.outlined);
+    for (i, row) in outlined.enumerate() {
+        if let Some((first, last)) =
+            visible_pixel_span(row.start, row.len, byte_index, bits_per_pixel, settings)
+        {
+            let colour = FIELD_COLOURS[i % FIELD_COLOURS.len()];
+            outline_pixel_span(framebuffer, first, last, settings, colour);
+        }
+    }
+
+    if let Some(row) = fields.selected() {
+        if let Some((first, last)) =
+            visible_pixel_span(row.start, row.len, byte_index, bits_per_pixel, settings)
+        {
+            tint_pixel_span(framebuffer, first, last, settings, SELECTED_TINT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(source: &str, bytes: &[u8]) -> Fields {
+        let mut fields = Fields::default();
+        fields.set_template(Template::parse(source).unwrap(), 0, bytes);
+        fields
+    }
+
+    #[test]
+    fn rows_list_the_field_tree_in_order() {
+        let fields = fields("struct s { n: u8 xs: u16[n] }", &[2, 0x34, 0x12, 5, 0]);
+
+        let rows: Vec<_> = fields
+            .rows()
+            .iter()
+            .map(|row| (row.depth, row.text.as_str(), row.outlined))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (0, "s", false),
+                (1, "n = 2", true),
+                (1, "xs[2]", true),
+                (2, "[0] = 4660 (0x1234)", false),
+                (2, "[1] = 5", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_innermost_field_under_the_cursor_is_selected() {
+        let mut fields = fields("struct s { a: u8 b: t } struct t { c: u16 }", &[0; 3]);
+
+        assert!(fields.select_containing(2));
+        assert_eq!(fields.selected().unwrap().text, "c = 0");
+        assert!(!fields.select_containing(1));
+        assert!(!fields.select_containing(3));
+        assert_eq!(fields.selected().unwrap().text, "c = 0");
+    }
+
+    #[test]
+    fn browsing_moves_the_cursor_and_hides_the_d_pad_from_the_mode() {
+        let mut state = GameState::new();
+        state.settings.field_panel = FieldPanel::Browse;
+        state.apply_template(Template::parse("struct s { a: u32 b: u32 }").unwrap(), 8);
+
+        let input = Input {
+            gamepad: Button::Down,
+            previous_gamepad: Button::Ty::empty(),
+            ..Input::new()
+        };
+        let input = browse(&mut state, input);
+
+        assert_eq!(state.cursor, 8);
+        assert!(!input.pressed_this_frame(Button::Down));
+
+        browse(
+            &mut state,
+            Input {
+                gamepad: Button::Right,
+                previous_gamepad: Button::Ty::empty(),
+                ..Input::new()
+            },
+        );
+        assert_eq!(state.cursor, 12);
+    }
+}
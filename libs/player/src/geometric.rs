@@ -0,0 +1,502 @@
+use platform_types::{Button, Input};
+use project_common::{Appearance, Colour, Shape};
+use rendering::Framebuffer;
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
+use crate::{highlight_colour, GeometricOptions, ModeContext, Search, Settings};
+
+/// What the geometric modes draw from each run of bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Primitive {
+    /// Four points, as a filled quad.
+    #[default]
+    Quadrilateral,
+    /// Three points, as a filled triangle.
+    Triangle,
+    /// Two points, as a line segment.
+    Line,
+    /// A centre point and a radius, as a filled circle.
+    Circle,
+    /// A centre point, a radius and an `Appearance` byte selecting the shape and colour.
+    Appearance,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 5] = [
+        Primitive::Quadrilateral,
+        Primitive::Triangle,
+        Primitive::Line,
+        Primitive::Circle,
+        Primitive::Appearance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Quadrilateral => "Quadrilateral",
+            Primitive::Triangle => "Triangle",
+            Primitive::Line => "Line",
+            Primitive::Circle => "Circle",
+            Primitive::Appearance => "Appearance",
+        }
+    }
+
+    /// How many bytes each primitive is read from.
+    pub fn byte_len(self) -> usize {
+        match self {
+            Primitive::Quadrilateral => 8,
+            Primitive::Triangle => 6,
+            Primitive::Line => 4,
+            Primitive::Circle => 3,
+            Primitive::Appearance => 4,
+        }
+    }
+
+    /// How many primitives it takes to draw all of `bytes`. The last one may be short.
+    pub fn count(self, bytes: &[u8]) -> usize {
+        bytes.len().div_ceil(self.byte_len())
+    }
+}
+
+#[derive(Default)]
+pub struct GeometricState {
+    pub primitive: Primitive,
+    pub byte_index: usize,
+    pub paused: bool,
+    seek_target: Option<usize>,
+    // Copies of the mode's viewport from partway through the animation, sorted by primitive index,
+    // so seeking backwards doesn't have to start over from the first primitive.
+    checkpoints: Vec<Checkpoint>,
+    // Checkpoints are `CHECKPOINT_INTERVAL << checkpoint_shift` primitives apart.
+    checkpoint_shift: u32,
+}
+
+struct Checkpoint {
+    index: usize,
+    buffer: Vec<u32>,
+}
+
+const CHECKPOINT_INTERVAL: usize = 1024;
+// Each checkpoint is a full copy of the viewport, so how many fit in this depends on the
+// size of the screen. At the default size that is 64 of them.
+const MAX_CHECKPOINT_BYTES: usize = 16 << 20;
+// Seeking redraws at most this many primitives a frame, to keep the page responsive.
+const SEEK_PRIMITIVES_PER_FRAME: usize = 4096;
+
+impl GeometricState {
+    pub fn new(primitive: Primitive) -> Self {
+        GeometricState { primitive, ..d!() }
+    }
+
+    /// The index of the next primitive to be drawn.
+    pub fn index(&self) -> usize {
+        self.byte_index / self.primitive.byte_len()
+    }
+
+    /// The primitive the animation is at, or is heading to if a seek is in progress.
+    pub fn target_index(&self) -> usize {
+        self.seek_target.unwrap_or_else(|| self.index())
+    }
+
+    /// The animation will redraw up to the primitive at `index` over the next few frames.
+    pub fn seek(&mut self, index: usize) {
+        self.seek_target = Some(index);
+    }
+
+    fn checkpoint_interval(&self) -> usize {
+        CHECKPOINT_INTERVAL << self.checkpoint_shift
+    }
+
+    fn record_checkpoint(&mut self, framebuffer: &Framebuffer) {
+        let index = self.index();
+        if index == 0
+            || !index.is_multiple_of(self.checkpoint_interval())
+            || self.checkpoints.last().map(|c| c.index) >= Some(index)
+        {
+            return;
+        }
+
+        self.checkpoints.push(Checkpoint {
+            index,
+            buffer: framebuffer.viewport_pixels(),
+        });
+
+        let (width, height) = framebuffer.viewport_size();
+        let checkpoint_bytes = width * height * std::mem::size_of::<u32>();
+        if self.checkpoints.len() > max(MAX_CHECKPOINT_BYTES / max(checkpoint_bytes, 1), 1) {
+            self.checkpoint_shift += 1;
+            let interval = self.checkpoint_interval();
+            self.checkpoints.retain(|c| c.index % interval == 0);
+        }
+    }
+
+    // Puts the screen back to how it was at the latest checkpoint at or before `index`,
+    // if that is closer than where we are now, or the animation has to go backwards.
+    fn restore_checkpoint(&mut self, framebuffer: &mut Framebuffer, index: usize) {
+        let current = self.index();
+        let after = self.checkpoints.partition_point(|c| c.index <= index);
+
+        match after.checked_sub(1).map(|i| &self.checkpoints[i]) {
+            Some(checkpoint) if index < current || checkpoint.index > current => {
+                framebuffer.set_viewport_pixels(&checkpoint.buffer);
+                self.byte_index = checkpoint.index * self.primitive.byte_len();
+            }
+            None if index < current => {
+                self.byte_index = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn update_and_render_geometric(
+    framebuffer: &mut Framebuffer,
+    state: &mut GeometricState,
+    input: Input,
+    context: &ModeContext,
+    options: &GeometricOptions,
+) {
+    let ModeContext {
+        bytes,
+        search,
+        settings,
+        ..
+    } = *context;
+    let primitive_count = state.primitive.count(bytes);
+
+    if input.pressed_this_frame(Button::Up) {
+        state.paused = !state.paused;
+    } else if input.pressed_this_frame(Button::Down) {
+        state.seek(0);
+    } else if input.pressed_this_frame(Button::Left) {
+        state.paused = true;
+        let target = state.target_index().saturating_sub(1);
+        state.seek(target);
+    } else if input.pressed_this_frame(Button::Right) {
+        state.paused = true;
+        let target = state.target_index() + 1;
+        state.seek(target);
+    }
+
+    let primitives_to_draw = match state.seek_target {
+        Some(target) => {
+            let target = min(target, primitive_count);
+            state.restore_checkpoint(framebuffer, target);
+
+            let remaining = target - min(state.index(), target);
+            if remaining <= SEEK_PRIMITIVES_PER_FRAME {
+                state.seek_target = None;
+            }
+            min(remaining, SEEK_PRIMITIVES_PER_FRAME)
+        }
+        None if state.paused => 0,
+        None => options.primitives_per_frame,
+    };
+
+    let palette = settings.palette.colours();
+    if state.byte_index == 0 {
+        framebuffer.clear_to(palette[palette.len() - 1]);
+    }
+
+    for _ in 0..primitives_to_draw {
+        if state.byte_index >= bytes.len() {
+            state.byte_index = bytes.len();
+            break;
+        }
+
+        state.record_checkpoint(framebuffer);
+        draw_primitive(
+            framebuffer,
+            state.primitive,
+            bytes,
+            state.byte_index,
+            search,
+            options,
+            settings,
+        );
+        state.byte_index += state.primitive.byte_len();
+    }
+}
+
+// Reads the primitive starting at `start` as coordinates, with every other byte treated as
+// a y coordinate. Both are scaled from 0 to 255 to the size of the screen, with the y
+// coordinates squashed vertically so they stay above the HUD. Bytes past the end read as
+// zero.
+struct Coords<'a> {
+    bytes: &'a [u8],
+    start: usize,
+    width: usize,
+    view_height: usize,
+}
+
+impl<'a> Coords<'a> {
+    fn new(bytes: &'a [u8], start: usize, settings: &Settings) -> Self {
+        Coords {
+            bytes,
+            start,
+            width: settings.screen_width,
+            view_height: settings.view_height(),
+        }
+    }
+
+    fn raw(&self, i: usize) -> u8 {
+        self.bytes.get(self.start + i).cloned().unwrap_or(0)
+    }
+
+    fn x(&self, i: usize) -> usize {
+        self.raw(i) as usize * self.width / 256
+    }
+
+    fn y(&self, i: usize) -> usize {
+        self.raw(i) as usize * self.view_height / 256
+    }
+
+    fn point(&self, i: usize) -> (usize, usize) {
+        (self.x(i * 2), self.y(i * 2 + 1))
+    }
+
+    // Returns the centre and radius, with the radius shrunk so the circle, including its
+    // anti-aliased edge, stays on screen.
+    fn circle(&self) -> (usize, usize, usize) {
+        let (x, y) = (self.x(0), self.y(1));
+        let room = min(
+            min(x, y),
+            min(
+                self.width.saturating_sub(x + 1),
+                self.view_height.saturating_sub(y + 1),
+            ),
+        );
+        let radius = self.raw(2) as usize * self.width / 256;
+
+        (x, y, min(radius, room.saturating_sub(1)))
+    }
+}
+
+fn draw_primitive(
+    framebuffer: &mut Framebuffer,
+    primitive: Primitive,
+    bytes: &[u8],
+    start: usize,
+    search: &Search,
+    options: &GeometricOptions,
+    settings: &Settings,
+) {
+    let coords = Coords::new(bytes, start, settings);
+    let len = primitive.byte_len();
+
+    let palette = settings.palette.colours();
+    let colour = if options.colour_from_bytes {
+        let xor = (0..len).fold(0, |acc, i| acc ^ coords.raw(i));
+        palette[xor as usize % palette.len()]
+    } else {
+        palette[(start / len) % palette.len()]
+    };
+
+    match primitive {
+        Primitive::Quadrilateral => {
+            let ((x0, y0), (x1, y1)) = (coords.point(0), coords.point(1));
+            let ((x2, y2), (x3, y3)) = (coords.point(2), coords.point(3));
+            framebuffer.draw_filled_quad(x0, y0, x1, y1, x2, y2, x3, y3, colour);
+        }
+        Primitive::Triangle => {
+            let ((x0, y0), (x1, y1), (x2, y2)) =
+                (coords.point(0), coords.point(1), coords.point(2));
+            framebuffer.draw_filled_triangle(x0, y0, x1, y1, x2, y2, colour);
+        }
+        Primitive::Line => {
+            let ((x0, y0), (x1, y1)) = (coords.point(0), coords.point(1));
+            framebuffer.draw_line(x0, y0, x1, y1, colour);
+        }
+        Primitive::Circle => {
+            draw_shape(framebuffer, Shape::FilledCircle, coords.circle(), colour);
+        }
+        Primitive::Appearance => {
+            let appearance = Appearance(coords.raw(3));
+            let colour = palette[usize::from(Colour::from(appearance))];
+            draw_shape(
+                framebuffer,
+                Shape::from(appearance),
+                coords.circle(),
+                colour,
+            );
+        }
+    }
+
+    if let Some(colour) = highlight_colour(search, start, len) {
+        outline_primitive(framebuffer, primitive, bytes, start, settings, colour);
+    }
+}
+
+fn draw_shape(
+    framebuffer: &mut Framebuffer,
+    shape: Shape,
+    (x, y, radius): (usize, usize, usize),
+    colour: u32,
+) {
+    if radius == 0 {
+        framebuffer.draw_filled_rect(x, y, 1, 1, colour);
+        return;
+    }
+
+    let (left, top, size) = (x - radius, y - radius, radius * 2 + 1);
+    match shape {
+        Shape::Rectangle => framebuffer.draw_rect(left, top, size, size, colour),
+        Shape::FilledRectangle => framebuffer.draw_filled_rect(left, top, size, size, colour),
+        Shape::Circle => framebuffer.draw_circle(x, y, radius, colour),
+        Shape::FilledCircle => framebuffer.draw_filled_circle(x, y, radius, colour),
+    }
+}
+
+fn primitive_bounds(
+    primitive: Primitive,
+    bytes: &[u8],
+    start: usize,
+    settings: &Settings,
+) -> (usize, usize, usize, usize) {
+    let coords = Coords::new(bytes, start, settings);
+
+    match primitive {
+        Primitive::Circle | Primitive::Appearance => {
+            let (x, y, radius) = coords.circle();
+            (x - radius, y - radius, radius * 2 + 1, radius * 2 + 1)
+        }
+        Primitive::Quadrilateral | Primitive::Triangle | Primitive::Line => {
+            let (x, y) = coords.point(0);
+            let (mut min_x, mut min_y) = (x, y);
+            let (mut max_x, mut max_y) = (min_x, min_y);
+            for i in 1..primitive.byte_len() / 2 {
+                let (x, y) = coords.point(i);
+                min_x = min(min_x, x);
+                max_x = max(max_x, x);
+                min_y = min(min_y, y);
+                max_y = max(max_y, y);
+            }
+
+            (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)
+        }
+    }
+}
+
+fn outline_primitive(
+    framebuffer: &mut Framebuffer,
+    primitive: Primitive,
+    bytes: &[u8],
+    start: usize,
+    settings: &Settings,
+    colour: u32,
+) {
+    let (x, y, w, h) = primitive_bounds(primitive, bytes, start, settings);
+    framebuffer.draw_rect(x, y, w, h, colour);
+}
+
+// Redraws the outlines of any primitives covering the given match which have already been
+// drawn, so the current match stands out without restarting the animation.
+pub fn outline_drawn_primitives_in_match(
+    framebuffer: &mut Framebuffer,
+    state: &GeometricState,
+    bytes: &[u8],
+    search: &Search,
+    match_start: usize,
+    settings: &Settings,
+) {
+    let len = state.primitive.byte_len();
+    let first = match_start / len * len;
+    let match_end = match_start + search.match_len();
+
+    for start in (first..match_end).step_by(len) {
+        if start >= state.byte_index {
+            break;
+        }
+
+        if let Some(colour) = highlight_colour(search, start, len) {
+            outline_primitive(framebuffer, state.primitive, bytes, start, settings, colour);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fields, DEFAULT_BYTES};
+
+    fn seek_and_settle(
+        framebuffer: &mut Framebuffer,
+        state: &mut GeometricState,
+        bytes: &[u8],
+        index: usize,
+    ) {
+        state.seek(index);
+        while state.seek_target.is_some() {
+            update_and_render_geometric(
+                framebuffer,
+                state,
+                Input::new(),
+                &ModeContext {
+                    bytes,
+                    search: &Search::default(),
+                    regions: &[],
+                    fields: &Fields::default(),
+                    selection: None,
+                    settings: &Settings::default(),
+                },
+                &GeometricOptions::default(),
+            );
+        }
+    }
+
+    #[test]
+    fn seeking_backwards_through_a_checkpoint_matches_drawing_straight_through() {
+        let bytes = DEFAULT_BYTES;
+        let target = CHECKPOINT_INTERVAL * 2 + 100;
+        assert!(target < Primitive::Quadrilateral.count(bytes));
+
+        let mut expected = Framebuffer::new();
+        let mut expected_state = GeometricState {
+            paused: true,
+            ..d!()
+        };
+        seek_and_settle(&mut expected, &mut expected_state, bytes, target);
+
+        let mut framebuffer = Framebuffer::new();
+        let mut state = GeometricState {
+            paused: true,
+            ..d!()
+        };
+        seek_and_settle(&mut framebuffer, &mut state, bytes, target + 500);
+        seek_and_settle(&mut framebuffer, &mut state, bytes, target - 700);
+        assert_eq!(state.checkpoints.len(), 2);
+        seek_and_settle(&mut framebuffer, &mut state, bytes, target);
+
+        assert_eq!(state.index(), target);
+        assert!(framebuffer.buffer == expected.buffer);
+    }
+
+    #[test]
+    fn every_primitive_can_be_drawn_from_arbitrary_bytes() {
+        let bytes: Vec<u8> = (0..=255u8).chain((0..=255u8).rev()).collect();
+
+        for &primitive in Primitive::ALL.iter() {
+            for &show_hud in &[false, true] {
+                let settings = Settings { show_hud, ..d!() };
+                let mut framebuffer = Framebuffer::new();
+                for start in 0..bytes.len() {
+                    draw_primitive(
+                        &mut framebuffer,
+                        primitive,
+                        &bytes,
+                        start,
+                        &Search::default(),
+                        &GeometricOptions::default(),
+                        &settings,
+                    );
+                }
+                assert_ne!(
+                    framebuffer.buffer,
+                    Framebuffer::new().buffer,
+                    "{} drew nothing",
+                    primitive.name()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use rendering::{Framebuffer, BLACK, FONT_ADVANCE, GREEN, WHITE_INDEX};
+
+use crate::{ComparisonView, GameState, RenderMode, HUD_HEIGHT};
+
+fn text(state: &GameState) -> String {
+    let (start, end) = state.render_mode.visible_range(
+        state.transformed.len(),
+        &state.pane_settings(state.focused_pane()),
+    );
+
+    let mut text = String::new();
+    if !state.views.is_empty() {
+        text.push_str(&format!("{} ", state.breadcrumb()));
+    }
+    if let Some(file_type) = state.file_type {
+        text.push_str(&format!("{} ", file_type.name()));
+    }
+    text.push_str(&format!(
+        "{} {:X}..{:X} @{:X}/{:X} {}",
+        state.render_mode.kind().name(),
+        start,
+        end,
+        state.cursor,
+        state.transformed.len(),
+        state.settings.bit_order.short_name(),
+    ));
+    if let Some((start, end)) = state.selection {
+        text.push_str(&format!(" sel {:X}..{:X}", start, end));
+    }
+    match state.render_mode {
+        RenderMode::Geometric(ref g_state) => {
+            if g_state.paused {
+                text.push_str(" paused");
+            } else {
+                text.push_str(&format!(
+                    " x{}",
+                    state.mode_options.geometric.primitives_per_frame
+                ));
+            }
+        }
+        RenderMode::Encoding(ref e_state) => {
+            text.push_str(&format!(" {}", state.mode_options.encoding.encoding.name()));
+            if let Some(role) = e_state.role(state.cursor) {
+                text.push_str(&format!(" {}", role.name()));
+            }
+        }
+        RenderMode::Comparison(_) => {
+            let view = state.mode_options.comparison.view;
+            text.push_str(&format!(" {}", view.name()));
+            let differs = if view == ComparisonView::Aligned {
+                state.comparison.changed(state.cursor)
+            } else {
+                state.comparison.differs(state.cursor)
+            };
+            if !state.comparison.is_active() {
+                text.push_str(" no file");
+            } else if differs {
+                text.push_str(" differs");
+            }
+        }
+        RenderMode::ThreeBitsPerPixel(_) => {}
+    }
+
+    text
+}
+
+/// Draws a one line status bar over the bottom `HUD_HEIGHT` rows of the screen, with a
+/// progress bar along its top edge showing how far through the bytes the view has got.
+pub fn render(framebuffer: &mut Framebuffer, state: &GameState) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let top = height.saturating_sub(HUD_HEIGHT);
+    framebuffer.draw_filled_rect(0, top, width, HUD_HEIGHT, BLACK);
+
+    let (_, end) = state.render_mode.visible_range(
+        state.transformed.len(),
+        &state.pane_settings(state.focused_pane()),
+    );
+    if !state.transformed.is_empty() {
+        let progress = width * end / state.transformed.len();
+        framebuffer.draw_filled_rect(0, top, progress, 1, GREEN);
+    }
+
+    let mut text = text(state);
+    text.truncate(width / FONT_ADVANCE as usize);
+    framebuffer.print_line(text.as_bytes(), 0, top + 1, WHITE_INDEX);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileType;
+
+    #[test]
+    fn text_shows_the_mode_range_cursor_and_length_in_hex() {
+        let mut state = GameState::new();
+        state.transformed = vec![0; 0x123];
+        state.cursor = 0x2A;
+
+        assert_eq!(text(&state), "Quadrilateral 0..0 @2A/123 MSB x1");
+
+        state.file_type = Some(FileType::Gzip);
+        assert!(text(&state).starts_with("gzip Quadrilateral "));
+    }
+}
@@ -0,0 +1,83 @@
+use platform_types::Action;
+
+use crate::{fields, GameState, PromptKind, RenderModeKind};
+
+impl GameState {
+    /// Does `action`, unless the menu or a prompt is open. The buttons are pressed through
+    /// `Input` instead, and the frontend does the exporting, so they do nothing here.
+    pub fn act(&mut self, action: Action) {
+        if self.menu.is_open() || self.prompt.is_open() {
+            return;
+        }
+
+        match action {
+            Action::NextMode => self.step_mode(1),
+            Action::PreviousMode => self.step_mode(-1),
+            Action::GoToOffset => self.open_prompt(PromptKind::GoToOffset),
+            Action::Search => self.open_prompt(PromptKind::Search),
+            Action::Template => self.open_prompt(PromptKind::Template),
+            Action::SetBookmark => self.bookmark = Some(self.cursor),
+            // Swapping the two means jumping again goes back to where the jump started.
+            Action::JumpToBookmark => {
+                if let Some(bookmark) = self.bookmark {
+                    if bookmark < self.transformed.len() {
+                        self.bookmark = Some(self.cursor);
+                        self.cursor = bookmark;
+                        self.show_cursor();
+                        fields::follow_cursor(self);
+                    }
+                }
+            }
+            Action::Export
+            | Action::A
+            | Action::B
+            | Action::Select
+            | Action::Start
+            | Action::Up
+            | Action::Down
+            | Action::Left
+            | Action::Right => {}
+        }
+    }
+
+    // Switches the focused pane to the mode `direction` places along `RenderModeKind::ALL`.
+    fn step_mode(&mut self, direction: isize) {
+        let all = RenderModeKind::ALL;
+        let kind = self.render_mode.kind();
+        let i = all.iter().position(|&k| k == kind).unwrap_or(0) as isize;
+        self.render_mode = all[(i + direction).rem_euclid(all.len() as isize) as usize].into();
+        self.reset_render_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_step_around_and_bookmarks_swap_with_the_cursor() {
+        let mut state = GameState::new();
+        state.act(Action::PreviousMode);
+        assert_eq!(
+            state.render_mode.kind(),
+            RenderModeKind::ALL[RenderModeKind::ALL.len() - 1]
+        );
+        state.act(Action::NextMode);
+        state.act(Action::NextMode);
+        assert_eq!(state.render_mode.kind(), RenderModeKind::ALL[1]);
+
+        state.cursor = 5;
+        state.act(Action::SetBookmark);
+        state.cursor = 9;
+        state.act(Action::JumpToBookmark);
+        assert_eq!(state.cursor, 5);
+        state.act(Action::JumpToBookmark);
+        assert_eq!(state.cursor, 9);
+
+        state.act(Action::Search);
+        assert_eq!(state.prompt.kind(), Some(PromptKind::Search));
+        // Nothing else happens while the prompt is open.
+        state.act(Action::NextMode);
+        assert_eq!(state.render_mode.kind(), RenderModeKind::ALL[1]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+
+use crate::{prompt::parse_offset, GameState, Palette, RenderModeKind, ZOOM_LEVELS};
+
+/// Bumped whenever a link would mean something different to this version.
+pub const LINK_VERSION: u32 = 1;
+
+/// Where to look in a file and how, compact enough to put in a URL, so a view can be shared
+/// with someone who has the same file. As text it looks like
+/// `v1&mode=3-bits-per-pixel&offset=0x400&zoom=2&palette=heat`: the version, then any of
+/// the fields, with the names lower case and dashed. Anything left out stays as it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Link {
+    pub mode: Option<RenderModeKind>,
+    pub offset: Option<usize>,
+    pub zoom: Option<u8>,
+    pub palette: Option<Palette>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkError {
+    /// The link doesn't start with the version, or it is a version this doesn't know.
+    Version(String),
+    UnknownField(String),
+    BadValue {
+        field: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LinkError::Version(ref version) => write!(
+                f,
+                "expected v{} at the start but found {:?}",
+                LINK_VERSION, version
+            ),
+            LinkError::UnknownField(ref field) => write!(f, "unknown field {:?}", field),
+            LinkError::BadValue {
+                ref field,
+                ref value,
+            } => write!(f, "{:?} is not a {}", value, field),
+        }
+    }
+}
+
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+fn pick<T: Copy>(all: &[T], name: fn(T) -> &'static str, value: &str) -> Option<T> {
+    all.iter().cloned().find(|&t| slug(name(t)) == value)
+}
+
+impl Link {
+    pub fn parse(text: &str) -> Result<Link, LinkError> {
+        let mut parts = text.split('&');
+        let version = parts.next().unwrap_or_default();
+        if version != format!("v{}", LINK_VERSION) {
+            return Err(LinkError::Version(version.to_string()));
+        }
+
+        let mut link = Link::default();
+        for part in parts.filter(|part| !part.is_empty()) {
+            let (field, value) = part.split_once('=').unwrap_or((part, ""));
+            let bad_value = || LinkError::BadValue {
+                field: field.to_string(),
+                value: value.to_string(),
+            };
+            match field {
+                "mode" => {
+                    link.mode = Some(
+                        pick(&RenderModeKind::ALL, RenderModeKind::name, value)
+                            .ok_or_else(bad_value)?,
+                    )
+                }
+                "offset" => link.offset = Some(parse_offset(value).ok_or_else(bad_value)?),
+                "zoom" => {
+                    link.zoom = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|zoom| ZOOM_LEVELS.contains(zoom))
+                            .ok_or_else(bad_value)?,
+                    )
+                }
+                "palette" => {
+                    link.palette =
+                        Some(pick(&Palette::ALL, Palette::name, value).ok_or_else(bad_value)?)
+                }
+                _ => return Err(LinkError::UnknownField(field.to_string())),
+            }
+        }
+        Ok(link)
+    }
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", LINK_VERSION)?;
+        if let Some(mode) = self.mode {
+            write!(f, "&mode={}", slug(mode.name()))?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, "&offset=0x{:x}", offset)?;
+        }
+        if let Some(zoom) = self.zoom {
+            write!(f, "&zoom={}", zoom)?;
+        }
+        if let Some(palette) = self.palette {
+            write!(f, "&palette={}", slug(palette.name()))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState {
+    /// A link to what the focused pane is showing.
+    pub fn link(&self) -> Link {
+        Link {
+            mode: Some(self.render_mode.kind()),
+            offset: Some(self.cursor),
+            zoom: Some(self.settings.zoom),
+            palette: Some(self.settings.palette),
+        }
+    }
+
+    /// Shows what `link` points to. Offsets past the end of the bytes go to the last byte.
+    pub fn follow_link(&mut self, link: Link) {
+        if let Some(mode) = link.mode {
+            self.render_mode = mode.into();
+        }
+        if let Some(offset) = link.offset {
+            self.cursor = std::cmp::min(offset, self.transformed.len().saturating_sub(1));
+            self.selection = None;
+        }
+        if let Some(zoom) = link.zoom {
+            self.settings.zoom = zoom;
+        }
+        if let Some(palette) = link.palette {
+            self.settings.palette = palette;
+        }
+        self.reset_render_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Primitive;
+
+    #[test]
+    fn links_come_back_the_same_from_text() {
+        let link = Link {
+            mode: Some(RenderModeKind::ThreeBitsPerPixel),
+            offset: Some(0x400),
+            zoom: Some(2),
+            palette: Some(Palette::Heat),
+        };
+        let text = link.to_string();
+        assert_eq!(
+            text,
+            "v1&mode=3-bits-per-pixel&offset=0x400&zoom=2&palette=heat"
+        );
+        assert_eq!(Link::parse(&text), Ok(link));
+
+        assert_eq!(
+            Link::parse("v1&offset=1024&mode=triangle"),
+            Ok(Link {
+                mode: Some(RenderModeKind::Geometric(Primitive::Triangle)),
+                offset: Some(1024),
+                ..Link::default()
+            })
+        );
+        assert_eq!(Link::parse("v1"), Ok(Link::default()));
+    }
+
+    #[test]
+    fn bad_links_say_what_is_wrong() {
+        assert_eq!(
+            Link::parse("v2&zoom=2"),
+            Err(LinkError::Version("v2".to_string()))
+        );
+        assert_eq!(
+            Link::parse("mode=comparison"),
+            Err(LinkError::Version("mode=comparison".to_string()))
+        );
+        assert_eq!(
+            Link::parse("v1&zoom=3"),
+            Err(LinkError::BadValue {
+                field: "zoom".to_string(),
+                value: "3".to_string(),
+            })
+        );
+        assert_eq!(
+            Link::parse("v1&speed=3"),
+            Err(LinkError::UnknownField("speed".to_string()))
+        );
+    }
+
+    #[test]
+    fn following_a_link_leaves_out_fields_alone() {
+        let mut state = GameState::new();
+        state.follow_link(Link::parse("v1&mode=text-encoding&offset=0x10").unwrap());
+        assert_eq!(state.render_mode.kind(), RenderModeKind::Encoding);
+        assert_eq!(state.cursor, 0x10);
+        assert_eq!(state.settings.palette, Palette::Default);
+
+        state.follow_link(Link::parse("v1&palette=greyscale&offset=0xFFFFFF").unwrap());
+        assert_eq!(state.render_mode.kind(), RenderModeKind::Encoding);
+        assert_eq!(state.cursor, state.transformed.len() - 1);
+        assert_eq!(state.link().palette, Some(Palette::Greyscale));
+    }
+}
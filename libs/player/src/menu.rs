@@ -0,0 +1,759 @@
+use platform_types::{Action, Button, Input, KeyBindings};
+use rendering::{Framebuffer, FONT_ADVANCE, RED_INDEX, SPRITE_SIZE, WHITE_INDEX, YELLOW_INDEX};
+use std::cmp::{max, min};
+
+use crate::{
+    redraw_mode, BitOrder, ComparisonView, Encoding, FieldPanel, GameState, Palette, Primitive,
+    PromptKind, RenderMode, RenderModeKind, SectionMap, Transform, MAX_PANES, PRIMITIVE_SPEEDS,
+    ZOOM_LEVELS,
+};
+
+const SPRITE: usize = SPRITE_SIZE as usize;
+
+const WINDOW_X: usize = SPRITE * 2;
+const WINDOW_Y: usize = SPRITE * 2;
+
+const ROW_X: usize = WINDOW_X + SPRITE;
+const ROW_HEIGHT: usize = SPRITE + 2;
+
+/// The pause menu, opened and closed with Start.
+#[derive(Default)]
+pub struct Menu {
+    open: bool,
+    selected: usize,
+    scroll: usize,
+    // What was on screen when the menu was opened, so it can be put back if nothing changed.
+    backdrop: Vec<u32>,
+    changed: bool,
+    // Shown at the bottom of the menu, for when an item couldn't do what it was asked.
+    message: Option<String>,
+    // An index into `Transform::KINDS`, for the kind of transform to add next.
+    new_transform: usize,
+    // The action the next key pressed will be bound to, while waiting for one.
+    binding: Option<Action>,
+}
+
+impl Menu {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Keeps what is behind the menu up to date when the mode is drawn again underneath it.
+    pub(crate) fn redrawn(&mut self, framebuffer: &Framebuffer) {
+        if self.open {
+            self.backdrop = framebuffer.buffer.clone();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Heading(&'static str),
+    Mode(RenderModeKind),
+    GeometricColourFromBytes,
+    GeometricSpeed,
+    GeometricPosition,
+    ThreeBitsPerPixelRepeatBytes,
+    TextEncoding,
+    ComparisonViewSetting,
+    /// Opens a prompt to type in what to find.
+    Prompt(PromptKind),
+    PaletteSetting,
+    BitOrderSetting,
+    ZoomSetting,
+    ShowHudSetting,
+    SectionMapSetting,
+    FieldPanelSetting,
+    PaneCount,
+    FocusedPane,
+    /// The keys bound to an action. A binds another, and Left takes them all away.
+    Key(Action),
+    ResetKeys,
+    CloseView,
+    InflateAtCursor,
+    /// An index into `GameState::streams`.
+    Stream(usize),
+    /// An index into `GameState::transforms()`.
+    Transform(usize),
+    AddTransform,
+}
+
+fn items(state: &GameState) -> Vec<Item> {
+    let mut items = vec![Item::Heading("Mode")];
+    items.extend(RenderModeKind::ALL.iter().map(|&kind| Item::Mode(kind)));
+
+    let mode_items: &[Item] = match state.render_mode.kind() {
+        // The appearance byte already picks the colour.
+        RenderModeKind::Geometric(Primitive::Appearance) => {
+            &[Item::GeometricSpeed, Item::GeometricPosition]
+        }
+        RenderModeKind::Geometric(_) => &[
+            Item::GeometricColourFromBytes,
+            Item::GeometricSpeed,
+            Item::GeometricPosition,
+        ],
+        RenderModeKind::ThreeBitsPerPixel => &[Item::ThreeBitsPerPixelRepeatBytes],
+        RenderModeKind::Encoding => &[Item::TextEncoding],
+        RenderModeKind::Comparison => &[Item::ComparisonViewSetting],
+    };
+    if !mode_items.is_empty() {
+        items.push(Item::Heading("Mode options"));
+        items.extend_from_slice(mode_items);
+    }
+
+    items.push(Item::Heading("Find"));
+    items.extend(PromptKind::ALL.iter().map(|&kind| Item::Prompt(kind)));
+
+    items.push(Item::Heading("Settings"));
+    items.push(Item::PaletteSetting);
+    items.push(Item::BitOrderSetting);
+    items.push(Item::ZoomSetting);
+    items.push(Item::ShowHudSetting);
+    items.push(Item::SectionMapSetting);
+    items.push(Item::FieldPanelSetting);
+
+    items.push(Item::Heading("Panes"));
+    items.push(Item::PaneCount);
+    if state.pane_count() > 1 {
+        items.push(Item::FocusedPane);
+    }
+
+    items.push(Item::Heading("Keys"));
+    items.extend(Action::ALL.iter().map(|&action| Item::Key(action)));
+    items.push(Item::ResetKeys);
+
+    items.push(Item::Heading("Streams"));
+    if !state.views.is_empty() {
+        items.push(Item::CloseView);
+    }
+    items.push(Item::InflateAtCursor);
+    items.extend((0..state.streams.len()).map(Item::Stream));
+
+    items.push(Item::Heading("Transforms"));
+    items.extend((0..state.transforms().len()).map(Item::Transform));
+    items.push(Item::AddTransform);
+
+    items
+}
+
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, direction: isize) -> T {
+    let i = all.iter().position(|&t| t == current).unwrap_or(0) as isize;
+    all[(i + direction).rem_euclid(all.len() as isize) as usize]
+}
+
+impl Item {
+    fn is_selectable(self) -> bool {
+        !matches!(self, Item::Heading(_))
+    }
+
+    // Whether the mode has to start over from the beginning once the menu closes.
+    fn needs_reset(self) -> bool {
+        !matches!(
+            self,
+            Item::GeometricSpeed
+                | Item::GeometricPosition
+                | Item::FocusedPane
+                | Item::Prompt(_)
+                | Item::Key(_)
+                | Item::ResetKeys
+        )
+    }
+
+    // Whether the bytes shown behind the menu change, so the mode should be drawn again
+    // while the menu is still open.
+    fn changes_bytes(self) -> bool {
+        matches!(self, Item::Transform(_) | Item::AddTransform)
+    }
+
+    // Whether the menu should close once the item has done its thing, so the result can be
+    // seen.
+    fn closes_menu(self) -> bool {
+        matches!(
+            self,
+            Item::CloseView | Item::InflateAtCursor | Item::Stream(_) | Item::Prompt(_)
+        )
+    }
+
+    fn label(self, state: &GameState) -> String {
+        let label = match self {
+            Item::CloseView => {
+                let opened = state.views.last().map(|view| view.opened.as_str());
+                return format!("Back from {}", opened.unwrap_or(""));
+            }
+            Item::Stream(i) => {
+                let stream = state.streams[i];
+                return format!("{} at {:X}", stream.format.name(), stream.offset);
+            }
+            Item::Transform(i) => {
+                return format!("{}. {}", i + 1, state.transforms()[i].name());
+            }
+            Item::Heading(text) => text,
+            Item::Mode(kind) => kind.name(),
+            Item::Prompt(kind) => kind.name(),
+            Item::Key(action) => action.name(),
+            Item::ResetKeys => "Reset keys",
+            Item::GeometricColourFromBytes => "Colour from bytes",
+            Item::GeometricSpeed => "Shapes per frame",
+            Item::GeometricPosition => "Position",
+            Item::ThreeBitsPerPixelRepeatBytes => "Repeat bytes",
+            Item::TextEncoding => "Encoding",
+            Item::ComparisonViewSetting => "View",
+            Item::PaletteSetting => "Palette",
+            Item::BitOrderSetting => "Bit order",
+            Item::ZoomSetting => "Zoom",
+            Item::ShowHudSetting => "Show HUD",
+            Item::SectionMapSetting => "Section map",
+            Item::FieldPanelSetting => "Field panel",
+            Item::PaneCount => "Panes",
+            Item::FocusedPane => "Focused pane",
+            Item::InflateAtCursor => "Inflate at cursor",
+            Item::AddTransform => "Add",
+        };
+        label.to_string()
+    }
+
+    fn checked(self, state: &GameState) -> Option<bool> {
+        match self {
+            Item::Mode(kind) => Some(state.render_mode.kind() == kind),
+            Item::GeometricColourFromBytes => Some(state.mode_options.geometric.colour_from_bytes),
+            Item::ThreeBitsPerPixelRepeatBytes => {
+                Some(state.mode_options.three_bits_per_pixel.repeat_bytes)
+            }
+            Item::ShowHudSetting => Some(state.settings.show_hud),
+            Item::Transform(_) => Some(true),
+            _ => None,
+        }
+    }
+
+    fn value(self, state: &GameState) -> Option<String> {
+        match self {
+            Item::TextEncoding => Some(state.mode_options.encoding.encoding.name().to_string()),
+            Item::ComparisonViewSetting => {
+                Some(state.mode_options.comparison.view.name().to_string())
+            }
+            Item::PaletteSetting => Some(state.settings.palette.name().to_string()),
+            Item::BitOrderSetting => Some(state.settings.bit_order.name().to_string()),
+            Item::ZoomSetting => Some(format!("{}x", state.settings.zoom)),
+            Item::SectionMapSetting => Some(state.settings.section_map.name().to_string()),
+            Item::FieldPanelSetting => Some(state.settings.field_panel.name().to_string()),
+            Item::PaneCount => Some(state.pane_count().to_string()),
+            Item::Key(action) => {
+                if state.menu.binding == Some(action) {
+                    return Some("press a key".to_string());
+                }
+                let keys: Vec<&str> = state.key_bindings.keys_for(action).collect();
+                Some(if keys.is_empty() {
+                    "none".to_string()
+                } else {
+                    keys.join(" ")
+                })
+            }
+            Item::FocusedPane => Some((state.focused_pane() + 1).to_string()),
+            Item::Transform(i) => state.transforms()[i].parameter(),
+            Item::AddTransform => Some(
+                Transform::KINDS[state.menu.new_transform]
+                    .name()
+                    .to_string(),
+            ),
+            Item::GeometricSpeed => Some(
+                state
+                    .mode_options
+                    .geometric
+                    .primitives_per_frame
+                    .to_string(),
+            ),
+            Item::GeometricPosition => match state.render_mode {
+                RenderMode::Geometric(ref g_state) => {
+                    let count = g_state.primitive.count(&state.transformed);
+                    let target = min(g_state.target_index(), count);
+                    Some(format!("{}%", target * 100 / max(count, 1)))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn adjust(self, state: &mut GameState, direction: isize) {
+        match self {
+            Item::Heading(_) => {}
+            Item::Prompt(kind) => state.open_prompt(kind),
+            Item::Mode(kind) => {
+                if state.render_mode.kind() != kind {
+                    state.render_mode = kind.into();
+                }
+            }
+            Item::GeometricColourFromBytes => {
+                let options = &mut state.mode_options.geometric;
+                options.colour_from_bytes = !options.colour_from_bytes;
+            }
+            Item::GeometricSpeed => {
+                let options = &mut state.mode_options.geometric;
+                options.primitives_per_frame =
+                    cycle(&PRIMITIVE_SPEEDS, options.primitives_per_frame, direction);
+            }
+            Item::GeometricPosition => {
+                if let RenderMode::Geometric(ref mut g_state) = state.render_mode {
+                    // Step in tenths of the file, rounding to the nearest tenth first so
+                    // repeated steps land on round numbers.
+                    let count = g_state.primitive.count(&state.transformed);
+                    let step = max(count / 10, 1);
+                    let current = min(g_state.target_index(), count);
+                    let tenth = (current + step / 2) / step;
+                    let target = (tenth as isize + direction).max(0) as usize * step;
+                    g_state.seek(min(target, count));
+                }
+            }
+            Item::ThreeBitsPerPixelRepeatBytes => {
+                let options = &mut state.mode_options.three_bits_per_pixel;
+                options.repeat_bytes = !options.repeat_bytes;
+            }
+            Item::TextEncoding => {
+                let options = &mut state.mode_options.encoding;
+                options.encoding = cycle(&Encoding::ALL, options.encoding, direction);
+            }
+            Item::ComparisonViewSetting => {
+                let options = &mut state.mode_options.comparison;
+                options.view = cycle(&ComparisonView::ALL, options.view, direction);
+            }
+            Item::PaletteSetting => {
+                state.settings.palette = cycle(&Palette::ALL, state.settings.palette, direction);
+            }
+            Item::BitOrderSetting => {
+                state.settings.bit_order =
+                    cycle(&BitOrder::ALL, state.settings.bit_order, direction);
+            }
+            Item::ZoomSetting => {
+                state.settings.zoom = cycle(&ZOOM_LEVELS, state.settings.zoom, direction);
+            }
+            Item::ShowHudSetting => {
+                state.settings.show_hud = !state.settings.show_hud;
+            }
+            Item::SectionMapSetting => {
+                state.settings.section_map =
+                    cycle(&SectionMap::ALL, state.settings.section_map, direction);
+            }
+            Item::FieldPanelSetting => {
+                state.settings.field_panel =
+                    cycle(&FieldPanel::ALL, state.settings.field_panel, direction);
+            }
+            Item::PaneCount => {
+                let counts: Vec<usize> = (1..=MAX_PANES).collect();
+                state.set_pane_count(cycle(&counts, state.pane_count(), direction));
+            }
+            Item::FocusedPane => {
+                let count = state.pane_count() as isize;
+                let focused = state.focused_pane() as isize + direction;
+                state.focus_pane(focused.rem_euclid(count) as usize);
+            }
+            Item::Key(action) => {
+                if direction > 0 {
+                    state.menu.binding = Some(action);
+                } else if action == Action::Start {
+                    state.menu.message = Some("Start needs a key to open the menu".to_string());
+                } else {
+                    state.key_bindings.unbind_action(action);
+                }
+            }
+            Item::ResetKeys => {
+                state.key_bindings = KeyBindings::default();
+            }
+            Item::CloseView => {
+                state.close_view();
+            }
+            Item::InflateAtCursor => {
+                if let Err(e) = state.inflate_at_cursor() {
+                    state.menu.message = Some(format!("Could not inflate: {}", e));
+                }
+            }
+            Item::Stream(i) => {
+                if let Err(e) = state.open_stream(state.streams[i]) {
+                    state.menu.message = Some(format!("Could not open: {}", e));
+                }
+            }
+            Item::Transform(i) => {
+                let mut transform = state.transforms()[i].clone();
+                transform.adjust(direction);
+                if transform != state.transforms()[i] {
+                    state.set_transform(i, transform);
+                }
+            }
+            Item::AddTransform => {
+                let kinds = Transform::KINDS.len() as isize;
+                state.menu.new_transform =
+                    (state.menu.new_transform as isize + direction).rem_euclid(kinds) as usize;
+            }
+        }
+    }
+
+    // Does what A does for the items where that isn't the same as Right. Returns false for
+    // the other items.
+    fn press(self, state: &mut GameState) -> bool {
+        match self {
+            // Unchecking a transform's box removes it.
+            Item::Transform(i) => {
+                state.remove_transform(i);
+                true
+            }
+            Item::AddTransform => {
+                state.push_transform(Transform::KINDS[state.menu.new_transform].clone());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl GameState {
+    /// Whether the key settings are waiting for a key to bind.
+    pub fn binding_key(&self) -> bool {
+        self.menu.binding.is_some()
+    }
+
+    /// Binds `key` to the action the key settings are waiting on. Escape cancels instead,
+    /// and the last key for Start can't be taken away, since the menu couldn't be opened
+    /// to put it back.
+    pub fn bind_key(&mut self, key: &str) {
+        let action = match self.menu.binding.take() {
+            Some(action) => action,
+            None => return,
+        };
+        if key == "Escape" {
+            return;
+        }
+
+        let keys_for_start: Vec<&str> = self.key_bindings.keys_for(Action::Start).collect();
+        if action != Action::Start && keys_for_start == [key] {
+            self.menu.message = Some(format!("{} is the only key for Start", key));
+            return;
+        }
+        self.key_bindings.bind(key, action);
+    }
+}
+
+/// Returns true if the menu was open this frame, in which case nothing else should
+/// respond to the input.
+pub fn update_and_render(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+) -> bool {
+    let mut items = items(state);
+    // Loading other bytes while the menu is open can take away items below the selection.
+    state.menu.selected = min(state.menu.selected, items.len() - 1);
+
+    if !state.menu.open {
+        if !input.pressed_this_frame(Button::Start) {
+            return false;
+        }
+
+        let current_mode = Item::Mode(state.render_mode.kind());
+        state.menu = Menu {
+            open: true,
+            selected: items.iter().position(|&i| i == current_mode).unwrap_or(0),
+            scroll: 0,
+            backdrop: framebuffer.buffer.clone(),
+            changed: false,
+            message: None,
+            new_transform: state.menu.new_transform,
+            binding: None,
+        };
+    } else if input.pressed_this_frame(Button::Start) || input.pressed_this_frame(Button::B) {
+        close(framebuffer, state);
+        return true;
+    } else {
+        if input.pressed_this_frame(Button::Up) {
+            state.menu.selected = step_selection(&items, state.menu.selected, -1);
+        } else if input.pressed_this_frame(Button::Down) {
+            state.menu.selected = step_selection(&items, state.menu.selected, 1);
+        }
+
+        // Typing a parameter is quicker than stepping through every value on the way.
+        if let Item::Transform(i) = items[state.menu.selected] {
+            if input.pressed_this_frame(Button::Select)
+                && state.transforms()[i].parameter().is_some()
+            {
+                state.open_parameter_prompt(i);
+                close(framebuffer, state);
+                return true;
+            }
+        }
+
+        let pressed_a = input.pressed_this_frame(Button::A);
+        let direction = if pressed_a || input.pressed_this_frame(Button::Right) {
+            1
+        } else if input.pressed_this_frame(Button::Left) {
+            -1
+        } else {
+            0
+        };
+
+        if direction != 0 {
+            let item = items[state.menu.selected];
+            state.menu.message = None;
+            if !(pressed_a && item.press(state)) {
+                item.adjust(state, direction);
+            }
+            state.menu.changed |= item.needs_reset();
+
+            if item.changes_bytes() {
+                redraw_mode(framebuffer, state);
+                state.menu.redrawn(framebuffer);
+            }
+
+            if item.closes_menu() && state.menu.message.is_none() {
+                close(framebuffer, state);
+                return true;
+            }
+
+            // Switching modes changes which options are listed, so keep the same item selected.
+            // A removed transform's place goes to whatever came after it.
+            items = self::items(state);
+            state.menu.selected = items
+                .iter()
+                .position(|&i| i == item)
+                .unwrap_or_else(|| min(state.menu.selected, items.len() - 1));
+        }
+    }
+
+    render(framebuffer, state, &items, input);
+
+    true
+}
+
+fn close(framebuffer: &mut Framebuffer, state: &mut GameState) {
+    state.menu.open = false;
+
+    if state.menu.changed {
+        state.reset_render_mode();
+    } else if state.menu.backdrop.len() == framebuffer.buffer.len() {
+        framebuffer.buffer.copy_from_slice(&state.menu.backdrop);
+    }
+}
+
+fn step_selection(items: &[Item], selected: usize, direction: isize) -> usize {
+    let mut i = selected;
+    for _ in 0..items.len() {
+        i = (i as isize + direction).rem_euclid(items.len() as isize) as usize;
+        if items[i].is_selectable() {
+            return i;
+        }
+    }
+    selected
+}
+
+fn render(framebuffer: &mut Framebuffer, state: &mut GameState, items: &[Item], input: Input) {
+    if state.menu.backdrop.len() == framebuffer.buffer.len() {
+        framebuffer.buffer.copy_from_slice(&state.menu.backdrop);
+    }
+
+    // The window leaves a two sprite border around it, whatever the size of the screen.
+    let window_w = framebuffer.width.saturating_sub(SPRITE * 4);
+    let window_h = framebuffer.height.saturating_sub(SPRITE * 4);
+    let row_w = window_w.saturating_sub(SPRITE * 2);
+    framebuffer.window(WINDOW_X, WINDOW_Y, window_w, window_h);
+
+    // The message takes the place of the last row.
+    let menu = &mut state.menu;
+    let rows = max(window_h.saturating_sub(SPRITE * 2) / ROW_HEIGHT, 2);
+    let visible_rows = if menu.message.is_some() {
+        rows - 1
+    } else {
+        rows
+    };
+    if menu.selected < menu.scroll {
+        menu.scroll = menu.selected;
+    } else if menu.selected >= menu.scroll + visible_rows {
+        menu.scroll = menu.selected + 1 - visible_rows;
+    }
+    // Show the heading above the first item when scrolled all the way up.
+    if menu.scroll == 1 {
+        menu.scroll = 0;
+    }
+
+    let (selected, scroll) = (menu.selected, menu.scroll);
+    for (row, &item) in items.iter().enumerate().skip(scroll).take(visible_rows) {
+        let y = WINDOW_Y + SPRITE + (row - scroll) * ROW_HEIGHT;
+
+        if !item.is_selectable() {
+            framebuffer.print_line(item.label(state).as_bytes(), ROW_X, y, YELLOW_INDEX);
+            continue;
+        }
+
+        let is_selected = row == selected;
+        if is_selected && input.gamepad.contains(Button::A) {
+            framebuffer.row_pressed(ROW_X, y, row_w);
+        } else if is_selected {
+            framebuffer.row_hot(ROW_X, y, row_w);
+        } else {
+            framebuffer.row(ROW_X, y, row_w);
+        }
+
+        let label_x = ROW_X + SPRITE + 4;
+        if let Some(checked) = item.checked(state) {
+            if is_selected {
+                framebuffer.checkbox_hot(ROW_X + 2, y, checked);
+            } else {
+                framebuffer.checkbox(ROW_X + 2, y, checked);
+            }
+        }
+        framebuffer.print_line(item.label(state).as_bytes(), label_x, y, WHITE_INDEX);
+
+        if let Some(value) = item.value(state) {
+            let text = format!("< {} >", value);
+            let x = (ROW_X + row_w).saturating_sub(text.len() * FONT_ADVANCE as usize + 2);
+            framebuffer.print_line(text.as_bytes(), x, y, WHITE_INDEX);
+        }
+    }
+
+    if let Some(ref message) = state.menu.message {
+        let mut message = message.clone();
+        message.truncate(row_w / FONT_ADVANCE as usize);
+        let y = WINDOW_Y + SPRITE + visible_rows * ROW_HEIGHT;
+        framebuffer.print_line(message.as_bytes(), ROW_X, y, RED_INDEX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_BYTES;
+
+    fn press(state: &mut GameState, framebuffer: &mut Framebuffer, button: Button::Ty) {
+        let input = Input {
+            gamepad: button,
+            previous_gamepad: Button::Ty::empty(),
+            ..Input::new()
+        };
+        update_and_render(framebuffer, state, input);
+    }
+
+    #[test]
+    fn step_selection_skips_headings() {
+        let items = [
+            Item::Heading("a"),
+            Item::PaletteSetting,
+            Item::Heading("b"),
+            Item::ZoomSetting,
+        ];
+
+        assert_eq!(step_selection(&items, 1, 1), 3);
+        assert_eq!(step_selection(&items, 3, 1), 1);
+        assert_eq!(step_selection(&items, 1, -1), 3);
+    }
+
+    #[test]
+    fn cycle_wraps_in_both_directions() {
+        assert_eq!(cycle(&ZOOM_LEVELS, 1, -1), 8);
+        assert_eq!(cycle(&ZOOM_LEVELS, 8, 1), 1);
+        assert_eq!(cycle(&ZOOM_LEVELS, 2, 1), 4);
+    }
+
+    #[test]
+    fn the_menu_can_switch_modes_with_the_d_pad_and_a() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        assert_eq!(
+            state.render_mode.kind(),
+            RenderModeKind::Geometric(Primitive::Quadrilateral)
+        );
+
+        press(&mut state, &mut framebuffer, Button::Start);
+        assert!(state.menu.is_open());
+
+        for _ in 0..Primitive::ALL.len() {
+            press(&mut state, &mut framebuffer, Button::Down);
+        }
+        press(&mut state, &mut framebuffer, Button::A);
+        press(&mut state, &mut framebuffer, Button::B);
+
+        assert!(!state.menu.is_open());
+        assert_eq!(state.render_mode.kind(), RenderModeKind::ThreeBitsPerPixel);
+    }
+
+    #[test]
+    fn transforms_can_be_added_adjusted_and_removed() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+
+        press(&mut state, &mut framebuffer, Button::Start);
+        // Going up from the first mode wraps around to the add item at the bottom.
+        press(&mut state, &mut framebuffer, Button::Up);
+        press(&mut state, &mut framebuffer, Button::A);
+        assert_eq!(state.transforms(), [Transform::Xor(Vec::new())]);
+
+        press(&mut state, &mut framebuffer, Button::Up);
+        press(&mut state, &mut framebuffer, Button::Right);
+        assert_eq!(state.transforms(), [Transform::Xor(vec![1])]);
+        assert_eq!(state.transformed[0], DEFAULT_BYTES[0] ^ 1);
+
+        press(&mut state, &mut framebuffer, Button::A);
+        assert!(state.transforms().is_empty());
+        assert_eq!(items(&state)[state.menu.selected], Item::AddTransform);
+    }
+
+    #[test]
+    fn select_types_the_parameter_of_a_transform() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        state.push_transform(Transform::Skip(0x10));
+
+        press(&mut state, &mut framebuffer, Button::Start);
+        state.menu.selected = items(&state)
+            .iter()
+            .position(|&i| i == Item::Transform(0))
+            .unwrap();
+        press(&mut state, &mut framebuffer, Button::Select);
+
+        assert!(!state.menu.is_open());
+        assert_eq!(state.prompt.kind(), Some(PromptKind::Parameter(0)));
+        assert_eq!(state.prompt.text(), "10");
+    }
+
+    #[test]
+    fn the_selection_stays_in_the_menu_when_items_go_away() {
+        // "hello hello hello hello" compressed with zlib, which is listed as a stream.
+        let zlib_hello = [
+            120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177,
+        ];
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        state.load_bytes(zlib_hello.repeat(3));
+        assert!(!state.streams.is_empty());
+
+        press(&mut state, &mut framebuffer, Button::Start);
+        state.menu.selected = items(&state).len() - 1;
+        state.load_bytes(vec![0; 16]);
+        press(&mut state, &mut framebuffer, Button::Left);
+
+        assert!(state.menu.is_open());
+        assert!(state.menu.selected < items(&state).len());
+    }
+
+    #[test]
+    fn keys_can_be_bound_but_start_keeps_its_last_key() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        let select = |state: &mut GameState, item| {
+            state.menu.selected = items(state).iter().position(|&i| i == item).unwrap();
+        };
+
+        press(&mut state, &mut framebuffer, Button::Start);
+        select(&mut state, Item::Key(Action::Search));
+        press(&mut state, &mut framebuffer, Button::A);
+        assert!(state.binding_key());
+        state.bind_key("KeyS");
+        assert!(!state.binding_key());
+        assert_eq!(state.key_bindings.lookup("KeyS"), Some(Action::Search));
+
+        select(&mut state, Item::Key(Action::B));
+        press(&mut state, &mut framebuffer, Button::A);
+        state.bind_key("Enter");
+        assert_eq!(state.key_bindings.lookup("Enter"), Some(Action::Start));
+        assert!(state.menu.message.is_some());
+
+        select(&mut state, Item::ResetKeys);
+        press(&mut state, &mut framebuffer, Button::A);
+        assert_eq!(state.key_bindings, KeyBindings::default());
+    }
+}
@@ -0,0 +1,330 @@
+use rendering::{Framebuffer, Viewport, GREY, WHITE};
+use std::cmp::min;
+use std::mem;
+
+use crate::{GameState, RenderMode, RenderModeKind, Settings};
+
+/// The most panes the screen can be split into.
+pub const MAX_PANES: usize = 4;
+
+// Where each of `count` panes goes in a `width` by `height` area, in reading order, with a
+// one pixel gap between them for the dividers. Two panes go side by side, a third splits
+// the right one, and a fourth splits the left one too.
+fn layout(count: usize, width: usize, height: usize) -> Vec<Viewport> {
+    let rect = |x, y, w, h| Viewport { x, y, w, h };
+    if count <= 1 {
+        return vec![rect(0, 0, width, height)];
+    }
+
+    let left_w = width.saturating_sub(1) / 2;
+    let right_x = min(left_w + 1, width);
+    let right_w = width - right_x;
+    let top_h = height.saturating_sub(1) / 2;
+    let bottom_y = min(top_h + 1, height);
+    let bottom_h = height - bottom_y;
+
+    match count {
+        2 => vec![
+            rect(0, 0, left_w, height),
+            rect(right_x, 0, right_w, height),
+        ],
+        3 => vec![
+            rect(0, 0, left_w, height),
+            rect(right_x, 0, right_w, top_h),
+            rect(right_x, bottom_y, right_w, bottom_h),
+        ],
+        _ => vec![
+            rect(0, 0, left_w, top_h),
+            rect(right_x, 0, right_w, top_h),
+            rect(0, bottom_y, left_w, bottom_h),
+            rect(right_x, bottom_y, right_w, bottom_h),
+        ],
+    }
+}
+
+// The settings the mode in `pane` sees. As far as it can tell the pane is the whole screen,
+// with no HUD on it.
+fn pane_settings(settings: &Settings, pane: Viewport) -> Settings {
+    Settings {
+        screen_width: pane.w,
+        screen_height: pane.h,
+        show_hud: false,
+        ..*settings
+    }
+}
+
+impl GameState {
+    pub fn pane_count(&self) -> usize {
+        self.other_panes.len() + 1
+    }
+
+    /// The index of the pane that the D-pad and the mode menu control.
+    pub fn focused_pane(&self) -> usize {
+        self.focused_pane
+    }
+
+    /// Where each pane is on screen, in the same order as `focus_pane` counts them.
+    pub fn pane_viewports(&self) -> Vec<Viewport> {
+        layout(
+            self.pane_count(),
+            self.settings.screen_width,
+            self.settings.view_height(),
+        )
+    }
+
+    /// The settings the mode in the pane at `index` sees, with the pane as its screen.
+    pub fn pane_settings(&self, index: usize) -> Settings {
+        let viewports = self.pane_viewports();
+        pane_settings(&self.settings, viewports[min(index, viewports.len() - 1)])
+    }
+
+    /// Splits the screen into `count` panes, from 1 to `MAX_PANES`, which all show the same
+    /// bytes and share the cursor. New panes start with the mode after the last pane's one,
+    /// so they show something different.
+    pub fn set_pane_count(&mut self, count: usize) {
+        let count = count.clamp(1, MAX_PANES);
+        let mut modes = self.take_panes();
+        modes.truncate(count);
+        while modes.len() < count {
+            let last = modes[modes.len() - 1].kind();
+            let i = RenderModeKind::ALL
+                .iter()
+                .position(|&kind| kind == last)
+                .unwrap_or(0);
+            modes.push(RenderModeKind::ALL[(i + 1) % RenderModeKind::ALL.len()].into());
+        }
+
+        let focused = min(self.focused_pane, count - 1);
+        self.put_panes(modes, focused);
+        // Every pane has changed size, so they all start over.
+        self.reset_render_mode();
+    }
+
+    /// Hands the D-pad and the mode menu over to the pane at `index`.
+    pub fn focus_pane(&mut self, index: usize) {
+        let modes = self.take_panes();
+        let index = min(index, modes.len() - 1);
+        self.put_panes(modes, index);
+    }
+
+    // Every pane's mode in order, leaving a placeholder behind. Pass them to `put_panes`
+    // to put them back.
+    fn take_panes(&mut self) -> Vec<RenderMode> {
+        let mut modes = mem::take(&mut self.other_panes);
+        modes.insert(self.focused_pane, mem::take(&mut self.render_mode));
+        modes
+    }
+
+    fn put_panes(&mut self, mut modes: Vec<RenderMode>, focused: usize) {
+        self.render_mode = modes.remove(focused);
+        self.other_panes = modes;
+        self.focused_pane = focused;
+    }
+
+    /// Each pane's mode, in order, with the viewport it draws in and the settings it sees.
+    pub(crate) fn panes_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&mut RenderMode, Viewport, Settings)> + '_ {
+        let settings = self.settings;
+        let viewports = self.pane_viewports();
+        let (before, after) = self.other_panes.split_at_mut(self.focused_pane);
+
+        before
+            .iter_mut()
+            .chain(Some(&mut self.render_mode))
+            .chain(after.iter_mut())
+            .zip(viewports)
+            .map(move |(mode, viewport)| (mode, viewport, pane_settings(&settings, viewport)))
+    }
+
+    pub(crate) fn pane(&self, index: usize) -> &RenderMode {
+        use std::cmp::Ordering::*;
+        match index.cmp(&self.focused_pane) {
+            Less => &self.other_panes[index],
+            Equal => &self.render_mode,
+            Greater => &self.other_panes[index - 1],
+        }
+    }
+
+    pub(crate) fn pane_mut(&mut self, index: usize) -> &mut RenderMode {
+        use std::cmp::Ordering::*;
+        match index.cmp(&self.focused_pane) {
+            Less => &mut self.other_panes[index],
+            Equal => &mut self.render_mode,
+            Greater => &mut self.other_panes[index - 1],
+        }
+    }
+
+    /// Scrolls every pane that can scroll so the cursor is on screen.
+    pub(crate) fn show_cursor(&mut self) {
+        let cursor = self.cursor;
+        for (mode, _, settings) in self.panes_mut() {
+            mode.show(cursor, &settings);
+        }
+    }
+}
+
+/// Runs `f` on the mode in the pane at `index`, with the pane's viewport pushed, and the
+/// settings the mode sees. The mode is taken out of `state` while `f` runs, so `f` can
+/// have the rest of `state` too.
+pub(crate) fn with_pane<F>(framebuffer: &mut Framebuffer, state: &mut GameState, index: usize, f: F)
+where
+    F: FnOnce(&mut Framebuffer, &mut RenderMode, &Settings, &mut GameState),
+{
+    let viewport = state.pane_viewports()[index];
+    let settings = pane_settings(&state.settings, viewport);
+    let mut mode = mem::take(state.pane_mut(index));
+
+    framebuffer.with_viewport(
+        viewport.x,
+        viewport.y,
+        viewport.w,
+        viewport.h,
+        |framebuffer| f(framebuffer, &mut mode, &settings, state),
+    );
+
+    *state.pane_mut(index) = mode;
+}
+
+/// Draws the lines between the panes, with the focused pane's edges picked out.
+pub(crate) fn render_dividers(framebuffer: &mut Framebuffer, state: &GameState) {
+    if state.pane_count() <= 1 {
+        return;
+    }
+
+    let viewports = state.pane_viewports();
+    // The panes' outlines land in the gaps between them, and off the edge of the screen.
+    for (i, viewport) in viewports.iter().enumerate() {
+        if i != state.focused_pane {
+            outline(framebuffer, *viewport, GREY);
+        }
+    }
+    outline(framebuffer, viewports[state.focused_pane], WHITE);
+}
+
+// Draws a line one pixel outside each edge of `viewport`, except the ones that would be
+// off the top or left of the screen.
+fn outline(framebuffer: &mut Framebuffer, viewport: Viewport, colour: u32) {
+    let Viewport { x, y, w, h } = viewport;
+    let (right, bottom) = (x + w, y + h);
+    let (left, top) = (x.checked_sub(1), y.checked_sub(1));
+    let (x0, y0) = (left.unwrap_or(x), top.unwrap_or(y));
+
+    if let Some(top) = top {
+        framebuffer.draw_filled_rect(x0, top, right + 1 - x0, 1, colour);
+    }
+    framebuffer.draw_filled_rect(x0, bottom, right + 1 - x0, 1, colour);
+    if let Some(left) = left {
+        framebuffer.draw_filled_rect(left, y0, 1, bottom + 1 - y0, colour);
+    }
+    framebuffer.draw_filled_rect(right, y0, 1, bottom + 1 - y0, colour);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{update_and_render, Primitive, ThreeBitsPerPixelState};
+    use platform_types::{Button, Input, Speaker};
+
+    #[test]
+    fn layout_leaves_a_gap_between_panes_and_covers_the_rest() {
+        assert_eq!(
+            layout(2, 9, 4),
+            [
+                Viewport {
+                    x: 0,
+                    y: 0,
+                    w: 4,
+                    h: 4
+                },
+                Viewport {
+                    x: 5,
+                    y: 0,
+                    w: 4,
+                    h: 4
+                },
+            ]
+        );
+
+        let panes = layout(4, 9, 7);
+        assert_eq!(
+            panes[0],
+            Viewport {
+                x: 0,
+                y: 0,
+                w: 4,
+                h: 3
+            }
+        );
+        assert_eq!(
+            panes[3],
+            Viewport {
+                x: 5,
+                y: 4,
+                w: 4,
+                h: 3
+            }
+        );
+        let area: usize = panes.iter().map(|p| p.w * p.h).sum();
+        // Everything but the one pixel wide cross between them.
+        assert_eq!(area, 9 * 7 - 9 - 7 + 1);
+    }
+
+    #[test]
+    fn panes_follow_the_cursor_moved_in_the_focused_one() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        state.render_mode = RenderModeKind::ThreeBitsPerPixel.into();
+        state.set_pane_count(2);
+        assert_eq!(state.pane_count(), 2);
+        assert_eq!(state.other_panes[0].kind(), RenderModeKind::Encoding);
+
+        state.focus_pane(1);
+        assert_eq!(state.render_mode.kind(), RenderModeKind::Encoding);
+        assert_eq!(
+            state.other_panes[0].kind(),
+            RenderModeKind::ThreeBitsPerPixel
+        );
+
+        // Move down in the encoding pane until the cursor is past what the other pane
+        // showed to start with.
+        let down = Input {
+            gamepad: Button::Down,
+            previous_gamepad: Button::Ty::empty(),
+            ..Input::new()
+        };
+        let first_screen = ThreeBitsPerPixelState::visible_byte_count(&state.pane_settings(0));
+        while state.cursor < first_screen {
+            update_and_render(&mut framebuffer, &mut state, down, &mut Speaker::new());
+        }
+
+        let cursor = state.cursor;
+        for (mode, _, settings) in state.panes_mut() {
+            let (start, end) = mode.visible_range(usize::MAX, &settings);
+            assert!(start <= cursor && cursor < end, "{:?}", mode.kind());
+        }
+    }
+
+    #[test]
+    fn fewer_panes_keeps_the_first_modes_and_the_focus_in_range() {
+        let mut state = GameState::new();
+        state.set_pane_count(3);
+        state.focus_pane(2);
+        let kinds: Vec<_> = state.panes_mut().map(|(mode, _, _)| mode.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                RenderModeKind::Geometric(Primitive::Quadrilateral),
+                RenderModeKind::Geometric(Primitive::Triangle),
+                RenderModeKind::Geometric(Primitive::Line),
+            ]
+        );
+
+        state.set_pane_count(1);
+        assert_eq!(state.focused_pane(), 0);
+        assert_eq!(
+            state.render_mode.kind(),
+            RenderModeKind::Geometric(Primitive::Quadrilateral)
+        );
+    }
+}
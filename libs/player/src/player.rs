@@ -1,9 +1,11 @@
 extern crate features;
 use features::{log, GLOBAL_ERROR_LOGGER, GLOBAL_LOGGER};
 extern crate platform_types;
-use platform_types::{Button, Input, Speaker, State, StateParams, SFX};
+use platform_types::{
+    Action, Button, EditKey, Input, KeyBindings, PointerButton, Speaker, State, StateParams, SFX,
+};
 extern crate rendering;
-use rendering::{Framebuffer, BLACK, BLUE, GREEN, GREY, PALETTE, PURPLE, RED, WHITE, YELLOW};
+use rendering::{Framebuffer, CURRENT_HIGHLIGHT, CURSOR, HIGHLIGHT, WHITE};
 
 macro_rules! d {
     () => {
@@ -11,6 +13,69 @@ macro_rules! d {
     };
 }
 
+mod compare;
+pub use compare::{update_and_render_comparison, Comparison, ComparisonState};
+
+mod encoding;
+pub use encoding::{update_and_render_encoding, EncodingState};
+
+mod fields;
+pub use fields::{Fields, Row};
+
+mod geometric;
+pub use geometric::{update_and_render_geometric, GeometricState, Primitive};
+
+mod hud;
+
+mod keys;
+
+mod link;
+pub use link::{Link, LinkError, LINK_VERSION};
+
+mod menu;
+pub use menu::Menu;
+
+mod panes;
+pub use panes::MAX_PANES;
+
+mod pointer;
+
+mod prompt;
+pub use prompt::{Prompt, PromptKind};
+
+mod render;
+pub use render::{render, RenderOptions};
+
+mod search;
+pub use search::{ParseError, Pattern, Search};
+
+mod sections;
+
+mod session;
+pub use session::{PaneSession, Session, SessionError, ViewSession, SESSION_VERSION};
+
+mod transform;
+pub use transform::Transform;
+
+mod views;
+pub use views::View;
+
+mod settings;
+pub use settings::{
+    BitOrder, ComparisonOptions, ComparisonView, EncodingOptions, FieldPanel, GeometricOptions,
+    ModeOptions, Palette, SectionMap, Settings, ThreeBitsPerPixelOptions, HUD_HEIGHT,
+    PRIMITIVE_SPEEDS, ZOOM_LEVELS,
+};
+
+pub use formats::{Region, RegionKind};
+pub use inflate::Stream;
+pub use signature::FileType;
+pub use template::Template;
+pub use text::{ByteRole, Encoding};
+
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
 pub struct EntireState {
     pub game_state: GameState,
     pub framebuffer: Framebuffer,
@@ -46,6 +111,8 @@ impl State for EntireState {
         );
 
         self.input.previous_gamepad = self.input.gamepad;
+        self.input.pointer.previous_buttons = self.input.pointer.buttons;
+        self.input.pointer.wheel = 0;
 
         for request in self.speaker.drain() {
             handle_sound(request);
@@ -71,150 +138,917 @@ impl State for EntireState {
     }
 
     fn update_bytes(&mut self, bytes: Vec<u8>) {
-        self.game_state.bytes = bytes;
-        self.game_state.render_mode = match self.game_state.render_mode {
-            RenderMode::Quadrilateral(_) => RenderMode::Quadrilateral(d!()),
-            RenderMode::ThreeBitsPerPixel(_) => RenderMode::ThreeBitsPerPixel(d!()),
-        };
+        self.game_state.views.clear();
+        self.game_state.transforms.clear();
+        self.game_state.load_bytes(bytes);
+    }
+
+    fn update_comparison_bytes(&mut self, bytes: Vec<u8>) {
+        self.game_state.compare_with(bytes);
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let state = &mut self.game_state;
+        state.settings.screen_width = width;
+        state.settings.screen_height = height;
+        self.framebuffer.resize(width, height);
+        redraw_mode(&mut self.framebuffer, state);
+        state.menu.redrawn(&self.framebuffer);
+        state.prompt.redrawn();
+    }
+
+    fn pointer_moved(&mut self, position: Option<(usize, usize)>) {
+        self.input.pointer.position = position;
+    }
+
+    fn pointer_pressed(&mut self, button: PointerButton::Ty) {
+        self.input.pointer.buttons.insert(button);
+    }
+
+    fn pointer_released(&mut self, button: PointerButton::Ty) {
+        self.input.pointer.buttons.remove(button);
+    }
+
+    fn wheel(&mut self, lines: i32) {
+        self.input.pointer.wheel += lines;
+    }
+
+    fn wants_text_input(&self) -> bool {
+        self.game_state.prompt.is_open()
+    }
+
+    fn text_input(&mut self, ch: char) {
+        self.game_state.type_into_prompt(ch);
+    }
+
+    fn edit_key(&mut self, key: EditKey) {
+        self.game_state.edit_prompt(key);
+    }
+
+    fn key_bindings(&self) -> &KeyBindings {
+        &self.game_state.key_bindings
+    }
+
+    fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.game_state.key_bindings = bindings;
+    }
+
+    fn act(&mut self, action: Action) {
+        self.game_state.act(action);
+    }
+
+    fn binding_key(&self) -> bool {
+        self.game_state.binding_key()
+    }
+
+    fn bind_key(&mut self, key: &str) {
+        self.game_state.bind_key(key);
+    }
+
+    fn session(&self) -> String {
+        self.game_state.session().to_json()
+    }
+
+    fn restore_session(&mut self, json: &str) -> Result<(), String> {
+        Session::from_json(json)
+            .and_then(|session| self.game_state.restore_session(session))
+            .map_err(|e| e.to_string())
+    }
+
+    fn link(&self) -> String {
+        self.game_state.link().to_string()
+    }
+
+    fn follow_link(&mut self, link: &str) -> Result<(), String> {
+        let link = Link::parse(link).map_err(|e| e.to_string())?;
+        self.game_state.follow_link(link);
+        Ok(())
     }
 }
 
 impl GameState {
     pub fn new() -> GameState {
-        GameState {
-            bytes: DEFAULT_BYTES.to_vec(),
-            ..d!()
+        let mut state: GameState = d!();
+        state.load_bytes(DEFAULT_BYTES.to_vec());
+        state
+    }
+
+    /// Searches for `query`, as parsed by `Pattern::parse`, and highlights the matches.
+    pub fn search_for(&mut self, query: &str) -> Result<(), ParseError> {
+        let pattern = Pattern::parse(query)?;
+        self.search.set_pattern(pattern, &self.transformed);
+        self.reset_render_mode();
+        Ok(())
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search.clear();
+        self.reset_render_mode();
+    }
+
+    /// Compares the bytes with `bytes`, and switches the focused pane to the comparison
+    /// mode to show where they differ. Empty `bytes` stop the comparison.
+    pub fn compare_with(&mut self, bytes: Vec<u8>) {
+        self.comparison.set_bytes(bytes, &self.transformed);
+        if self.comparison.is_active() {
+            self.render_mode = RenderModeKind::Comparison.into();
         }
+        self.reset_render_mode();
     }
-}
 
-#[derive(Default)]
-pub struct QuadrilateralState {
-    pub byte_index: usize,
+    /// Decodes the bytes from `offset` onwards with `template`, and lists the fields in
+    /// the field panel.
+    pub fn apply_template(&mut self, template: Template, offset: usize) {
+        self.fields
+            .set_template(template, offset, &self.transformed);
+        self.reset_render_mode();
+    }
+
+    pub fn clear_template(&mut self) {
+        self.fields.clear();
+        self.reset_render_mode();
+    }
+
+    /// Jumps the geometric modes' animation to just before the primitive with the given
+    /// index is drawn. Does nothing in other modes.
+    pub fn seek_primitive(&mut self, index: usize) {
+        if let RenderMode::Geometric(ref mut g_state) = self.render_mode {
+            g_state.seek(index);
+        }
+    }
+
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// Adds `transform` to the end of the transforms, so it applies to the output of the
+    /// ones already there.
+    pub fn push_transform(&mut self, transform: Transform) {
+        self.transforms.push(transform);
+        self.transforms_changed();
+    }
+
+    pub fn remove_transform(&mut self, index: usize) -> Transform {
+        let transform = self.transforms.remove(index);
+        self.transforms_changed();
+        transform
+    }
+
+    pub fn set_transform(&mut self, index: usize, transform: Transform) {
+        self.transforms[index] = transform;
+        self.transforms_changed();
+    }
+
+    pub fn clear_transforms(&mut self) {
+        self.transforms.clear();
+        self.transforms_changed();
+    }
+
+    fn transforms_changed(&mut self) {
+        self.refresh_transformed();
+        self.reset_render_mode();
+    }
+
+    // Switches to looking at `bytes`, and works out everything we show about them.
+    fn load_bytes(&mut self, bytes: Vec<u8>) {
+        self.bytes = bytes;
+        self.cursor = 0;
+        self.selection = None;
+        self.bookmark = None;
+        self.refresh_transformed();
+        if let Some(file_type) = self.file_type {
+            self.render_mode = suggested_mode(file_type).into();
+        }
+        self.reset_render_mode();
+    }
+
+    // Applies the transforms to the bytes again, and works out everything we show about
+    // the result. Undoing an XOR can reveal a file type, so that is detected here too.
+    fn refresh_transformed(&mut self) {
+        self.transformed = transform::apply_all(&self.transforms, &self.bytes);
+        self.file_type = signature::detect(&self.transformed);
+        self.regions = formats::regions(&self.transformed).unwrap_or_else(|e| {
+            log(&format!("Could not read the sections: {}", e));
+            Vec::new()
+        });
+        self.streams = inflate::find_streams(&self.transformed);
+        self.cursor = min(self.cursor, self.transformed.len().saturating_sub(1));
+        self.search.refresh(&self.transformed);
+        self.fields.refresh(&self.transformed);
+        self.comparison.refresh(&self.transformed);
+    }
+
+    // Starts every pane's mode over, keeping the cursor on screen.
+    fn reset_render_mode(&mut self) {
+        let cursor = self.cursor;
+        for (mode, _, settings) in self.panes_mut() {
+            *mode = mode.kind().into();
+            mode.show(cursor, &settings);
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct ThreeBitsPerPixelState {
     pub byte_index: usize,
-    pub done_first_render: bool,
+    /// Set this to false to have the screen redrawn on the next frame.
+    pub rendered: bool,
 }
 
 pub enum RenderMode {
-    Quadrilateral(QuadrilateralState),
+    Geometric(GeometricState),
     ThreeBitsPerPixel(ThreeBitsPerPixelState),
+    Encoding(EncodingState),
+    Comparison(ComparisonState),
 }
 
 impl Default for RenderMode {
     fn default() -> RenderMode {
-        RenderMode::Quadrilateral(d!())
+        RenderMode::Geometric(d!())
+    }
+}
+
+impl RenderMode {
+    pub fn kind(&self) -> RenderModeKind {
+        match *self {
+            RenderMode::Geometric(ref g_state) => RenderModeKind::Geometric(g_state.primitive),
+            RenderMode::ThreeBitsPerPixel(_) => RenderModeKind::ThreeBitsPerPixel,
+            RenderMode::Encoding(_) => RenderModeKind::Encoding,
+            RenderMode::Comparison(_) => RenderModeKind::Comparison,
+        }
+    }
+
+    /// Whether the mode has drawn everything it is going to for now, given that many bytes.
+    pub fn is_finished(&self, byte_count: usize) -> bool {
+        match *self {
+            RenderMode::Geometric(ref g_state) => g_state.byte_index >= byte_count,
+            RenderMode::ThreeBitsPerPixel(ref tbbp_state) => tbbp_state.rendered,
+            RenderMode::Encoding(ref e_state) => e_state.rendered,
+            RenderMode::Comparison(ref c_state) => c_state.rendered,
+        }
+    }
+
+    /// Scrolls the pixel based modes so that `offset` is on screen. The geometric modes
+    /// always show every byte they have drawn, so this does nothing to them.
+    pub fn show(&mut self, offset: usize, settings: &Settings) {
+        match *self {
+            RenderMode::Geometric(_) => {}
+            RenderMode::ThreeBitsPerPixel(ref mut tbbp_state) => tbbp_state.show(offset, settings),
+            RenderMode::Encoding(ref mut e_state) => e_state.show(offset, settings),
+            RenderMode::Comparison(ref mut c_state) => c_state.show(offset, settings),
+        }
+    }
+
+    /// The range of byte offsets currently shown on screen.
+    pub fn visible_range(&self, byte_count: usize, settings: &Settings) -> (usize, usize) {
+        match *self {
+            RenderMode::Geometric(ref g_state) => (0, min(g_state.byte_index, byte_count)),
+            RenderMode::ThreeBitsPerPixel(ref tbbp_state) => {
+                let start = min(tbbp_state.byte_index, byte_count);
+                let end =
+                    tbbp_state.byte_index + ThreeBitsPerPixelState::visible_byte_count(settings);
+                (start, min(end, byte_count))
+            }
+            RenderMode::Encoding(ref e_state) => {
+                let start = min(e_state.byte_index, byte_count);
+                let end = e_state.byte_index + EncodingState::visible_byte_count(settings);
+                (start, min(end, byte_count))
+            }
+            RenderMode::Comparison(ref c_state) => {
+                let start = min(c_state.byte_index, byte_count);
+                let end = c_state.byte_index + c_state.visible_byte_count(settings);
+                (start, min(end, byte_count))
+            }
+        }
+    }
+
+    /// How many bytes the pixel based modes show on each row. The geometric modes don't
+    /// have rows.
+    pub fn bytes_per_row(&self, settings: &Settings) -> Option<usize> {
+        match *self {
+            RenderMode::Geometric(_) => None,
+            RenderMode::ThreeBitsPerPixel(_) => Some(three_bits_per_pixel_bytes_per_row(settings)),
+            RenderMode::Encoding(_) => Some(pixel_grid_size(settings).0),
+            RenderMode::Comparison(ref c_state) => Some(c_state.bytes_per_row(settings)),
+        }
+    }
+
+    /// The offset of the byte drawn at `(x, y)` on the screen, for the pixel based modes.
+    /// This may be past the end of the bytes.
+    pub fn offset_at(&self, x: usize, y: usize, settings: &Settings) -> Option<usize> {
+        let zoom = settings.zoom as usize;
+        let (column, row) = (x / zoom, y / zoom);
+        let (width, height) = pixel_grid_size(settings);
+        if row >= height {
+            return None;
+        }
+
+        match *self {
+            RenderMode::Geometric(_) => None,
+            RenderMode::ThreeBitsPerPixel(ref tbbp_state) if column < width => {
+                Some(tbbp_state.byte_index + (row * width + column) * 3 / 8)
+            }
+            RenderMode::Encoding(ref e_state) if column < width => {
+                Some(e_state.byte_index + row * width + column)
+            }
+            RenderMode::Comparison(ref c_state) => c_state.offset_at(column, row, settings),
+            RenderMode::ThreeBitsPerPixel(_) | RenderMode::Encoding(_) => None,
+        }
+    }
+}
+
+/// The `RenderMode` variants, without their state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderModeKind {
+    Geometric(Primitive),
+    ThreeBitsPerPixel,
+    Encoding,
+    Comparison,
+}
+
+impl RenderModeKind {
+    pub const ALL: [RenderModeKind; 8] = [
+        RenderModeKind::Geometric(Primitive::Quadrilateral),
+        RenderModeKind::Geometric(Primitive::Triangle),
+        RenderModeKind::Geometric(Primitive::Line),
+        RenderModeKind::Geometric(Primitive::Circle),
+        RenderModeKind::Geometric(Primitive::Appearance),
+        RenderModeKind::ThreeBitsPerPixel,
+        RenderModeKind::Encoding,
+        RenderModeKind::Comparison,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderModeKind::Geometric(primitive) => primitive.name(),
+            RenderModeKind::ThreeBitsPerPixel => "3 bits per pixel",
+            RenderModeKind::Encoding => "Text encoding",
+            RenderModeKind::Comparison => "Comparison",
+        }
+    }
+}
+
+/// The mode most likely to show something interesting about a file of the given type.
+pub fn suggested_mode(file_type: FileType) -> RenderModeKind {
+    use FileType::*;
+    match file_type {
+        // Code, tables and headers all have distinctive textures when shown as pixels.
+        Elf | Pe | Mz | MachO | Pdf | Riff | INes | Sqlite => RenderModeKind::ThreeBitsPerPixel,
+        // Consecutive samples are close together, so lines between pairs of them cluster
+        // along the diagonal, and quiet and loud sections look different.
+        Wav => RenderModeKind::Geometric(Primitive::Line),
+        // Compressed data is close to random whichever way it is drawn.
+        Png | Jpeg | Gif | Zip | Gzip => RenderModeKind::Geometric(Primitive::Quadrilateral),
+    }
+}
+
+impl From<RenderModeKind> for RenderMode {
+    fn from(kind: RenderModeKind) -> Self {
+        match kind {
+            RenderModeKind::Geometric(primitive) => {
+                RenderMode::Geometric(GeometricState::new(primitive))
+            }
+            RenderModeKind::ThreeBitsPerPixel => RenderMode::ThreeBitsPerPixel(d!()),
+            RenderModeKind::Encoding => RenderMode::Encoding(d!()),
+            RenderModeKind::Comparison => RenderMode::Comparison(d!()),
+        }
     }
 }
 
 #[derive(Default)]
 pub struct GameState {
+    /// The mode in the focused pane, which is the one the D-pad and the mode menu control.
     pub render_mode: RenderMode,
+    // The modes in the rest of the panes, in order, skipping the focused one.
+    other_panes: Vec<RenderMode>,
+    focused_pane: usize,
+    /// The bytes as they were loaded, before any transforms.
     pub bytes: Vec<u8>,
+    // Changed through methods, so `transformed` is always up to date.
+    transforms: Vec<Transform>,
+    /// `bytes` with the transforms applied. This is what the modes show, and what
+    /// everything below describes.
+    pub transformed: Vec<u8>,
+    /// What kind of file `transformed` came from, if we could tell.
+    pub file_type: Option<FileType>,
+    /// The sections and so on of `transformed`, if it is a format we can parse.
+    pub regions: Vec<Region>,
+    /// Where `transformed` looks like it contains zlib or gzip streams.
+    pub streams: Vec<Stream>,
+    /// The bytes that `bytes` was decompressed from, and so on back to the original ones.
+    pub views: Vec<View>,
+    pub search: Search,
+    /// The second file the user is comparing `transformed` with, if they are.
+    pub comparison: Comparison,
+    /// The fields of the template the user applied, if they did.
+    pub fields: Fields,
+    /// The bytes the user dragged over with the pointer, as the offset of the first one and
+    /// the offset after the last one.
+    pub selection: Option<(usize, usize)>,
+    // Where the drag that is making `selection` started, while the pointer is held down.
+    drag_start: Option<usize>,
+    /// The offset of the byte the user is currently looking at.
+    pub cursor: usize,
+    /// An offset the user marked to jump back to.
+    pub bookmark: Option<usize>,
+    pub settings: Settings,
+    pub mode_options: ModeOptions,
+    pub menu: Menu,
+    pub prompt: Prompt,
+    pub key_bindings: KeyBindings,
 }
 
 pub const DEFAULT_BYTES: &[u8] = include_bytes!("player.rs");
 
+// Nothing plays a sound yet, but `EntireState` drains the speaker every frame for when
+// something does.
 #[inline]
 pub fn update_and_render(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
-    speaker: &mut Speaker,
+    _speaker: &mut Speaker,
 ) {
-    if input.pressed_this_frame(Button::Start) {
-        state.render_mode = match state.render_mode {
-            RenderMode::Quadrilateral(_) => RenderMode::ThreeBitsPerPixel(d!()),
-            RenderMode::ThreeBitsPerPixel(_) => RenderMode::Quadrilateral(d!()),
-        };
+    if menu::update_and_render(framebuffer, state, input) {
+        return;
     }
 
-    match state.render_mode {
-        RenderMode::Quadrilateral(ref mut q_state) => {
-            update_and_render_quadrilateral(framebuffer, q_state, input, speaker, &state.bytes)
+    if input.pressed_this_frame(Button::Select) {
+        state.settings.show_hud = !state.settings.show_hud;
+        state.reset_render_mode();
+    }
+
+    let previous_cursor = state.cursor;
+    let input = fields::browse(state, input);
+    let browsed = state.cursor != previous_cursor;
+    pointer::update(state, input);
+
+    // The comparison mode uses A and B to move between the differences instead.
+    let previous_match = state.search.current_match();
+    if state.render_mode.kind() != RenderModeKind::Comparison {
+        if input.pressed_this_frame(Button::A) {
+            state.search.next();
+        } else if input.pressed_this_frame(Button::B) {
+            state.search.previous();
+        }
+    }
+
+    if state.search.current_match() != previous_match {
+        if let Some(match_start) = state.search.current_match() {
+            state.cursor = match_start;
+        }
+
+        for pane in 0..state.pane_count() {
+            panes::with_pane(
+                framebuffer,
+                state,
+                pane,
+                |framebuffer, mode, settings, state| match *mode {
+                    RenderMode::Geometric(ref g_state) => {
+                        for &match_start in previous_match
+                            .iter()
+                            .chain(state.search.current_match().iter())
+                        {
+                            geometric::outline_drawn_primitives_in_match(
+                                framebuffer,
+                                g_state,
+                                &state.transformed,
+                                &state.search,
+                                match_start,
+                                settings,
+                            );
+                        }
+                    }
+                    RenderMode::ThreeBitsPerPixel(_)
+                    | RenderMode::Encoding(_)
+                    | RenderMode::Comparison(_) => {
+                        mode.show(state.cursor, settings);
+                    }
+                },
+            );
+        }
+    }
+
+    update_and_render_panes(framebuffer, state, input);
+
+    if state.cursor != previous_cursor && !browsed {
+        fields::follow_cursor(state);
+    }
+    fields::render_panel(framebuffer, &mut state.fields, &state.settings);
+
+    prompt::render(framebuffer, &mut state.prompt);
+    if state.settings.show_hud && !state.prompt.is_open() {
+        hud::render(framebuffer, state);
+    }
+}
+
+// Runs every pane's mode for a frame. The focused pane goes first since it is the one that
+// moves the cursor, and the others then scroll to wherever it ended up.
+fn update_and_render_panes(framebuffer: &mut Framebuffer, state: &mut GameState, input: Input) {
+    let previous_cursor = state.cursor;
+    let focused = state.focused_pane;
+    panes::with_pane(
+        framebuffer,
+        state,
+        focused,
+        |framebuffer, mode, settings, state| {
+            update_and_render_mode(framebuffer, mode, state, input, settings);
+        },
+    );
+
+    let moved = state.cursor != previous_cursor;
+    for pane in (0..state.pane_count()).filter(|&pane| pane != focused) {
+        panes::with_pane(
+            framebuffer,
+            state,
+            pane,
+            |framebuffer, mode, settings, state| {
+                if moved {
+                    mode.show(state.cursor, settings);
+                }
+                update_and_render_mode(framebuffer, mode, state, Input::new(), settings);
+            },
+        );
+    }
+
+    panes::render_dividers(framebuffer, state);
+}
+
+/// What the modes read while drawing a pane, besides their own state and options.
+#[derive(Clone, Copy)]
+pub struct ModeContext<'a> {
+    /// The bytes to show, with the transforms applied.
+    pub bytes: &'a [u8],
+    pub search: &'a Search,
+    pub regions: &'a [Region],
+    pub fields: &'a Fields,
+    pub selection: Option<(usize, usize)>,
+    /// The settings of the pane being drawn.
+    pub settings: &'a Settings,
+}
+
+// `render_mode` is one of the panes' modes, taken out of `state` for the moment.
+fn update_and_render_mode(
+    framebuffer: &mut Framebuffer,
+    render_mode: &mut RenderMode,
+    state: &mut GameState,
+    input: Input,
+    settings: &Settings,
+) {
+    let context = ModeContext {
+        bytes: &state.transformed,
+        search: &state.search,
+        regions: &state.regions,
+        fields: &state.fields,
+        selection: state.selection,
+        settings,
+    };
+    let cursor = &mut state.cursor;
+    let options = &state.mode_options;
+
+    match *render_mode {
+        RenderMode::Geometric(ref mut g_state) => {
+            update_and_render_geometric(framebuffer, g_state, input, &context, &options.geometric)
         }
         RenderMode::ThreeBitsPerPixel(ref mut tbbp_state) => {
             update_and_render_three_bits_per_pixel(
                 framebuffer,
                 tbbp_state,
                 input,
-                speaker,
-                &state.bytes,
+                &context,
+                cursor,
+                &options.three_bits_per_pixel,
             )
         }
+        RenderMode::Encoding(ref mut e_state) => update_and_render_encoding(
+            framebuffer,
+            e_state,
+            input,
+            &context,
+            cursor,
+            &options.encoding,
+        ),
+        RenderMode::Comparison(ref mut c_state) => update_and_render_comparison(
+            framebuffer,
+            c_state,
+            input,
+            &context,
+            &state.comparison,
+            cursor,
+            &options.comparison,
+        ),
+    }
+}
+
+// Draws every pane's mode again from the start, as far as it had got, for when the bytes
+// change while the menu is open, or the screen changes size.
+fn redraw_mode(framebuffer: &mut Framebuffer, state: &mut GameState) {
+    let targets: Vec<Option<usize>> = state
+        .panes_mut()
+        .map(|(mode, _, _)| match *mode {
+            RenderMode::Geometric(ref g_state) => Some(g_state.target_index()),
+            RenderMode::ThreeBitsPerPixel(_)
+            | RenderMode::Encoding(_)
+            | RenderMode::Comparison(_) => None,
+        })
+        .collect();
+    state.reset_render_mode();
+
+    for (pane, target) in targets.into_iter().enumerate() {
+        panes::with_pane(
+            framebuffer,
+            state,
+            pane,
+            |framebuffer, mode, settings, state| {
+                if let (RenderMode::Geometric(ref mut g_state), Some(target)) = (&mut *mode, target)
+                {
+                    g_state.seek(target);
+                }
+                update_and_render_mode(framebuffer, mode, state, Input::new(), settings);
+            },
+        );
+    }
+    panes::render_dividers(framebuffer, state);
+}
+
+fn highlight_colour(search: &Search, start: usize, len: usize) -> Option<u32> {
+    let overlaps_current = search
+        .current_match()
+        .map(|m| m < start + len && start < m + search.match_len())
+        .unwrap_or(false);
+
+    if overlaps_current {
+        Some(CURRENT_HIGHLIGHT)
+    } else {
+        search.match_overlapping(start, len).map(|_| HIGHLIGHT)
+    }
+}
+
+// The size, in zoomed pixels, of the area the pixel based modes draw on. The width is
+// rounded down to a multiple of 8, and any columns left over on the right stay blank.
+fn pixel_grid_size(settings: &Settings) -> (usize, usize) {
+    let zoom = settings.zoom as usize;
+    (
+        max(settings.screen_width / zoom / 8 * 8, 8),
+        max(settings.view_height() / zoom, 1),
+    )
+}
+
+// Every 3 bytes make exactly 8 pixels, and the width in pixels is always a multiple of 8
+// so this many bytes fill exactly one row.
+fn three_bits_per_pixel_bytes_per_row(settings: &Settings) -> usize {
+    pixel_grid_size(settings).0 * 3 / 8
+}
+
+impl ThreeBitsPerPixelState {
+    fn visible_byte_count(settings: &Settings) -> usize {
+        three_bits_per_pixel_bytes_per_row(settings) * pixel_grid_size(settings).1
+    }
+
+    /// Scrolls so that `offset` is on screen, if it isn't already.
+    pub fn show(&mut self, offset: usize, settings: &Settings) {
+        if offset < self.byte_index
+            || offset >= self.byte_index + Self::visible_byte_count(settings)
+        {
+            let bytes_per_row = three_bits_per_pixel_bytes_per_row(settings);
+            self.byte_index = offset / bytes_per_row * bytes_per_row;
+        }
+        self.rendered = false;
     }
 }
 
-pub fn update_and_render_quadrilateral(
+pub fn update_and_render_three_bits_per_pixel(
     framebuffer: &mut Framebuffer,
-    state: &mut QuadrilateralState,
+    state: &mut ThreeBitsPerPixelState,
     input: Input,
-    speaker: &mut Speaker,
-    bytes: &[u8],
+    context: &ModeContext,
+    cursor: &mut usize,
+    options: &ThreeBitsPerPixelOptions,
 ) {
-    if state.byte_index == 0 {
-        framebuffer.clear_to(PALETTE[PALETTE.len() - 1]);
+    let ModeContext {
+        bytes,
+        search,
+        regions,
+        fields,
+        selection,
+        settings,
+    } = *context;
+    let previous_cursor = *cursor;
+    let bytes_per_row = three_bits_per_pixel_bytes_per_row(settings);
+    if input.pressed_this_frame(Button::Up) {
+        *cursor = cursor.saturating_sub(bytes_per_row);
+    } else if input.pressed_this_frame(Button::Down) {
+        *cursor += bytes_per_row;
+    } else if input.pressed_this_frame(Button::Left) {
+        *cursor = cursor.saturating_sub(1);
+    } else if input.pressed_this_frame(Button::Right) {
+        *cursor += 1;
     }
+    *cursor = min(*cursor, bytes.len().saturating_sub(1));
 
-    macro_rules! extract_or_zero {
-        ($i: expr) => {
-            if $i >= bytes.len() {
-                0
-            } else {
-                bytes[$i]
-            }
-        };
+    if *cursor != previous_cursor {
+        state.show(*cursor, settings);
     }
 
-    let u8s = [
-        extract_or_zero!(state.byte_index + 0),
-        extract_or_zero!(state.byte_index + 1),
-        extract_or_zero!(state.byte_index + 2),
-        extract_or_zero!(state.byte_index + 3),
-        extract_or_zero!(state.byte_index + 4),
-        extract_or_zero!(state.byte_index + 5),
-        extract_or_zero!(state.byte_index + 6),
-        extract_or_zero!(state.byte_index + 7),
-    ];
+    if state.rendered {
+        return;
+    }
+
+    let zoom = settings.zoom as usize;
+    let (width, height) = pixel_grid_size(settings);
+    let mut cells = vec![0; width * height];
+
+    let palette = settings.palette.colours();
+    match settings.bit_order {
+        BitOrder::MsbFirst => render_from_msb(bytes, &mut cells, state.byte_index, palette),
+        BitOrder::LsbFirst => render_from_lsb(bytes, &mut cells, state.byte_index, palette),
+    }
+
+    if !options.repeat_bytes {
+        let used_cells = bytes.len().saturating_sub(state.byte_index) * 8 / 3;
+        for cell in cells.iter_mut().skip(used_cells) {
+            *cell = palette[palette.len() - 1];
+        }
+    }
 
-    framebuffer.draw_filled_quad(
-        u8s[0],
-        u8s[1],
-        u8s[2],
-        u8s[3],
-        u8s[4],
-        u8s[5],
-        u8s[6],
-        u8s[7],
-        PALETTE[(state.byte_index / 8) % PALETTE.len()],
+    for (i, &colour) in cells.iter().enumerate() {
+        framebuffer.draw_filled_rect((i % width) * zoom, (i / width) * zoom, zoom, zoom, colour);
+    }
+
+    highlight_three_bits_per_pixel_matches(
+        framebuffer,
+        state.byte_index,
+        bytes.len(),
+        search,
+        settings,
     );
 
-    state.byte_index += 8;
-    if state.byte_index >= bytes.len() {
-        state.byte_index = bytes.len();
+    sections::render_over_pixels(framebuffer, regions, state.byte_index, 3, settings);
+    fields::render_over_pixels(framebuffer, fields, state.byte_index, 3, settings);
+
+    if let Some((start, end)) = selection {
+        if end > state.byte_index {
+            let start_bit = start.saturating_sub(state.byte_index) * 8;
+            let end_bit = (end - state.byte_index) * 8;
+            outline_pixel_span(
+                framebuffer,
+                start_bit / 3,
+                (end_bit - 1) / 3,
+                settings,
+                WHITE,
+            );
+        }
     }
+
+    if *cursor >= state.byte_index {
+        let start_bit = (*cursor - state.byte_index) * 8;
+        outline_pixel_span(
+            framebuffer,
+            start_bit / 3,
+            (start_bit + 7) / 3,
+            settings,
+            CURSOR,
+        );
+    }
+
+    state.rendered = true;
 }
 
-pub fn update_and_render_three_bits_per_pixel(
+fn highlight_three_bits_per_pixel_matches(
     framebuffer: &mut Framebuffer,
-    state: &mut ThreeBitsPerPixelState,
-    input: Input,
-    speaker: &mut Speaker,
-    bytes: &[u8],
+    byte_index: usize,
+    byte_count: usize,
+    search: &Search,
+    settings: &Settings,
 ) {
-    if !state.done_first_render {
-        render_from_msb(bytes, &mut framebuffer.buffer, state.byte_index);
-        state.done_first_render = true;
+    let visible_end = min(
+        byte_index + ThreeBitsPerPixelState::visible_byte_count(settings),
+        byte_count,
+    );
+    let match_len = search.match_len();
+
+    for &match_start in search.matches() {
+        if match_start + match_len <= byte_index {
+            continue;
+        }
+        if match_start >= visible_end {
+            break;
+        }
+
+        let start_bit = match_start.saturating_sub(byte_index) * 8;
+        let end_bit = (match_start + match_len - byte_index) * 8;
+        let colour = if search.is_current(match_start) {
+            CURRENT_HIGHLIGHT
+        } else {
+            HIGHLIGHT
+        };
+
+        outline_pixel_span(
+            framebuffer,
+            start_bit / 3,
+            (end_bit - 1) / 3,
+            settings,
+            colour,
+        );
+    }
+}
+
+// The first and last pixels showing the bytes from `start` to `start + len`, in the pixel
+// based modes when they start from `byte_index` and show `bits_per_pixel` bits in each
+// pixel, or `None` if none of them are on screen.
+fn visible_pixel_span(
+    start: usize,
+    len: usize,
+    byte_index: usize,
+    bits_per_pixel: usize,
+    settings: &Settings,
+) -> Option<(usize, usize)> {
+    let (width, height) = pixel_grid_size(settings);
+    let visible_end = byte_index + width * bits_per_pixel / 8 * height;
+    let end = start + len;
+    if len == 0 || end <= byte_index || start >= visible_end {
+        return None;
+    }
+
+    let start_bit = start.saturating_sub(byte_index) * 8;
+    let end_bit = (min(end, visible_end) - byte_index) * 8;
+    Some((start_bit / bits_per_pixel, (end_bit - 1) / bits_per_pixel))
+}
+
+// Outlines the pixels from `first` to `last` inclusive, where pixels are `settings.zoom`
+// screen pixels square and are counted left to right then top to bottom. A span over
+// several rows gets one outline around the whole shape, like a text selection, since a
+// box around each row would fill in rows only one screen pixel high.
+fn outline_pixel_span(
+    framebuffer: &mut Framebuffer,
+    first: usize,
+    last: usize,
+    settings: &Settings,
+    colour: u32,
+) {
+    let zoom = settings.zoom as usize;
+    let (width, height) = pixel_grid_size(settings);
+    let last = min(last, width * height - 1);
+    if first > last {
+        return;
+    }
+    let (first_x, first_y) = (first % width, first / width);
+    let (last_x, last_y) = (last % width, last / width);
+
+    // The screen coordinates of the lines just outside each edge of a pixel.
+    let left = |x: usize| (x * zoom).saturating_sub(1);
+    let last_column = settings.screen_width.saturating_sub(1);
+    let right = |x: usize| min((x + 1) * zoom, last_column);
+    let top = |y: usize| (y * zoom).saturating_sub(1);
+    let bottom = |y: usize| (y + 1) * zoom;
+
+    if first_y == last_y {
+        let (x, y) = (left(first_x), top(first_y));
+        framebuffer.draw_rect(x, y, right(last_x) - x + 1, bottom(first_y) - y + 1, colour);
         return;
     }
 
-    if input.pressed_this_frame(Button::Right) {
-        render_from_lsb(bytes, &mut framebuffer.buffer, state.byte_index);
-    } else if input.pressed_this_frame(Button::Left) {
-        render_from_msb(bytes, &mut framebuffer.buffer, state.byte_index);
+    let mut horizontal = |x0: usize, x1: usize, y: usize| {
+        framebuffer.draw_filled_rect(x0, y, x1 - x0 + 1, 1, colour);
+    };
+    let (screen_left, screen_right) = (left(0), right(width - 1));
+    // Over the first row and along under the part of it before the span.
+    horizontal(left(first_x), screen_right, top(first_y));
+    horizontal(screen_left, left(first_x), top(first_y + 1));
+    // Under the last row and along over the part of it after the span.
+    horizontal(screen_left, right(last_x), bottom(last_y));
+    horizontal(right(last_x), screen_right, bottom(last_y - 1));
+
+    let mut vertical = |x: usize, y0: usize, y1: usize| {
+        framebuffer.draw_filled_rect(x, y0, 1, y1 - y0 + 1, colour);
+    };
+    vertical(left(first_x), top(first_y), top(first_y + 1));
+    vertical(screen_left, top(first_y + 1), bottom(last_y));
+    vertical(right(last_x), bottom(last_y - 1), bottom(last_y));
+    vertical(screen_right, top(first_y), bottom(last_y - 1));
+}
+
+// Blends `colour` over every pixel from `first` to `last` inclusive, in the same grid as
+// `outline_pixel_span`.
+fn tint_pixel_span(
+    framebuffer: &mut Framebuffer,
+    first: usize,
+    last: usize,
+    settings: &Settings,
+    colour: u32,
+) {
+    let zoom = settings.zoom as usize;
+    let (width, height) = pixel_grid_size(settings);
+    let last = min(last, width * height - 1);
+
+    for pixel in first..=last {
+        let left = pixel % width * zoom;
+        let top = pixel / width * zoom;
+        for y in top..top + zoom {
+            for x in left..left + zoom {
+                framebuffer.blend_xy(x, y, colour);
+            }
+        }
     }
 }
 
@@ -227,9 +1061,9 @@ macro_rules! advance {
     };
 }
 
-fn render_from_lsb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
+fn render_from_lsb(bytes: &[u8], buffer: &mut [u32], byte_index: usize, palette: &[u32; 8]) {
     let len = buffer.len();
-    if byte_index >= len {
+    if bytes.is_empty() {
         return;
     }
 
@@ -253,13 +1087,13 @@ fn render_from_lsb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
     loop {
         let mut byte = iter.next().unwrap();
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
 
         byte >>= 3;
 
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
 
@@ -272,20 +1106,20 @@ fn render_from_lsb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
             merged_byte |= (byte & 0b1) << 2;
 
-            buffer[i] = colour_from_byte(merged_byte);
+            buffer[i] = colour_from_byte(merged_byte, palette);
 
             advance!(i, len);
 
             byte >>= 1;
         }
 
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
 
         byte >>= 3;
 
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
 
@@ -300,26 +1134,26 @@ fn render_from_lsb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
             byte >>= 2;
 
-            buffer[i] = colour_from_byte(merged_byte);
+            buffer[i] = colour_from_byte(merged_byte, palette);
 
             advance!(i, len);
         }
 
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
 
         byte >>= 3;
 
-        buffer[i] = colour_from_byte(byte);
+        buffer[i] = colour_from_byte(byte, palette);
 
         advance!(i, len);
     }
 }
 
-fn render_from_msb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
+fn render_from_msb(bytes: &[u8], buffer: &mut [u32], byte_index: usize, palette: &[u32; 8]) {
     let len = buffer.len();
-    if byte_index >= len {
+    if bytes.is_empty() {
         return;
     }
 
@@ -343,13 +1177,13 @@ fn render_from_msb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
     loop {
         let mut byte = iter.next().unwrap();
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
 
         byte <<= 3;
 
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
 
@@ -362,20 +1196,20 @@ fn render_from_msb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
             merged_byte |= (byte & 0b1000_0000) >> 2;
 
-            buffer[i] = colour_from_byte(merged_byte >> 5);
+            buffer[i] = colour_from_byte(merged_byte >> 5, palette);
 
             advance!(i, len);
 
             byte <<= 1;
         }
 
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
 
         byte <<= 3;
 
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
 
@@ -390,40 +1224,31 @@ fn render_from_msb(bytes: &[u8], buffer: &mut [u32], byte_index: usize) {
 
             byte <<= 2;
 
-            buffer[i] = colour_from_byte(merged_byte >> 5);
+            buffer[i] = colour_from_byte(merged_byte >> 5, palette);
 
             advance!(i, len);
         }
 
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
 
         byte <<= 3;
 
-        buffer[i] = colour_from_byte(byte >> 5);
+        buffer[i] = colour_from_byte(byte >> 5, palette);
 
         advance!(i, len);
     }
 }
 
-fn colour_from_byte(byte: u8) -> u32 {
-    match byte & 0b111 {
-        0 => BLUE,
-        1 => GREEN,
-        2 => RED,
-        3 => YELLOW,
-        4 => PURPLE,
-        5 => GREY,
-        6 => WHITE,
-        7 => BLACK,
-        _ => 0,
-    }
+fn colour_from_byte(byte: u8, palette: &[u32; 8]) -> u32 {
+    palette[(byte & 0b111) as usize]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rendering::{BLACK, BLUE, GREEN, GREY, PALETTE, PURPLE, RED, WHITE, YELLOW};
 
     //What does it mean!?!
     const double_rainbow: [u32; 16] = [
@@ -481,7 +1306,7 @@ mod tests {
 
         let mut buffer = [0; 16];
 
-        render_from_lsb(&bytes, &mut buffer, 0);
+        render_from_lsb(&bytes, &mut buffer, 0, &PALETTE);
 
         assert_eq!(pretty!(double_rainbow), pretty!(buffer));
     }
@@ -499,7 +1324,7 @@ mod tests {
 
         let mut buffer = [0; 16];
 
-        render_from_msb(&bytes, &mut buffer, 0);
+        render_from_msb(&bytes, &mut buffer, 0, &PALETTE);
 
         assert_eq!(pretty!(double_rainbow), pretty!(buffer));
     }
@@ -510,7 +1335,7 @@ mod tests {
 
         let mut buffer = [0; 32];
 
-        render_from_msb(&bytes, &mut buffer, 0);
+        render_from_msb(&bytes, &mut buffer, 0, &PALETTE);
 
         assert_eq!(
             pretty!([
@@ -522,4 +1347,47 @@ mod tests {
             pretty!(buffer)
         );
     }
+
+    #[test]
+    fn resizing_redraws_everything_at_the_new_size() {
+        let mut state = EntireState::new(([0; 16], None, None));
+
+        state.resize(1024, 512);
+        state.frame(|_| {});
+
+        assert_eq!(state.get_frame_buffer().len(), 1024 * 512);
+        assert_eq!(
+            pixel_grid_size(&state.game_state.settings),
+            (1024, 512 - HUD_HEIGHT)
+        );
+        // The HUD moves down to the new bottom of the screen.
+        assert_eq!(state.framebuffer.buffer[511 * 1024 + 1000], BLACK);
+    }
+
+    #[test]
+    fn outline_pixel_span_leaves_the_inside_of_several_rows_alone() {
+        let mut framebuffer = Framebuffer::new();
+        let settings = Settings::default();
+        let (width, _) = pixel_grid_size(&settings);
+
+        outline_pixel_span(
+            &mut framebuffer,
+            width * 10 + 10,
+            width * 14 + 5,
+            &settings,
+            CURSOR,
+        );
+
+        let background = PALETTE[0];
+        let pixel = |x: usize, y: usize| framebuffer.buffer[framebuffer.xy_to_i(x, y)];
+        assert_eq!(pixel(100, 12), background);
+        assert_eq!(pixel(100, 9), CURSOR);
+        assert_eq!(pixel(5, 10), CURSOR);
+        assert_eq!(pixel(9, 10), CURSOR);
+        assert_eq!(pixel(11, 10), background);
+        assert_eq!(pixel(3, 15), CURSOR);
+        assert_eq!(pixel(6, 14), CURSOR);
+        assert_eq!(pixel(7, 14), CURSOR);
+        assert_eq!(pixel(3, 14), background);
+    }
 }
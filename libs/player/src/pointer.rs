@@ -0,0 +1,177 @@
+use platform_types::{Input, PointerButton};
+use std::cmp::{max, min};
+
+use crate::GameState;
+
+// How many rows each line scrolled with the wheel moves the cursor.
+const WHEEL_ROWS: usize = 2;
+
+impl GameState {
+    // The index of the pane at `(x, y)` on the screen, if there is one there.
+    fn pane_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.pane_viewports()
+            .iter()
+            .position(|v| v.x <= x && x < v.x + v.w && v.y <= y && y < v.y + v.h)
+    }
+
+    // The offset of the byte drawn at `(x, y)` on the screen by the focused pane, if there
+    // is one there.
+    fn offset_under(&self, x: usize, y: usize) -> Option<usize> {
+        let pane = self.focused_pane();
+        let viewport = self.pane_viewports()[pane];
+        let (x, y) = (x.checked_sub(viewport.x)?, y.checked_sub(viewport.y)?);
+        self.render_mode
+            .offset_at(x, y, &self.pane_settings(pane))
+            .filter(|&offset| offset < self.transformed.len())
+    }
+}
+
+/// Clicking on a byte moves the cursor to it, and focuses the pane it is in. Dragging
+/// selects the bytes from where the drag started to the one under the pointer, and the
+/// wheel moves the cursor a few rows at a time, scrolling the panes along with it.
+pub(crate) fn update(state: &mut GameState, input: Input) {
+    let pointer = input.pointer;
+    let (previous_cursor, previous_selection) = (state.cursor, state.selection);
+
+    if let Some((x, y)) = pointer.position {
+        if pointer.pressed_this_frame(PointerButton::Primary) {
+            if let Some(pane) = state.pane_at(x, y) {
+                state.focus_pane(pane);
+            }
+            state.drag_start = state.offset_under(x, y);
+            if let Some(offset) = state.drag_start {
+                state.cursor = offset;
+                state.selection = None;
+            }
+        } else if pointer.buttons.contains(PointerButton::Primary) {
+            if let (Some(start), Some(offset)) = (state.drag_start, state.offset_under(x, y)) {
+                state.cursor = offset;
+                state.selection = if offset == start {
+                    None
+                } else {
+                    Some((min(start, offset), max(start, offset) + 1))
+                };
+            }
+        }
+    }
+    if !pointer.buttons.contains(PointerButton::Primary) {
+        state.drag_start = None;
+    }
+
+    let settings = state.pane_settings(state.focused_pane());
+    if let Some(bytes_per_row) = state.render_mode.bytes_per_row(&settings) {
+        let rows = pointer.wheel.unsigned_abs() as usize * WHEEL_ROWS;
+        let distance = rows.saturating_mul(bytes_per_row);
+        state.cursor = if pointer.wheel < 0 {
+            state.cursor.saturating_sub(distance)
+        } else {
+            min(
+                state.cursor.saturating_add(distance),
+                state.transformed.len().saturating_sub(1),
+            )
+        };
+    }
+
+    if state.cursor != previous_cursor || state.selection != previous_selection {
+        state.show_cursor();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{update_and_render, RenderModeKind, Settings};
+    use platform_types::{Pointer, Speaker};
+    use rendering::Framebuffer;
+
+    fn frame(state: &mut GameState, framebuffer: &mut Framebuffer, pointer: Pointer) {
+        let input = Input {
+            pointer,
+            ..Input::new()
+        };
+        update_and_render(framebuffer, state, input, &mut Speaker::new());
+    }
+
+    fn pointer(x: usize, y: usize, previous: PointerButton::Ty) -> Pointer {
+        Pointer {
+            position: Some((x, y)),
+            buttons: PointerButton::Primary,
+            previous_buttons: previous,
+            wheel: 0,
+        }
+    }
+
+    #[test]
+    fn clicking_a_pixel_moves_the_cursor_to_its_byte_and_dragging_selects() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        state.render_mode = RenderModeKind::Encoding.into();
+        state.settings = Settings {
+            zoom: 2,
+            ..Settings::default()
+        };
+        let width = state.settings.screen_width / 2;
+
+        frame(
+            &mut state,
+            &mut framebuffer,
+            pointer(7, 5, PointerButton::Ty::empty()),
+        );
+        assert_eq!(state.cursor, 2 * width + 3);
+        assert_eq!(state.selection, None);
+
+        frame(
+            &mut state,
+            &mut framebuffer,
+            pointer(1, 1, PointerButton::Primary),
+        );
+        assert_eq!(state.cursor, 0);
+        assert_eq!(state.selection, Some((0, 2 * width + 4)));
+
+        // Letting go keeps the selection, and moving the pointer afterwards doesn't
+        // change it.
+        let released = Pointer {
+            buttons: PointerButton::Ty::empty(),
+            ..pointer(9, 9, PointerButton::Primary)
+        };
+        frame(&mut state, &mut framebuffer, released);
+        frame(
+            &mut state,
+            &mut framebuffer,
+            Pointer {
+                previous_buttons: PointerButton::Ty::empty(),
+                ..released
+            },
+        );
+        assert_eq!(state.selection, Some((0, 2 * width + 4)));
+    }
+
+    #[test]
+    fn clicking_another_pane_focuses_it_and_the_wheel_scrolls_by_rows() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        state.render_mode = RenderModeKind::ThreeBitsPerPixel.into();
+        state.set_pane_count(2);
+        let right = state.pane_viewports()[1];
+
+        frame(
+            &mut state,
+            &mut framebuffer,
+            pointer(right.x, right.y, PointerButton::Ty::empty()),
+        );
+        assert_eq!(state.focused_pane(), 1);
+        assert_eq!(state.render_mode.kind(), RenderModeKind::Encoding);
+        assert_eq!(state.cursor, 0);
+
+        let wheel = Pointer {
+            wheel: 3,
+            ..Pointer::default()
+        };
+        frame(&mut state, &mut framebuffer, wheel);
+        let bytes_per_row = state
+            .render_mode
+            .bytes_per_row(&state.pane_settings(1))
+            .unwrap();
+        assert_eq!(state.cursor, 3 * WHEEL_ROWS * bytes_per_row);
+    }
+}
@@ -0,0 +1,374 @@
+use platform_types::EditKey;
+use rendering::{Framebuffer, BLACK, CURSOR, FONT_ADVANCE, RED_INDEX, WHITE_INDEX};
+
+use crate::{GameState, Template, Transform, HUD_HEIGHT};
+
+/// What the text typed into the prompt is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    GoToOffset,
+    Search,
+    Template,
+    /// The parameter of the transform at this index, like an XOR key.
+    Parameter(usize),
+}
+
+impl PromptKind {
+    /// The kinds that stand on their own, rather than being about something in the menu.
+    pub const ALL: [PromptKind; 3] = [
+        PromptKind::GoToOffset,
+        PromptKind::Search,
+        PromptKind::Template,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptKind::GoToOffset => "Go to offset",
+            PromptKind::Search => "Search",
+            PromptKind::Template => "Template",
+            PromptKind::Parameter(_) => "Parameter in hex",
+        }
+    }
+}
+
+/// A one line text field over the bottom of the screen, opened from the menu. Enter does
+/// whatever the kind of prompt is for with the text, and Escape closes it.
+#[derive(Default)]
+pub struct Prompt {
+    kind: Option<PromptKind>,
+    text: String,
+    // Where the caret is in `text`, counting in chars rather than bytes.
+    caret: usize,
+    // Why the text couldn't be used the last time Enter was pressed.
+    error: Option<String>,
+    // What was on screen under the prompt, so it can be put back if nothing changed.
+    backdrop: Vec<u32>,
+}
+
+impl Prompt {
+    pub fn is_open(&self) -> bool {
+        self.kind.is_some()
+    }
+
+    pub fn kind(&self) -> Option<PromptKind> {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    // Forgets what was behind the prompt when everything behind it is drawn again, so it
+    // is picked up afresh on the next frame.
+    pub(crate) fn redrawn(&mut self) {
+        self.backdrop.clear();
+    }
+
+    // The byte index in `text` of the char at `caret`.
+    fn caret_index(&self) -> usize {
+        self.text
+            .char_indices()
+            .nth(self.caret)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+// Reads an offset typed in decimal, or in hex after `0x`.
+pub(crate) fn parse_offset(text: &str) -> Option<usize> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+impl GameState {
+    /// Opens an empty prompt of the given kind, replacing any prompt that was open.
+    pub fn open_prompt(&mut self, kind: PromptKind) {
+        self.prompt.kind = Some(kind);
+        self.prompt.text.clear();
+        self.prompt.caret = 0;
+        self.prompt.error = None;
+    }
+
+    /// Opens a prompt for the parameter of the transform at `index`, starting from the
+    /// value it has now.
+    pub fn open_parameter_prompt(&mut self, index: usize) {
+        self.open_prompt(PromptKind::Parameter(index));
+        if let Some(parameter) = self.transforms().get(index).and_then(Transform::parameter) {
+            self.prompt.caret = parameter.chars().count();
+            self.prompt.text = parameter;
+        }
+    }
+
+    /// Types `ch` at the caret. Does nothing if no prompt is open.
+    pub fn type_into_prompt(&mut self, ch: char) {
+        if !self.prompt.is_open() || ch.is_control() {
+            return;
+        }
+        let i = self.prompt.caret_index();
+        self.prompt.text.insert(i, ch);
+        self.prompt.caret += 1;
+        self.prompt.error = None;
+    }
+
+    /// Does what `key` does to the prompt's text. Does nothing if no prompt is open.
+    pub fn edit_prompt(&mut self, key: EditKey) {
+        let prompt = &mut self.prompt;
+        if !prompt.is_open() {
+            return;
+        }
+        let length = prompt.text.chars().count();
+        match key {
+            EditKey::Backspace => {
+                if prompt.caret > 0 {
+                    prompt.caret -= 1;
+                    let i = prompt.caret_index();
+                    prompt.text.remove(i);
+                }
+            }
+            EditKey::Delete => {
+                if prompt.caret < length {
+                    let i = prompt.caret_index();
+                    prompt.text.remove(i);
+                }
+            }
+            EditKey::Left => prompt.caret = prompt.caret.saturating_sub(1),
+            EditKey::Right => prompt.caret = std::cmp::min(prompt.caret + 1, length),
+            EditKey::Home => prompt.caret = 0,
+            EditKey::End => prompt.caret = length,
+            EditKey::Enter => match self.submit_prompt() {
+                Ok(()) => {
+                    // Whatever the text was for redraws the screen, so the backdrop is
+                    // out of date.
+                    self.prompt.kind = None;
+                    self.prompt.backdrop.clear();
+                }
+                Err(error) => self.prompt.error = Some(error),
+            },
+            EditKey::Escape => prompt.kind = None,
+        }
+    }
+
+    // Uses the prompt's text for what the prompt is for, or says why it can't be used.
+    fn submit_prompt(&mut self) -> Result<(), String> {
+        let text = self.prompt.text.clone();
+        match self.prompt.kind {
+            Some(PromptKind::GoToOffset) => {
+                let offset = parse_offset(&text).ok_or("not an offset")?;
+                if offset >= self.transformed.len() {
+                    return Err(format!("past the end at {:X}", self.transformed.len()));
+                }
+                self.cursor = offset;
+                self.selection = None;
+                self.reset_render_mode();
+            }
+            Some(PromptKind::Search) if text.trim().is_empty() => self.clear_search(),
+            Some(PromptKind::Search) => self.search_for(&text).map_err(|e| e.to_string())?,
+            Some(PromptKind::Template) if text.trim().is_empty() => self.clear_template(),
+            Some(PromptKind::Template) => {
+                let template = Template::parse(&text).map_err(|e| e.to_string())?;
+                self.apply_template(template, self.cursor);
+            }
+            Some(PromptKind::Parameter(i)) => {
+                // Loading other bytes clears the transforms while the prompt is open.
+                let mut transform = self.transforms().get(i).cloned().ok_or("no transform")?;
+                transform.set_parameter(&text)?;
+                self.set_transform(i, transform);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Draws the prompt over the bottom `HUD_HEIGHT` rows of the screen while it is open, and
+/// puts back what was there once it closes.
+pub fn render(framebuffer: &mut Framebuffer, prompt: &mut Prompt) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let top = height.saturating_sub(HUD_HEIGHT);
+
+    let kind = match prompt.kind {
+        Some(kind) => kind,
+        None => {
+            if !prompt.backdrop.is_empty() {
+                framebuffer.with_viewport(0, top, width, HUD_HEIGHT, |framebuffer| {
+                    framebuffer.set_viewport_pixels(&prompt.backdrop)
+                });
+                prompt.backdrop.clear();
+            }
+            return;
+        }
+    };
+    if prompt.backdrop.is_empty() {
+        prompt.backdrop = framebuffer.with_viewport(0, top, width, HUD_HEIGHT, |framebuffer| {
+            framebuffer.viewport_pixels()
+        });
+    }
+
+    framebuffer.draw_filled_rect(0, top, width, HUD_HEIGHT, BLACK);
+
+    // The font only has ASCII, so anything else is shown as a question mark.
+    let label = format!("{}: ", kind.name());
+    let text: Vec<u8> = prompt
+        .text
+        .chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .collect();
+
+    // Scroll the text along so the caret is always on screen. On a screen too narrow for
+    // the label this still keeps one column, so the scroll never passes the caret.
+    let columns = width / FONT_ADVANCE as usize;
+    let text_columns = std::cmp::max(columns.saturating_sub(label.len() + 1), 1);
+    let scroll = (prompt.caret + 1).saturating_sub(text_columns);
+    let shown = &text[scroll..std::cmp::min(text.len(), scroll + text_columns)];
+
+    let advance = FONT_ADVANCE as usize;
+    framebuffer.print_line(label.as_bytes(), 0, top + 1, WHITE_INDEX);
+    let text_x = label.len() * advance;
+    framebuffer.print_line(shown, text_x, top + 1, WHITE_INDEX);
+    let caret_x = text_x + (prompt.caret - scroll) * advance;
+    framebuffer.draw_filled_rect(caret_x, top + 1, 1, HUD_HEIGHT - 2, CURSOR);
+
+    if let Some(ref error) = prompt.error {
+        let mut error = error.clone();
+        error.truncate(columns);
+        let x = width.saturating_sub(error.len() * advance);
+        framebuffer.draw_filled_rect(x, top, width - x, HUD_HEIGHT, BLACK);
+        framebuffer.print_line(error.as_bytes(), x, top + 1, RED_INDEX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(state: &mut GameState, text: &str) {
+        for ch in text.chars() {
+            state.type_into_prompt(ch);
+        }
+    }
+
+    #[test]
+    fn parse_offset_reads_decimal_and_hex() {
+        assert_eq!(parse_offset("42"), Some(42));
+        assert_eq!(parse_offset(" 0x2A "), Some(42));
+        assert_eq!(parse_offset("0X2a"), Some(42));
+        assert_eq!(parse_offset("2A"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn the_caret_moves_through_the_text_and_edits_happen_there() {
+        let mut state = GameState::new();
+        type_text(&mut state, "ignored");
+        assert!(!state.prompt.is_open());
+
+        state.open_prompt(PromptKind::Search);
+        type_text(&mut state, "acé");
+        state.edit_prompt(EditKey::Left);
+        state.edit_prompt(EditKey::Backspace);
+        assert_eq!(state.prompt.text(), "aé");
+
+        state.edit_prompt(EditKey::Home);
+        state.type_into_prompt('"');
+        state.edit_prompt(EditKey::Delete);
+        state.edit_prompt(EditKey::End);
+        state.type_into_prompt('\n');
+        state.type_into_prompt('"');
+        assert_eq!(state.prompt.text(), "\"é\"");
+
+        state.edit_prompt(EditKey::Escape);
+        assert!(!state.prompt.is_open());
+    }
+
+    #[test]
+    fn enter_goes_to_offsets_and_keeps_the_prompt_open_on_errors() {
+        let mut state = GameState::new();
+        state.open_prompt(PromptKind::GoToOffset);
+        type_text(&mut state, "0x10");
+        state.edit_prompt(EditKey::Enter);
+        assert_eq!(state.cursor, 0x10);
+        assert!(!state.prompt.is_open());
+
+        state.open_prompt(PromptKind::GoToOffset);
+        let length = state.transformed.len().to_string();
+        type_text(&mut state, &length);
+        state.edit_prompt(EditKey::Enter);
+        assert!(state.prompt.is_open());
+        assert!(state.prompt.error().unwrap().starts_with("past the end"));
+        assert_eq!(state.cursor, 0x10);
+    }
+
+    #[test]
+    fn enter_searches_and_applies_templates_at_the_cursor() {
+        let mut state = GameState::new();
+        state.open_prompt(PromptKind::Search);
+        type_text(&mut state, "\"extern\"");
+        state.edit_prompt(EditKey::Enter);
+        assert!(!state.prompt.is_open());
+        assert_eq!(state.search.current_match(), Some(0));
+
+        state.open_prompt(PromptKind::Template);
+        type_text(&mut state, "struct s { a: u8 }");
+        state.edit_prompt(EditKey::Enter);
+        assert!(state.fields.is_active());
+
+        state.open_prompt(PromptKind::Template);
+        type_text(&mut state, "struct");
+        state.edit_prompt(EditKey::Enter);
+        assert!(state.prompt.error().is_some());
+    }
+
+    #[test]
+    fn enter_sets_the_parameter_of_a_transform() {
+        let mut state = GameState::new();
+        state.push_transform(Transform::Xor(vec![0x12]));
+        state.open_parameter_prompt(0);
+        assert_eq!(state.prompt.text(), "12");
+
+        type_text(&mut state, "34");
+        state.edit_prompt(EditKey::Enter);
+        assert!(!state.prompt.is_open());
+        assert_eq!(state.transforms(), [Transform::Xor(vec![0x12, 0x34])]);
+
+        state.open_parameter_prompt(0);
+        state.clear_transforms();
+        state.edit_prompt(EditKey::Enter);
+        assert_eq!(state.prompt.error(), Some("no transform"));
+    }
+
+    #[test]
+    fn the_prompt_is_drawn_over_the_bottom_and_taken_away_again() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.clear_to(0xFF12_3456);
+        let bottom = framebuffer.buffer.len() - 1;
+
+        state.open_prompt(PromptKind::Search);
+        render(&mut framebuffer, &mut state.prompt);
+        assert_eq!(framebuffer.buffer[bottom], BLACK);
+
+        state.edit_prompt(EditKey::Escape);
+        render(&mut framebuffer, &mut state.prompt);
+        assert_eq!(framebuffer.buffer[bottom], 0xFF12_3456);
+    }
+
+    #[test]
+    fn the_prompt_fits_on_screens_narrower_than_its_label() {
+        let mut state = GameState::new();
+        let mut framebuffer = Framebuffer::new();
+        framebuffer.resize(30, 100);
+
+        state.open_prompt(PromptKind::Search);
+        type_text(&mut state, "ab");
+        render(&mut framebuffer, &mut state.prompt);
+        state.edit_prompt(EditKey::Home);
+        render(&mut framebuffer, &mut state.prompt);
+        assert_eq!(framebuffer.buffer[framebuffer.buffer.len() - 1], BLACK);
+    }
+}
@@ -0,0 +1,98 @@
+use platform_types::{Input, Speaker};
+use rendering::Framebuffer;
+use std::cmp::min;
+
+use crate::{update_and_render, GameState, ModeOptions, RenderMode, RenderModeKind, Settings};
+
+/// Everything about a visualisation besides the bytes and the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub settings: Settings,
+    pub mode_options: ModeOptions,
+    /// Where to put the cursor. The pixel based modes scroll so it is on screen.
+    pub offset: usize,
+    /// How many frames to run the mode for, or `None` to keep going until it has drawn
+    /// every byte. The geometric modes draw `primitives_per_frame` primitives a frame.
+    pub frames: Option<usize>,
+}
+
+/// Draws `bytes` the way `mode` would, without needing a browser or any input. This
+/// runs the same code as the interactive version, so the HUD and the other overlays are
+/// drawn too, according to `options.settings`.
+pub fn render(bytes: &[u8], mode: RenderModeKind, options: &RenderOptions) -> Framebuffer {
+    let mut framebuffer = Framebuffer::with_size(
+        options.settings.screen_width,
+        options.settings.screen_height,
+    );
+    let mut speaker = Speaker::new();
+
+    let mut state = GameState::new();
+    state.settings = options.settings;
+    state.mode_options = options.mode_options;
+    state.load_bytes(bytes.to_vec());
+    state.render_mode = mode.into();
+    state.cursor = min(options.offset, state.transformed.len().saturating_sub(1));
+    state.show_cursor();
+
+    match options.frames {
+        Some(frames) => {
+            for _ in 0..frames {
+                update_and_render(&mut framebuffer, &mut state, Input::new(), &mut speaker);
+            }
+        }
+        None => {
+            if let RenderMode::Geometric(ref mut g_state) = state.render_mode {
+                g_state.seek(state.transformed.len());
+            }
+            loop {
+                update_and_render(&mut framebuffer, &mut state, Input::new(), &mut speaker);
+                if state.render_mode.is_finished(state.transformed.len()) {
+                    break;
+                }
+            }
+        }
+    }
+
+    framebuffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Primitive;
+    use rendering::PALETTE;
+
+    #[test]
+    fn geometric_modes_are_drawn_to_the_end_by_default() {
+        let bytes = [0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80];
+        let options = RenderOptions::default();
+
+        let drawn = render(&bytes, RenderModeKind::Geometric(Primitive::Line), &options);
+        let first_frame = render(
+            &bytes,
+            RenderModeKind::Geometric(Primitive::Line),
+            &RenderOptions {
+                frames: Some(1),
+                ..options
+            },
+        );
+
+        assert_ne!(drawn.buffer, first_frame.buffer);
+    }
+
+    #[test]
+    fn pixel_modes_scroll_to_the_offset() {
+        let mut bytes = vec![0; 0x10000];
+        bytes.extend(vec![0xFF; 0x10000]);
+        let options = RenderOptions {
+            offset: 0x18000,
+            ..RenderOptions::default()
+        };
+
+        let framebuffer = render(&bytes, RenderModeKind::ThreeBitsPerPixel, &options);
+
+        // Every byte on screen is 0xFF, so away from the cursor every pixel is the last
+        // colour.
+        assert_eq!(framebuffer.buffer[framebuffer.width * 100], PALETTE[7]);
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt;
+
+/// A sequence of bytes to look for, where each byte comes with a mask of the bits that
+/// have to match. A mask of `0xFF` is an exact byte, `0xF0` only checks the high nibble
+/// and `0` matches anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    bytes: Vec<u8>,
+    masks: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    InvalidCharacter(char),
+    OddNibbleCount,
+    UnterminatedString,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Empty => write!(f, "empty pattern"),
+            ParseError::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            ParseError::OddNibbleCount => write!(f, "odd number of nibbles"),
+            ParseError::UnterminatedString => write!(f, "unterminated string"),
+        }
+    }
+}
+
+impl Pattern {
+    pub fn exact(bytes: &[u8]) -> Self {
+        Pattern {
+            bytes: bytes.to_vec(),
+            masks: vec![0xFF; bytes.len()],
+        }
+    }
+
+    pub fn ascii(s: &str) -> Self {
+        Self::exact(s.as_bytes())
+    }
+
+    pub fn utf16_le(s: &str) -> Self {
+        let bytes: Vec<u8> = s
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes().to_vec())
+            .collect();
+        Self::exact(&bytes)
+    }
+
+    pub fn utf16_be(s: &str) -> Self {
+        let bytes: Vec<u8> = s
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes().to_vec())
+            .collect();
+        Self::exact(&bytes)
+    }
+
+    /// Parses hex pairs like `4D 5A ?? ?? 50 45`. Whitespace is ignored and a `?` in place
+    /// of a hex digit matches any nibble, so `4?` matches `0x40` through `0x4F`.
+    pub fn parse_hex(s: &str) -> Result<Self, ParseError> {
+        let mut bytes = Vec::with_capacity(s.len() / 2);
+        let mut masks = Vec::with_capacity(s.len() / 2);
+
+        let mut high: Option<(u8, u8)> = None;
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            let (nibble, mask) = match c {
+                '?' => (0, 0),
+                _ => match c.to_digit(16) {
+                    Some(d) => (d as u8, 0xF),
+                    None => return Err(ParseError::InvalidCharacter(c)),
+                },
+            };
+
+            match high.take() {
+                None => high = Some((nibble, mask)),
+                Some((high_nibble, high_mask)) => {
+                    bytes.push(high_nibble << 4 | nibble);
+                    masks.push(high_mask << 4 | mask);
+                }
+            }
+        }
+
+        if high.is_some() {
+            return Err(ParseError::OddNibbleCount);
+        }
+        if bytes.is_empty() {
+            return Err(ParseError::Empty);
+        }
+
+        Ok(Pattern { bytes, masks })
+    }
+
+    /// Parses a query typed by the user. `"text"` searches for ASCII, `u"text"` for
+    /// UTF-16LE, `U"text"` for UTF-16BE, and anything else is read as hex.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let s = s.trim();
+
+        let (quoted, constructor): (&str, fn(&str) -> Self) = if s.starts_with("u\"") {
+            (&s[1..], Self::utf16_le)
+        } else if s.starts_with("U\"") {
+            (&s[1..], Self::utf16_be)
+        } else if s.starts_with('"') {
+            (s, Self::ascii)
+        } else {
+            return Self::parse_hex(s);
+        };
+
+        if quoted.len() < 2 || !quoted.ends_with('"') {
+            return Err(ParseError::UnterminatedString);
+        }
+
+        let pattern = constructor(&quoted[1..quoted.len() - 1]);
+        if pattern.is_empty() {
+            Err(ParseError::Empty)
+        } else {
+            Ok(pattern)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    #[inline]
+    fn byte_matches(&self, j: usize, byte: u8) -> bool {
+        byte & self.masks[j] == self.bytes[j] & self.masks[j]
+    }
+
+    fn matches_at(&self, haystack: &[u8], i: usize) -> bool {
+        (0..self.len())
+            .rev()
+            .all(|j| self.byte_matches(j, haystack[i + j]))
+    }
+
+    /// Returns the start of every match, including overlapping ones, in ascending order.
+    //
+    // This is Horspool's algorithm, with the shift table built by checking every byte
+    // value against every position's mask. A wildcard near the end of the pattern limits
+    // how far we can skip, but exact patterns get the usual sublinear behaviour.
+    pub fn find_all(&self, haystack: &[u8]) -> Vec<usize> {
+        let mut output = Vec::new();
+
+        let m = self.len();
+        if m == 0 || m > haystack.len() {
+            return output;
+        }
+
+        let mut shift = [m; 256];
+        for j in 0..m - 1 {
+            for (c, s) in shift.iter_mut().enumerate() {
+                if self.byte_matches(j, c as u8) {
+                    *s = m - 1 - j;
+                }
+            }
+        }
+
+        let mut i = 0;
+        while i + m <= haystack.len() {
+            if self.matches_at(haystack, i) {
+                output.push(i);
+            }
+            i += shift[haystack[i + m - 1] as usize];
+        }
+
+        output
+    }
+}
+
+/// The current search, with its matches cached so the renderers can ask about them every
+/// frame without rescanning the bytes.
+#[derive(Default)]
+pub struct Search {
+    pattern: Option<Pattern>,
+    matches: Vec<usize>,
+    current: usize,
+}
+
+impl Search {
+    pub fn set_pattern(&mut self, pattern: Pattern, bytes: &[u8]) {
+        self.pattern = Some(pattern);
+        self.refresh(bytes);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Call this whenever the bytes being searched change.
+    pub fn refresh(&mut self, bytes: &[u8]) {
+        self.matches = match self.pattern {
+            Some(ref pattern) => pattern.find_all(bytes),
+            None => Vec::new(),
+        };
+        self.current = 0;
+    }
+
+    pub fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    pub fn match_len(&self) -> usize {
+        self.pattern.as_ref().map(Pattern::len).unwrap_or(0)
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_match(&self) -> Option<usize> {
+        self.matches.get(self.current).cloned()
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// Returns the start of a match overlapping `start..start + len`, if any.
+    pub fn match_overlapping(&self, start: usize, len: usize) -> Option<usize> {
+        let match_len = self.match_len();
+        if len == 0 || match_len == 0 {
+            return None;
+        }
+
+        // Every match has the same length, so if the last match starting before the end of
+        // the range doesn't reach the range, none of the earlier ones do either.
+        let end = start + len;
+        let after = self.matches.partition_point(|&m| m < end);
+        if after == 0 {
+            return None;
+        }
+
+        let candidate = self.matches[after - 1];
+        if candidate + match_len > start {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_current(&self, match_start: usize) -> bool {
+        self.current_match() == Some(match_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_find_all(pattern: &Pattern, haystack: &[u8]) -> Vec<usize> {
+        if pattern.len() > haystack.len() {
+            return Vec::new();
+        }
+        (0..=haystack.len() - pattern.len())
+            .filter(|&i| pattern.matches_at(haystack, i))
+            .collect()
+    }
+
+    #[test]
+    fn parse_hex_handles_wildcards_and_spacing() {
+        let pattern = Pattern::parse_hex("4D 5A ?? 4? 50").unwrap();
+
+        assert_eq!(pattern.bytes, vec![0x4D, 0x5A, 0, 0x40, 0x50]);
+        assert_eq!(pattern.masks, vec![0xFF, 0xFF, 0, 0xF0, 0xFF]);
+        assert_eq!(
+            Pattern::parse_hex("4D5A"),
+            Ok(Pattern::exact(&[0x4D, 0x5A]))
+        );
+    }
+
+    #[test]
+    fn parse_hex_rejects_bad_input() {
+        assert_eq!(Pattern::parse_hex("4D 5"), Err(ParseError::OddNibbleCount));
+        assert_eq!(
+            Pattern::parse_hex("4G"),
+            Err(ParseError::InvalidCharacter('G'))
+        );
+        assert_eq!(Pattern::parse_hex("  "), Err(ParseError::Empty));
+    }
+
+    #[test]
+    fn parse_recognizes_strings() {
+        assert_eq!(Pattern::parse("\"MZ\""), Ok(Pattern::exact(b"MZ")));
+        assert_eq!(Pattern::parse("u\"MZ\""), Ok(Pattern::exact(b"M\0Z\0")));
+        assert_eq!(Pattern::parse("U\"MZ\""), Ok(Pattern::exact(b"\0M\0Z")));
+        assert_eq!(Pattern::parse("\"MZ"), Err(ParseError::UnterminatedString));
+    }
+
+    #[test]
+    fn find_all_finds_overlapping_matches() {
+        let pattern = Pattern::exact(b"aa");
+
+        assert_eq!(pattern.find_all(b"aaab aa"), vec![0, 1, 5]);
+    }
+
+    #[test]
+    fn find_all_matches_the_naive_search() {
+        let haystack = include_bytes!("search.rs");
+        for query in &[
+            "4D 5A",
+            "?? 65",
+            "6? 6?",
+            "\"self\"",
+            "0A ?? ?? ?? ?? 6C",
+            "?7",
+        ] {
+            let pattern = Pattern::parse(query).unwrap();
+
+            assert_eq!(
+                pattern.find_all(haystack),
+                naive_find_all(&pattern, haystack),
+                "query: {}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn match_overlapping_finds_matches_touching_the_range() {
+        let mut search = Search::default();
+        search.set_pattern(Pattern::exact(b"ab"), b"xxabxxxxab");
+
+        assert_eq!(search.match_overlapping(0, 2), None);
+        assert_eq!(search.match_overlapping(0, 3), Some(2));
+        assert_eq!(search.match_overlapping(3, 4), Some(2));
+        assert_eq!(search.match_overlapping(4, 4), None);
+        assert_eq!(search.match_overlapping(8, 8), Some(8));
+    }
+
+    #[test]
+    fn next_and_previous_wrap_around() {
+        let mut search = Search::default();
+        search.set_pattern(Pattern::exact(b"a"), b"a a a");
+
+        search.previous();
+        assert_eq!(search.current_match(), Some(4));
+        search.next();
+        assert_eq!(search.current_match(), Some(0));
+    }
+}
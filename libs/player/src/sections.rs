@@ -0,0 +1,226 @@
+use formats::{Region, RegionKind};
+use rendering::{
+    Framebuffer, BLACK, BLUE_INDEX, FONT_ADVANCE, FONT_SIZE, GREEN_INDEX, GREY_INDEX, PALETTE,
+    PURPLE_INDEX, RED_INDEX, SPRITE_SIZE, WHITE_INDEX, YELLOW_INDEX,
+};
+use std::cmp::min;
+
+use crate::{
+    outline_pixel_span, pixel_grid_size, tint_pixel_span, visible_pixel_span, SectionMap, Settings,
+};
+
+// Font colours for the sections, in the order they are handed out. Black is left out so
+// the names show up on the label and legend backgrounds.
+const SECTION_COLOURS: [u8; 7] = [
+    RED_INDEX,
+    YELLOW_INDEX,
+    GREEN_INDEX,
+    BLUE_INDEX,
+    PURPLE_INDEX,
+    WHITE_INDEX,
+    GREY_INDEX,
+];
+
+// Chunks alternate between these, so neighbouring ones can be told apart.
+const CHUNK_COLOURS: [u8; 2] = [YELLOW_INDEX, BLUE_INDEX];
+
+// How opaque the chunk bands are. Enough to see the bytes underneath.
+const CHUNK_ALPHA: u32 = 0x70;
+
+const LEGEND_CHARS: usize = 26;
+const LEGEND_W: usize = SPRITE_SIZE as usize * 2 + LEGEND_CHARS * FONT_ADVANCE as usize;
+
+// Sections are coloured by their position in the file's section table, which is also the
+// order the legend lists them in. Segments are all drawn in white, and chunks alternate.
+fn colour_index(regions: &[Region], i: usize) -> u8 {
+    let number = |kind| regions[..i].iter().filter(|r| r.kind == kind).count();
+
+    match regions[i].kind {
+        RegionKind::Segment => WHITE_INDEX,
+        RegionKind::Section => SECTION_COLOURS[number(RegionKind::Section) % SECTION_COLOURS.len()],
+        RegionKind::Chunk => CHUNK_COLOURS[number(RegionKind::Chunk) % CHUNK_COLOURS.len()],
+    }
+}
+
+/// Outlines and labels `regions` over a pixel based mode, which is showing the bytes from
+/// `byte_index` onwards, `bits_per_pixel` bits to a pixel. Chunks are tinted bands instead,
+/// since there are often too many of them to label.
+pub fn render_over_pixels(
+    framebuffer: &mut Framebuffer,
+    regions: &[Region],
+    byte_index: usize,
+    bits_per_pixel: usize,
+    settings: &Settings,
+) {
+    if settings.section_map == SectionMap::Off || regions.is_empty() {
+        return;
+    }
+
+    // Segments go underneath, since they usually contain several sections.
+    for &kind in &[RegionKind::Chunk, RegionKind::Segment, RegionKind::Section] {
+        for (i, region) in regions.iter().enumerate() {
+            if region.kind != kind {
+                continue;
+            }
+            let (first_pixel, last_pixel) = match visible_pixel_span(
+                region.start,
+                region.len,
+                byte_index,
+                bits_per_pixel,
+                settings,
+            ) {
+                Some(span) => span,
+                None => continue,
+            };
+            let colour_index = colour_index(regions, i);
+            let colour = PALETTE[colour_index as usize];
+
+            if kind == RegionKind::Chunk {
+                let tint = (colour & 0x00FF_FFFF) | CHUNK_ALPHA << 24;
+                tint_pixel_span(framebuffer, first_pixel, last_pixel, settings, tint);
+                continue;
+            }
+
+            outline_pixel_span(framebuffer, first_pixel, last_pixel, settings, colour);
+
+            if kind == RegionKind::Section {
+                label(
+                    framebuffer,
+                    &region.name,
+                    first_pixel,
+                    colour_index,
+                    settings,
+                );
+            }
+        }
+    }
+
+    if settings.section_map == SectionMap::OutlinesAndLegend {
+        legend(framebuffer, regions, settings);
+    }
+}
+
+// Prints `name` on a black background at the given pixel, nudged back onto the screen if
+// it would run off the edge.
+fn label(
+    framebuffer: &mut Framebuffer,
+    name: &str,
+    pixel: usize,
+    colour_index: u8,
+    settings: &Settings,
+) {
+    let zoom = settings.zoom as usize;
+    let (width, _) = pixel_grid_size(settings);
+
+    let text_w = name.len() * FONT_ADVANCE as usize + 1;
+    let text_h = FONT_SIZE as usize;
+    let x = min(
+        (pixel % width) * zoom,
+        settings.screen_width.saturating_sub(text_w),
+    );
+    let y = min(
+        (pixel / width) * zoom,
+        settings.view_height().saturating_sub(text_h),
+    );
+
+    framebuffer.draw_filled_rect(x, y, text_w, text_h, BLACK);
+    framebuffer.print_line(name.as_bytes(), x + 1, y, colour_index);
+}
+
+fn legend(framebuffer: &mut Framebuffer, regions: &[Region], settings: &Settings) {
+    let margin = SPRITE_SIZE as usize;
+    let row_h = FONT_SIZE as usize;
+    let max_rows = settings.view_height().saturating_sub(margin * 2) / row_h;
+
+    let overflow = regions.len() > max_rows;
+    let rows = if overflow { max_rows } else { regions.len() };
+
+    let x = settings.screen_width.saturating_sub(LEGEND_W);
+    framebuffer.window(x, 0, LEGEND_W, rows * row_h + margin * 2);
+
+    let mut lines = Vec::with_capacity(rows);
+    for (i, region) in regions.iter().enumerate().take(rows) {
+        let line = format!(
+            "{:<10.10} {:>6X} {:>5X}",
+            region.name, region.start, region.len
+        );
+        // Names read from files can have any characters in them, so this cuts by
+        // characters rather than bytes.
+        let line = line.chars().take(LEGEND_CHARS).collect();
+        lines.push((line, colour_index(regions, i)));
+    }
+    if overflow && rows > 0 {
+        let hidden = regions.len() - (rows - 1);
+        lines[rows - 1] = (format!("... {} more", hidden), WHITE_INDEX);
+    }
+
+    for (row, (line, colour_index)) in lines.iter().enumerate() {
+        let y = margin + row * row_h;
+        framebuffer.print_line(line.as_bytes(), x + margin, y, *colour_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(name: &str, kind: RegionKind) -> Region {
+        Region {
+            name: name.to_string(),
+            kind,
+            start: 0,
+            len: 1,
+        }
+    }
+
+    #[test]
+    fn sections_are_coloured_in_order_regardless_of_segments() {
+        let regions = [
+            region("LOAD", RegionKind::Segment),
+            region(".text", RegionKind::Section),
+            region("LOAD", RegionKind::Segment),
+            region(".data", RegionKind::Section),
+        ];
+
+        assert_eq!(colour_index(&regions, 0), WHITE_INDEX);
+        assert_eq!(colour_index(&regions, 1), SECTION_COLOURS[0]);
+        assert_eq!(colour_index(&regions, 3), SECTION_COLOURS[1]);
+    }
+
+    #[test]
+    fn neighbouring_chunks_get_different_colours() {
+        let regions = [
+            region("IHDR", RegionKind::Chunk),
+            region("IDAT", RegionKind::Chunk),
+            region("IEND", RegionKind::Chunk),
+        ];
+
+        assert_ne!(colour_index(&regions, 0), colour_index(&regions, 1));
+        assert_ne!(colour_index(&regions, 1), colour_index(&regions, 2));
+    }
+
+    #[test]
+    fn long_section_lists_stay_above_the_hud() {
+        let regions: Vec<_> = (0..100)
+            .map(|i| region(&format!(".s{}", i), RegionKind::Section))
+            .collect();
+        let settings = Settings::default();
+        let mut framebuffer = Framebuffer::new();
+
+        legend(&mut framebuffer, &regions, &settings);
+
+        let below_view = &framebuffer.buffer[settings.view_height() * framebuffer.width..];
+        assert!(below_view.iter().all(|&pixel| pixel == PALETTE[0]));
+    }
+
+    #[test]
+    fn names_that_are_not_utf_8_fit_in_the_legend() {
+        // What `formats` makes of a section name of invalid bytes.
+        let name = String::from_utf8_lossy(&[0xFF; 9]);
+        let regions = [region(&name, RegionKind::Section)];
+        let mut framebuffer = Framebuffer::new();
+
+        legend(&mut framebuffer, &regions, &Settings::default());
+        assert_ne!(framebuffer.buffer, Framebuffer::new().buffer);
+    }
+}
@@ -0,0 +1,433 @@
+use inflate::{Format, Stream};
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::min;
+use std::fmt;
+
+use crate::{
+    transform, GameState, ModeOptions, RenderMode, RenderModeKind, Settings, Transform, MAX_PANES,
+    ZOOM_LEVELS,
+};
+
+/// Bumped whenever a saved session would mean something different to this version.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Everything about how a file is being looked at, so the investigation can be saved and
+/// picked up again later, or handed to someone else along with the file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    /// The FNV-1a hash of the file, in hex, so the session is only restored against the
+    /// file it was saved with. Kept as text since JavaScript can't hold all 64 bits.
+    pub file_hash: String,
+    pub file_length: usize,
+    /// The streams that were opened to get to the bytes being shown, outermost first.
+    pub views: Vec<ViewSession>,
+    pub transforms: Vec<Transform>,
+    /// Each pane's mode, in order.
+    pub panes: Vec<PaneSession>,
+    pub focused_pane: usize,
+    pub cursor: usize,
+    pub selection: Option<(usize, usize)>,
+    pub bookmark: Option<usize>,
+    pub settings: Settings,
+    pub mode_options: ModeOptions,
+}
+
+/// A stream that was opened, with how the bytes it was found in were being looked at.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewSession {
+    /// The name of the stream's `inflate::Format`.
+    pub format: String,
+    pub offset: usize,
+    pub transforms: Vec<Transform>,
+    pub cursor: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneSession {
+    pub kind: RenderModeKind,
+    /// The first byte shown, or for the geometric modes how many shapes have been drawn.
+    pub position: usize,
+    pub paused: bool,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Json(serde_json::Error),
+    Version(u32),
+    /// The session was saved while looking at some other file.
+    DifferentFile,
+    UnknownFormat(String),
+    Stream(inflate::Error),
+    /// There have to be from 1 to `MAX_PANES` panes.
+    PaneCount(usize),
+    /// The zoom isn't one of `ZOOM_LEVELS`.
+    Zoom(u8),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SessionError::Json(ref e) => write!(f, "not a session: {}", e),
+            SessionError::Version(version) => write!(
+                f,
+                "saved by version {} but this is version {}",
+                version, SESSION_VERSION
+            ),
+            SessionError::DifferentFile => write!(f, "saved with a different file"),
+            SessionError::UnknownFormat(ref name) => write!(f, "unknown stream format {:?}", name),
+            SessionError::Stream(ref e) => write!(f, "could not open a stream again: {}", e),
+            SessionError::PaneCount(count) => write!(
+                f,
+                "{} panes, but there can only be from 1 to {}",
+                count, MAX_PANES
+            ),
+            SessionError::Zoom(zoom) => {
+                write!(f, "zoom {} is not one of {:?}", zoom, ZOOM_LEVELS)
+            }
+        }
+    }
+}
+
+impl Session {
+    pub fn to_json(&self) -> String {
+        // Every field is plain data, so this can't fail.
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SessionError> {
+        serde_json::from_str(json).map_err(SessionError::Json)
+    }
+}
+
+// 64 bit FNV-1a, which is quick and plenty to tell files apart.
+fn file_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xCBF2_9CE4_8422_2325u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    });
+    format!("{:016x}", hash)
+}
+
+impl RenderMode {
+    fn position(&self) -> usize {
+        match *self {
+            RenderMode::Geometric(ref g_state) => g_state.target_index(),
+            RenderMode::ThreeBitsPerPixel(ref tbbp_state) => tbbp_state.byte_index,
+            RenderMode::Encoding(ref e_state) => e_state.byte_index,
+            RenderMode::Comparison(ref c_state) => c_state.byte_index,
+        }
+    }
+
+    fn restore(&mut self, pane: PaneSession) {
+        match *self {
+            RenderMode::Geometric(ref mut g_state) => {
+                g_state.seek(pane.position);
+                g_state.paused = pane.paused;
+            }
+            RenderMode::ThreeBitsPerPixel(ref mut tbbp_state) => {
+                tbbp_state.byte_index = pane.position;
+                tbbp_state.rendered = false;
+            }
+            RenderMode::Encoding(ref mut e_state) => {
+                e_state.byte_index = pane.position;
+                e_state.rendered = false;
+            }
+            RenderMode::Comparison(ref mut c_state) => {
+                c_state.byte_index = pane.position;
+                c_state.rendered = false;
+            }
+        }
+    }
+}
+
+impl GameState {
+    // The bytes as they were loaded, before any streams were opened.
+    fn file(&self) -> &[u8] {
+        self.views.first().map_or(&self.bytes, |view| &view.bytes)
+    }
+
+    pub fn session(&self) -> Session {
+        let file = self.file();
+        Session {
+            version: SESSION_VERSION,
+            file_hash: file_hash(file),
+            file_length: file.len(),
+            views: self
+                .views
+                .iter()
+                .map(|view| ViewSession {
+                    format: view.stream.format.name().to_string(),
+                    offset: view.stream.offset,
+                    transforms: view.transforms.clone(),
+                    cursor: view.cursor,
+                })
+                .collect(),
+            transforms: self.transforms.clone(),
+            panes: (0..self.pane_count())
+                .map(|index| {
+                    let mode = self.pane(index);
+                    PaneSession {
+                        kind: mode.kind(),
+                        position: mode.position(),
+                        paused: match *mode {
+                            RenderMode::Geometric(ref g_state) => g_state.paused,
+                            _ => false,
+                        },
+                    }
+                })
+                .collect(),
+            focused_pane: self.focused_pane,
+            cursor: self.cursor,
+            selection: self.selection,
+            bookmark: self.bookmark,
+            settings: self.settings,
+            mode_options: self.mode_options,
+        }
+    }
+
+    /// Goes back to looking at the file the way `session` says, opening the same streams
+    /// again. The file has to be loaded already, and be the one the session was saved with.
+    /// If the session can't be restored, nothing is changed. Offsets past the end of the
+    /// bytes go to the last byte, like `follow_link`.
+    pub fn restore_session(&mut self, mut session: Session) -> Result<(), SessionError> {
+        if session.version != SESSION_VERSION {
+            return Err(SessionError::Version(session.version));
+        }
+        let file = self.file();
+        if session.file_length != file.len() || session.file_hash != file_hash(file) {
+            return Err(SessionError::DifferentFile);
+        }
+        if session.panes.is_empty() || session.panes.len() > MAX_PANES {
+            return Err(SessionError::PaneCount(session.panes.len()));
+        }
+        if !ZOOM_LEVELS.contains(&session.settings.zoom) {
+            return Err(SessionError::Zoom(session.settings.zoom));
+        }
+        let streams = session
+            .views
+            .iter()
+            .map(|view| {
+                Format::ALL
+                    .iter()
+                    .cloned()
+                    .find(|format| format.name() == view.format)
+                    .map(|format| Stream {
+                        offset: view.offset,
+                        format,
+                    })
+                    .ok_or_else(|| SessionError::UnknownFormat(view.format.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Decompress every stream before changing anything, so one that doesn't open any
+        // more leaves the current view alone.
+        let mut outputs: Vec<Vec<u8>> = Vec::with_capacity(streams.len());
+        for (view, stream) in session.views.iter_mut().zip(&streams) {
+            let bytes = outputs.last().map_or(self.file(), |output| &output[..]);
+            let transformed = transform::apply_all(&view.transforms, bytes);
+            let output = inflate::decompress(&transformed, stream.offset, stream.format)
+                .map_err(SessionError::Stream)?;
+            view.cursor = min(view.cursor, transformed.len().saturating_sub(1));
+            outputs.push(output);
+        }
+
+        let bytes = outputs.last().map_or(self.file(), |output| &output[..]);
+        let len = transform::apply_all(&session.transforms, bytes).len();
+        let last = len.saturating_sub(1);
+        session.cursor = min(session.cursor, last);
+        session.bookmark = session.bookmark.map(|bookmark| min(bookmark, last));
+        session.selection = session
+            .selection
+            .map(|(start, end)| (min(start, len), min(end, len)))
+            .filter(|&(start, end)| start < end);
+        for pane in &mut session.panes {
+            pane.position = min(pane.position, len);
+        }
+
+        while self.close_view() {}
+        for ((view, stream), output) in session.views.into_iter().zip(streams).zip(outputs) {
+            self.transforms = view.transforms;
+            self.refresh_transformed();
+            self.cursor = view.cursor;
+            self.push_view(stream, output);
+        }
+        self.transforms = session.transforms;
+        self.refresh_transformed();
+
+        self.settings = Settings {
+            screen_width: self.settings.screen_width,
+            screen_height: self.settings.screen_height,
+            ..session.settings
+        };
+        self.mode_options = session.mode_options;
+        self.cursor = session.cursor;
+        self.selection = session.selection;
+        self.bookmark = session.bookmark;
+
+        self.set_pane_count(session.panes.len());
+        for (index, &pane) in session.panes.iter().enumerate() {
+            *self.pane_mut(index) = pane.kind.into();
+        }
+        self.focus_pane(session.focused_pane);
+        self.reset_render_mode();
+        for (index, &pane) in session.panes.iter().enumerate() {
+            self.pane_mut(index).restore(pane);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{update_and_render, ComparisonView, Palette};
+    use platform_types::{Input, Speaker};
+    use rendering::Framebuffer;
+
+    // "hello hello hello hello" compressed with zlib.
+    const ZLIB_HELLO: [u8; 16] = [
+        120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177,
+    ];
+
+    #[test]
+    fn a_session_comes_back_the_same_through_json() {
+        let mut state = GameState::new();
+        state.settings.palette = Palette::Heat;
+        state.mode_options.comparison.view = ComparisonView::Aligned;
+        state.push_transform(Transform::Xor(vec![1, 2]));
+        state.set_pane_count(2);
+        state.focus_pane(1);
+        state.cursor = 0x40;
+        state.selection = Some((0x40, 0x48));
+        state.bookmark = Some(3);
+
+        let session = state.session();
+        let json = session.to_json();
+        assert!(json.contains("\"Heat\""));
+        // The screen size is left out, so compare what was saved.
+        assert_eq!(Session::from_json(&json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn restoring_a_session_reopens_streams_and_puts_the_panes_back() {
+        let mut bytes = b"head".to_vec();
+        bytes.extend_from_slice(&ZLIB_HELLO);
+
+        let mut state = GameState::new();
+        state.load_bytes(bytes.clone());
+        state.cursor = 7;
+        state.open_stream(state.streams[0]).unwrap();
+        state.push_transform(Transform::SwapNibbles);
+        state.render_mode = RenderModeKind::Encoding.into();
+        state.set_pane_count(2);
+        state.cursor = 6;
+        state.settings.zoom = 4;
+        let session = state.session();
+
+        let mut restored = GameState::new();
+        restored.settings.screen_width = 100;
+        assert!(matches!(
+            restored.restore_session(session.clone()),
+            Err(SessionError::DifferentFile)
+        ));
+
+        restored.load_bytes(bytes);
+        restored.restore_session(session.clone()).unwrap();
+        assert_eq!(restored.breadcrumb(), "file>zlib@4");
+        assert_eq!(restored.transforms(), [Transform::SwapNibbles]);
+        assert_eq!(restored.pane(0).kind(), RenderModeKind::Encoding);
+        assert_eq!(restored.pane_count(), 2);
+        assert_eq!(restored.cursor, 6);
+        assert_eq!(restored.settings.zoom, 4);
+        assert_eq!(restored.settings.screen_width, 100);
+
+        assert!(restored.close_view());
+        assert_eq!(restored.cursor, 7);
+
+        let old = Session {
+            version: SESSION_VERSION + 1,
+            ..session
+        };
+        assert!(matches!(
+            restored.restore_session(old),
+            Err(SessionError::Version(_))
+        ));
+    }
+
+    #[test]
+    fn sessions_that_cant_be_restored_change_nothing() {
+        let mut bytes = b"head".to_vec();
+        bytes.extend_from_slice(&ZLIB_HELLO);
+
+        let mut state = GameState::new();
+        state.load_bytes(bytes);
+        state.open_stream(state.streams[0]).unwrap();
+        state.push_transform(Transform::SwapNibbles);
+        state.set_pane_count(2);
+        let session = state.session();
+
+        let too_many_panes = Session {
+            panes: vec![session.panes[0]; MAX_PANES + 1],
+            ..session.clone()
+        };
+        assert!(matches!(
+            state.restore_session(too_many_panes),
+            Err(SessionError::PaneCount(5))
+        ));
+        let no_panes = Session {
+            panes: Vec::new(),
+            ..session.clone()
+        };
+        assert!(matches!(
+            state.restore_session(no_panes),
+            Err(SessionError::PaneCount(0))
+        ));
+        let mut no_zoom = session.clone();
+        no_zoom.settings.zoom = 0;
+        assert!(matches!(
+            state.restore_session(no_zoom),
+            Err(SessionError::Zoom(0))
+        ));
+
+        // "head" isn't a zlib header.
+        let mut moved_stream = session.clone();
+        moved_stream.views[0].offset = 0;
+        moved_stream.transforms.clear();
+        moved_stream.panes.pop();
+        assert!(matches!(
+            state.restore_session(moved_stream),
+            Err(SessionError::Stream(_))
+        ));
+
+        assert_eq!(state.session(), session);
+    }
+
+    #[test]
+    fn offsets_past_the_end_are_clamped_to_the_bytes() {
+        let mut state = GameState::new();
+        state.load_bytes(vec![0x55; 100]);
+        state.render_mode = RenderModeKind::ThreeBitsPerPixel.into();
+        let mut session = state.session();
+        session.cursor = usize::MAX;
+        session.bookmark = Some(usize::MAX);
+        session.selection = Some((90, usize::MAX));
+        session.panes[0].position = usize::MAX;
+
+        state.restore_session(session.clone()).unwrap();
+        assert_eq!(state.cursor, 99);
+        assert_eq!(state.bookmark, Some(99));
+        assert_eq!(state.selection, Some((90, 100)));
+        assert_eq!(state.pane(0).position(), 100);
+        update_and_render(
+            &mut Framebuffer::new(),
+            &mut state,
+            Input::new(),
+            &mut Speaker::new(),
+        );
+
+        session.selection = Some((usize::MAX - 1, usize::MAX));
+        state.restore_session(session).unwrap();
+        assert_eq!(state.selection, None);
+    }
+}
@@ -0,0 +1,262 @@
+use rendering::{FONT_SIZE, GREYSCALE_PALETTE, HEAT_PALETTE, PALETTE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use serde_derive::{Deserialize, Serialize};
+use text::Encoding;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Default,
+    Greyscale,
+    Heat,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Default, Palette::Greyscale, Palette::Heat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "Default",
+            Palette::Greyscale => "Greyscale",
+            Palette::Heat => "Heat",
+        }
+    }
+
+    pub fn colours(self) -> &'static [u32; 8] {
+        match self {
+            Palette::Default => &PALETTE,
+            Palette::Greyscale => &GREYSCALE_PALETTE,
+            Palette::Heat => &HEAT_PALETTE,
+        }
+    }
+}
+
+/// Which end of each byte the bit-packed modes start reading from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+impl BitOrder {
+    pub const ALL: [BitOrder; 2] = [BitOrder::MsbFirst, BitOrder::LsbFirst];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BitOrder::MsbFirst => "MSB first",
+            BitOrder::LsbFirst => "LSB first",
+        }
+    }
+
+    pub fn short_name(self) -> &'static str {
+        match self {
+            BitOrder::MsbFirst => "MSB",
+            BitOrder::LsbFirst => "LSB",
+        }
+    }
+}
+
+/// How much of the section map of executables, or the chunks of container formats, to
+/// draw over the pixel based modes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SectionMap {
+    Off,
+    #[default]
+    Outlines,
+    /// Outlines, and a window listing every section or chunk.
+    OutlinesAndLegend,
+}
+
+impl SectionMap {
+    pub const ALL: [SectionMap; 3] = [
+        SectionMap::Off,
+        SectionMap::Outlines,
+        SectionMap::OutlinesAndLegend,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionMap::Off => "Off",
+            SectionMap::Outlines => "Outlines",
+            SectionMap::OutlinesAndLegend => "Legend",
+        }
+    }
+}
+
+/// Whether to show the fields decoded by a template, and whether the D-pad moves through
+/// them instead of controlling the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldPanel {
+    Off,
+    #[default]
+    Show,
+    Browse,
+}
+
+impl FieldPanel {
+    pub const ALL: [FieldPanel; 3] = [FieldPanel::Off, FieldPanel::Show, FieldPanel::Browse];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldPanel::Off => "Off",
+            FieldPanel::Show => "Show",
+            FieldPanel::Browse => "Browse",
+        }
+    }
+}
+
+/// How many rows at the bottom of the screen the HUD takes up when it is shown.
+pub const HUD_HEIGHT: usize = FONT_SIZE as usize + 2;
+
+/// How many screen pixels wide and high each pixel of the pixel based modes is.
+pub const ZOOM_LEVELS: [u8; 4] = [1, 2, 4, 8];
+
+/// Settings that apply to every mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settings {
+    pub palette: Palette,
+    pub bit_order: BitOrder,
+    pub zoom: u8,
+    pub show_hud: bool,
+    pub section_map: SectionMap,
+    pub field_panel: FieldPanel,
+    /// The size of the framebuffer in pixels, which frontends change with `State::resize`.
+    /// This belongs to the frontend, so it isn't saved with the rest.
+    #[serde(skip)]
+    pub screen_width: usize,
+    #[serde(skip)]
+    pub screen_height: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            palette: Palette::default(),
+            bit_order: BitOrder::default(),
+            zoom: ZOOM_LEVELS[0],
+            show_hud: true,
+            section_map: SectionMap::default(),
+            field_panel: FieldPanel::default(),
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+        }
+    }
+}
+
+impl Settings {
+    /// The height of the part of the screen the modes can draw on, which is everything
+    /// above the HUD.
+    pub fn view_height(&self) -> usize {
+        if self.show_hud {
+            self.screen_height.saturating_sub(HUD_HEIGHT)
+        } else {
+            self.screen_height
+        }
+    }
+}
+
+/// How many primitives the geometric modes can draw each frame while playing.
+pub const PRIMITIVE_SPEEDS: [usize; 7] = [1, 2, 4, 8, 16, 64, 256];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeometricOptions {
+    /// Pick each primitive's colour from its own bytes instead of cycling through the
+    /// palette.
+    pub colour_from_bytes: bool,
+    pub primitives_per_frame: usize,
+}
+
+impl Default for GeometricOptions {
+    fn default() -> Self {
+        GeometricOptions {
+            colour_from_bytes: false,
+            primitives_per_frame: PRIMITIVE_SPEEDS[0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreeBitsPerPixelOptions {
+    /// Start again from the beginning of the bytes if they run out before the screen does.
+    pub repeat_bytes: bool,
+}
+
+impl Default for ThreeBitsPerPixelOptions {
+    fn default() -> Self {
+        ThreeBitsPerPixelOptions { repeat_bytes: true }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodingOptions {
+    #[serde(with = "encoding_name")]
+    pub encoding: Encoding,
+}
+
+// Encodings are saved by name, since the `text` crate doesn't know about serde.
+mod encoding_name {
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::Serializer;
+    use text::Encoding;
+
+    pub fn serialize<S: Serializer>(encoding: &Encoding, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(encoding.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Encoding, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Encoding::ALL
+            .iter()
+            .cloned()
+            .find(|encoding| encoding.name() == name)
+            .ok_or_else(|| D::Error::custom(format!("unknown encoding {:?}", name)))
+    }
+}
+
+/// How the comparison mode shows the two files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComparisonView {
+    /// Both files, each in half of the screen.
+    #[default]
+    SideBySide,
+    /// The two files XORed together, so equal bytes come out black.
+    Xor,
+    /// Just whether each byte is the same in both files.
+    Differences,
+    /// Both files in columns, lined up by what they have in common, so bytes inserted into
+    /// one don't make everything after them look different.
+    Aligned,
+}
+
+impl ComparisonView {
+    pub const ALL: [ComparisonView; 4] = [
+        ComparisonView::SideBySide,
+        ComparisonView::Xor,
+        ComparisonView::Differences,
+        ComparisonView::Aligned,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ComparisonView::SideBySide => "Side by side",
+            ComparisonView::Xor => "XOR",
+            ComparisonView::Differences => "Differences",
+            ComparisonView::Aligned => "Aligned",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComparisonOptions {
+    pub view: ComparisonView,
+}
+
+/// Per-mode options. These are kept outside of the modes' own state so they survive the
+/// mode being reset, for example when new bytes are loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModeOptions {
+    pub geometric: GeometricOptions,
+    pub three_bits_per_pixel: ThreeBitsPerPixelOptions,
+    pub encoding: EncodingOptions,
+    pub comparison: ComparisonOptions,
+}
@@ -0,0 +1,283 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Something done to the bytes before they are shown. These are applied in order, each
+/// to the output of the last.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transform {
+    /// XOR with a key, repeated for as long as the bytes are.
+    Xor(Vec<u8>),
+    /// Reverses the order of the bits within each byte.
+    ReverseBits,
+    /// Swaps the high and low nibbles of each byte.
+    SwapNibbles,
+    /// Reverses each pair of bytes.
+    Swap16,
+    /// Reverses each group of four bytes.
+    Swap32,
+    /// Decodes base64 text, ignoring anything that isn't in either the standard or the URL
+    /// safe alphabet.
+    Base64,
+    /// Decodes hex digits two at a time, ignoring anything that isn't a hex digit.
+    Hex,
+    /// Drops this many bytes from the start.
+    Skip(usize),
+    /// Keeps at most this many bytes from the start.
+    Take(usize),
+}
+
+impl Transform {
+    /// One of each kind of transform, with parameters that leave the bytes as they are
+    /// where there are any.
+    pub const KINDS: [Transform; 9] = [
+        Transform::Xor(Vec::new()),
+        Transform::ReverseBits,
+        Transform::SwapNibbles,
+        Transform::Swap16,
+        Transform::Swap32,
+        Transform::Base64,
+        Transform::Hex,
+        Transform::Skip(0),
+        Transform::Take(usize::MAX),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Transform::Xor(_) => "XOR",
+            Transform::ReverseBits => "Reverse bits",
+            Transform::SwapNibbles => "Swap nibbles",
+            Transform::Swap16 => "Swap 16",
+            Transform::Swap32 => "Swap 32",
+            Transform::Base64 => "Base64",
+            Transform::Hex => "Hex",
+            Transform::Skip(_) => "Skip",
+            Transform::Take(_) => "Take",
+        }
+    }
+
+    /// The parameter, in hex, for the transforms that have one.
+    pub fn parameter(&self) -> Option<String> {
+        match *self {
+            Transform::Xor(ref key) => Some(key.iter().map(|b| format!("{:02X}", b)).collect()),
+            Transform::Skip(n) => Some(format!("{:X}", n)),
+            Transform::Take(usize::MAX) => Some("all".to_string()),
+            Transform::Take(n) => Some(format!("{:X}", n)),
+            _ => None,
+        }
+    }
+
+    /// Steps the parameter up or down by one. A key is treated as one big endian number,
+    /// so the last byte changes fastest, and an empty key becomes one byte long.
+    pub fn adjust(&mut self, direction: isize) {
+        match *self {
+            Transform::Xor(ref mut key) => {
+                if key.is_empty() {
+                    key.push(0);
+                }
+                for byte in key.iter_mut().rev() {
+                    let (stepped, carried) = if direction < 0 {
+                        byte.overflowing_sub(1)
+                    } else {
+                        byte.overflowing_add(1)
+                    };
+                    *byte = stepped;
+                    if !carried {
+                        break;
+                    }
+                }
+            }
+            Transform::Skip(ref mut n) | Transform::Take(ref mut n) => {
+                *n = if direction < 0 {
+                    n.saturating_sub(1)
+                } else {
+                    n.saturating_add(1)
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the parameter from text written the way `parameter` shows it: a key as pairs
+    /// of hex digits, or a count in hex. Says why if the text can't be used.
+    pub fn set_parameter(&mut self, text: &str) -> Result<(), &'static str> {
+        let text: String = text.split_whitespace().collect();
+        let digits = text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0X"))
+            .unwrap_or(&text);
+        match *self {
+            Transform::Xor(ref mut key) => {
+                if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err("not a hex key");
+                }
+                if digits.len() & 1 == 1 {
+                    return Err("a key needs two hex digits per byte");
+                }
+                // Every digit is ASCII, so the pairs can be sliced out.
+                *key = (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "not a hex key")?;
+            }
+            Transform::Take(ref mut n) if text.eq_ignore_ascii_case("all") => *n = usize::MAX,
+            Transform::Skip(ref mut n) | Transform::Take(ref mut n) => {
+                *n = usize::from_str_radix(digits, 16).map_err(|_| "not a hex number")?;
+            }
+            _ => return Err("nothing to set"),
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, bytes: &[u8]) -> Vec<u8> {
+        match *self {
+            Transform::Xor(ref key) if key.is_empty() => bytes.to_vec(),
+            Transform::Xor(ref key) => bytes
+                .iter()
+                .zip(key.iter().cycle())
+                .map(|(b, k)| b ^ k)
+                .collect(),
+            Transform::ReverseBits => bytes.iter().map(|b| b.reverse_bits()).collect(),
+            Transform::SwapNibbles => bytes.iter().map(|b| b.rotate_left(4)).collect(),
+            Transform::Swap16 => swap_groups(bytes, 2),
+            Transform::Swap32 => swap_groups(bytes, 4),
+            Transform::Base64 => base64(bytes),
+            Transform::Hex => hex(bytes),
+            Transform::Skip(n) => bytes.get(n..).unwrap_or(&[]).to_vec(),
+            Transform::Take(n) => bytes[..std::cmp::min(n, bytes.len())].to_vec(),
+        }
+    }
+}
+
+/// Applies each of `transforms` in turn.
+pub fn apply_all(transforms: &[Transform], bytes: &[u8]) -> Vec<u8> {
+    let mut output = bytes.to_vec();
+    for transform in transforms {
+        output = transform.apply(&output);
+    }
+    output
+}
+
+// Any bytes left over at the end that don't make a whole group are left as they are.
+fn swap_groups(bytes: &[u8], size: usize) -> Vec<u8> {
+    let mut output = bytes.to_vec();
+    for group in output.chunks_exact_mut(size) {
+        group.reverse();
+    }
+    output
+}
+
+fn base64(bytes: &[u8]) -> Vec<u8> {
+    let sextets = bytes.iter().filter_map(|&b| match b {
+        b'A'..=b'Z' => Some(b - b'A'),
+        b'a'..=b'z' => Some(b - b'a' + 26),
+        b'0'..=b'9' => Some(b - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    });
+
+    let mut output = Vec::with_capacity(bytes.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for sextet in sextets {
+        buffer = buffer << 6 | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    output
+}
+
+fn hex(bytes: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = bytes
+        .iter()
+        .filter_map(|&b| (b as char).to_digit(16))
+        .map(|d| d as u8)
+        .collect();
+
+    digits
+        .chunks_exact(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_do_what_they_say() {
+        let bytes = [0x12, 0x34, 0x56, 0x78, 0x9A];
+
+        assert_eq!(
+            Transform::Xor(vec![0xFF, 0]).apply(&bytes),
+            [0xED, 0x34, 0xA9, 0x78, 0x65]
+        );
+        assert_eq!(Transform::ReverseBits.apply(&[0b0000_0001]), [0b1000_0000]);
+        assert_eq!(Transform::SwapNibbles.apply(&[0x12]), [0x21]);
+        assert_eq!(
+            Transform::Swap16.apply(&bytes),
+            [0x34, 0x12, 0x78, 0x56, 0x9A]
+        );
+        assert_eq!(
+            Transform::Swap32.apply(&bytes),
+            [0x78, 0x56, 0x34, 0x12, 0x9A]
+        );
+        assert_eq!(Transform::Skip(3).apply(&bytes), [0x78, 0x9A]);
+        assert_eq!(Transform::Skip(9).apply(&bytes), [0u8; 0]);
+        assert_eq!(Transform::Take(2).apply(&bytes), [0x12, 0x34]);
+    }
+
+    #[test]
+    fn text_decoders_skip_what_they_do_not_understand() {
+        assert_eq!(Transform::Base64.apply(b"aGVs\nbG8=\n"), b"hello");
+        assert_eq!(Transform::Base64.apply(b"-_8"), [0xFB, 0xFF]);
+        assert_eq!(Transform::Hex.apply(b"4d 5A\n90 0"), [0x4D, 0x5A, 0x90]);
+    }
+
+    #[test]
+    fn apply_all_chains_the_transforms_in_order() {
+        let transforms = [
+            Transform::Hex,
+            Transform::Xor(vec![0x20]),
+            Transform::Skip(1),
+        ];
+
+        assert_eq!(apply_all(&transforms, b"41 42 43"), b"bc");
+    }
+
+    #[test]
+    fn adjusting_a_key_carries_into_the_earlier_bytes() {
+        let mut xor = Transform::Xor(vec![0x00, 0xFF]);
+        xor.adjust(1);
+        assert_eq!(xor, Transform::Xor(vec![0x01, 0x00]));
+        xor.adjust(-1);
+        assert_eq!(xor, Transform::Xor(vec![0x00, 0xFF]));
+
+        let mut xor = Transform::Xor(Vec::new());
+        xor.adjust(1);
+        assert_eq!(xor, Transform::Xor(vec![0x01]));
+    }
+
+    #[test]
+    fn parameters_can_be_set_from_what_they_show_as() {
+        let mut xor = Transform::Xor(Vec::new());
+        xor.set_parameter("DE ad be ef").unwrap();
+        assert_eq!(xor, Transform::Xor(vec![0xDE, 0xAD, 0xBE, 0xEF]));
+        assert_eq!(xor.parameter().unwrap(), "DEADBEEF");
+        assert!(xor.set_parameter("ABC").is_err());
+        assert!(xor.set_parameter("é1").is_err());
+
+        let mut skip = Transform::Skip(0);
+        skip.set_parameter("0x1000").unwrap();
+        assert_eq!(skip, Transform::Skip(0x1000));
+        assert!(skip.set_parameter("all").is_err());
+
+        let mut take = Transform::Take(4);
+        take.set_parameter("all").unwrap();
+        assert_eq!(take, Transform::Take(usize::MAX));
+
+        assert!(Transform::Hex.set_parameter("1").is_err());
+    }
+}
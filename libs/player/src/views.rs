@@ -0,0 +1,134 @@
+use inflate::{Format, Stream};
+use std::mem;
+
+use crate::{GameState, Transform};
+
+/// Bytes that were being looked at before a stream inside them was opened.
+pub struct View {
+    /// What was opened from these bytes, like `zlib@1A2`.
+    pub opened: String,
+    /// Where the stream that was opened is in the transformed bytes.
+    pub stream: Stream,
+    pub bytes: Vec<u8>,
+    pub transforms: Vec<Transform>,
+    pub cursor: usize,
+}
+
+impl GameState {
+    /// Replaces the bytes with the decompressed contents of `stream`, which is found in the
+    /// transformed bytes, keeping the current bytes and transforms to go back to with
+    /// `close_view`. The decompressed bytes start with no transforms.
+    pub fn open_stream(&mut self, stream: Stream) -> Result<(), inflate::Error> {
+        let output = inflate::decompress(&self.transformed, stream.offset, stream.format)?;
+        self.push_view(stream, output);
+
+        Ok(())
+    }
+
+    // Shows `output`, which `stream` has already been decompressed to, the way
+    // `open_stream` does.
+    pub(crate) fn push_view(&mut self, stream: Stream, output: Vec<u8>) {
+        self.views.push(View {
+            opened: format!("{}@{:X}", stream.format.name(), stream.offset),
+            stream,
+            bytes: mem::take(&mut self.bytes),
+            transforms: mem::take(&mut self.transforms),
+            cursor: self.cursor,
+        });
+        self.load_bytes(output);
+    }
+
+    /// Treats the bytes from the cursor onwards as raw DEFLATE data and opens them.
+    pub fn inflate_at_cursor(&mut self) -> Result<(), inflate::Error> {
+        self.open_stream(Stream {
+            offset: self.cursor,
+            format: Format::Deflate,
+        })
+    }
+
+    /// Goes back to the bytes the current ones were decompressed from, with the cursor
+    /// where it was. Returns false if these are the original bytes.
+    pub fn close_view(&mut self) -> bool {
+        let view = match self.views.pop() {
+            Some(view) => view,
+            None => return false,
+        };
+
+        self.transforms = view.transforms;
+        self.load_bytes(view.bytes);
+        self.cursor = view.cursor;
+        self.show_cursor();
+
+        true
+    }
+
+    /// The path from the original bytes to the current ones, like `file>zlib@1A2`.
+    pub fn breadcrumb(&self) -> String {
+        let mut breadcrumb = "file".to_string();
+        for view in self.views.iter() {
+            breadcrumb.push('>');
+            breadcrumb.push_str(&view.opened);
+        }
+        breadcrumb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "hello hello hello hello" compressed with zlib.
+    const ZLIB_HELLO: [u8; 16] = [
+        120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177,
+    ];
+
+    #[test]
+    fn streams_can_be_opened_and_closed_again() {
+        let mut state = GameState::new();
+        let mut bytes = b"head".to_vec();
+        bytes.extend_from_slice(&ZLIB_HELLO);
+        state.load_bytes(bytes.clone());
+        state.cursor = 7;
+
+        let stream = state.streams[0];
+        assert_eq!(stream.offset, 4);
+        state.open_stream(stream).unwrap();
+
+        assert_eq!(state.bytes, b"hello hello hello hello");
+        assert_eq!(state.cursor, 0);
+        assert_eq!(state.breadcrumb(), "file>zlib@4");
+
+        assert!(state.close_view());
+        assert_eq!(state.bytes, bytes);
+        assert_eq!(state.cursor, 7);
+        assert_eq!(state.breadcrumb(), "file");
+        assert!(!state.close_view());
+    }
+
+    #[test]
+    fn streams_are_found_in_the_transformed_bytes() {
+        let mut state = GameState::new();
+        let xored: Vec<u8> = ZLIB_HELLO.iter().map(|b| b ^ 0x5A).collect();
+        state.load_bytes(xored);
+        assert!(state.streams.is_empty());
+
+        state.push_transform(Transform::Xor(vec![0x5A]));
+        state.open_stream(state.streams[0]).unwrap();
+        assert_eq!(state.transformed, b"hello hello hello hello");
+        assert!(state.transforms().is_empty());
+
+        state.close_view();
+        assert_eq!(state.transforms(), [Transform::Xor(vec![0x5A])]);
+        assert_eq!(state.transformed, ZLIB_HELLO);
+    }
+
+    #[test]
+    fn a_failed_open_leaves_the_bytes_alone() {
+        let mut state = GameState::new();
+        state.load_bytes(vec![0xFF; 8]);
+
+        assert!(state.inflate_at_cursor().is_err());
+        assert_eq!(state.bytes, vec![0xFF; 8]);
+        assert!(state.views.is_empty());
+    }
+}
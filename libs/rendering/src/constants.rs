@@ -27,6 +27,36 @@ pub const PALETTE: [u32; 8] = [
     BLACK,
 ];
 
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const GREYSCALE_PALETTE: [u32; 8] = [
+    0xFF000000,
+    0xFF242424,
+    0xFF494949,
+    0xFF6D6D6D,
+    0xFF929292,
+    0xFFB6B6B6,
+    0xFFDBDBDB,
+    0xFFFFFFFF,
+];
+
+// black through red and yellow to white
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const HEAT_PALETTE: [u32; 8] = [
+    0xFF000000,
+    0xFF000060,
+    0xFF0000C0,
+    0xFF0040FF,
+    0xFF0090FF,
+    0xFF00E0FF,
+    0xFF80FFFF,
+    0xFFFFFFFF,
+];
+
+// These are deliberately not in `PALETTE`, so they stand out against anything drawn with it.
+pub const HIGHLIGHT: u32 = 0xFFFF00FF;
+pub const CURRENT_HIGHLIGHT: u32 = 0xFFFFFF00;
+pub const CURSOR: u32 = 0xFF00FF00;
+
 pub const BLUE_INDEX: u8 = 0;
 pub const GREEN_INDEX: u8 = 1;
 pub const RED_INDEX: u8 = 2;
@@ -1,7 +1,7 @@
 use text::bytes_lines;
 
 use crate::constants::*;
-use features::log;
+use features::{invariant_assert, log};
 use std::cmp::{max, min};
 
 #[cfg(not(test))]
@@ -14,56 +14,34 @@ macro_rules! test_dbg {
     ($($arg:tt)*) => {dbg!($($arg)*)};
 }
 
-pub trait Dimensions {
-    fn get() -> (usize, usize);
-}
-
-pub struct ScreenDim;
-
-#[macro_export]
-macro_rules! impl_dimensions {
-    (($w: expr, $h: expr): $type: ty) => {
-        impl Dimensions for $type {
-            fn get() -> (usize, usize) {
-                ($w, $h)
-            }
-        }
-    };
-}
-
-impl_dimensions!((SCREEN_WIDTH, SCREEN_HEIGHT): ScreenDim);
-
-//The type parameter lets us have Framebuffers of diferent dimensions
-pub type Framebuffer = FramebufferInternal<ScreenDim>;
-
-use std::marker::PhantomData;
-
-pub struct FramebufferInternal<D: Dimensions> {
+/// Pixels in the `0xAABBGGRR` format, `width` to a row. The size is picked at runtime, and
+/// everything drawn outside of the current viewport is clipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
     pub buffer: Vec<u32>,
-    _marker: std::marker::PhantomData<D>,
+    pub width: usize,
+    pub height: usize,
+    viewport: Viewport,
+    // The viewports to go back to, innermost last.
+    saved_viewports: Vec<Viewport>,
 }
 
-impl<D: Dimensions> Default for FramebufferInternal<D> {
-    fn default() -> Self {
-        let mut buffer = Vec::new();
-        let (w, h) = D::get();
-        buffer.resize(w * h, PALETTE[0]);
-
-        FramebufferInternal {
-            buffer,
-            _marker: PhantomData,
-        }
-    }
+/// The part of a framebuffer that is drawn to, in the framebuffer's coordinates. Drawing
+/// coordinates are relative to its top left corner, and anything outside it is clipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
 }
 
-impl<D: Dimensions> PartialEq for FramebufferInternal<D> {
-    fn eq(&self, other: &FramebufferInternal<D>) -> bool {
-        &self.buffer[..] == &other.buffer[..]
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Framebuffer::with_size(SCREEN_WIDTH, SCREEN_HEIGHT)
     }
 }
 
-impl<D: Dimensions> Eq for FramebufferInternal<D> {}
-
 macro_rules! red {
     ($colour:expr) => {
         $colour & 0xFF
@@ -101,13 +79,137 @@ macro_rules! set_alpha {
 }
 
 #[allow(dead_code)]
-impl<D: Dimensions> FramebufferInternal<D> {
-    pub fn new() -> FramebufferInternal<D> {
+impl Framebuffer {
+    /// A framebuffer the default size of `SCREEN_WIDTH` by `SCREEN_HEIGHT`.
+    pub fn new() -> Framebuffer {
         Self::default()
     }
 
-    pub fn xy_to_i<U1: Into<usize>, U2: Into<usize>>(x: U1, y: U2) -> usize {
-        y.into().saturating_mul(D::get().0).saturating_add(x.into())
+    pub fn with_size(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            buffer: vec![PALETTE[0]; width * height],
+            width,
+            height,
+            viewport: Viewport {
+                x: 0,
+                y: 0,
+                w: width,
+                h: height,
+            },
+            saved_viewports: Vec::new(),
+        }
+    }
+
+    /// Changes the size, which clears everything drawn so far and goes back to drawing on
+    /// the whole framebuffer.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self = Framebuffer::with_size(width, height);
+    }
+
+    /// Where the framebuffer's pixel at `x`, `y` is in `buffer`. This ignores the viewport.
+    pub fn xy_to_i(&self, x: usize, y: usize) -> usize {
+        y.saturating_mul(self.width).saturating_add(x)
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// The width and height that can be drawn on.
+    pub fn viewport_size(&self) -> (usize, usize) {
+        (self.viewport.w, self.viewport.h)
+    }
+
+    /// Limits drawing to the rectangle at `x`, `y`, relative to the current viewport, and
+    /// moves the origin to its top left corner, until the matching `pop_viewport`. The
+    /// rectangle is clipped to the current viewport, so viewports can only shrink.
+    pub fn push_viewport(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        let current = self.viewport;
+        let x = min(x, current.w);
+        let y = min(y, current.h);
+        let viewport = Viewport {
+            x: current.x + x,
+            y: current.y + y,
+            w: min(w, current.w - x),
+            h: min(h, current.h - y),
+        };
+        invariant_assert!(
+            viewport.x + viewport.w <= self.width && viewport.y + viewport.h <= self.height
+        );
+
+        self.saved_viewports.push(current);
+        self.viewport = viewport;
+    }
+
+    /// Goes back to the viewport from before the last `push_viewport`.
+    pub fn pop_viewport(&mut self) {
+        invariant_assert!(!self.saved_viewports.is_empty());
+        self.viewport = self.saved_viewports.pop().unwrap_or(Viewport {
+            w: self.width,
+            h: self.height,
+            ..Viewport::default()
+        });
+    }
+
+    /// Runs `draw` with the viewport pushed, and pops it afterwards.
+    pub fn with_viewport<F, R>(&mut self, x: usize, y: usize, w: usize, h: usize, draw: F) -> R
+    where
+        F: FnOnce(&mut Framebuffer) -> R,
+    {
+        self.push_viewport(x, y, w, h);
+        let result = draw(self);
+        self.pop_viewport();
+        result
+    }
+
+    /// A copy of the pixels in the viewport, a row at a time.
+    pub fn viewport_pixels(&self) -> Vec<u32> {
+        let Viewport { x, y, w, h } = self.viewport;
+        let mut pixels = Vec::with_capacity(w * h);
+        for row in y..y + h {
+            let start = self.xy_to_i(x, row);
+            pixels.extend_from_slice(&self.buffer[start..start + w]);
+        }
+        pixels
+    }
+
+    /// Puts back pixels from `viewport_pixels`. Does nothing if the viewport has changed
+    /// size since then.
+    pub fn set_viewport_pixels(&mut self, pixels: &[u32]) {
+        let Viewport { x, y, w, h } = self.viewport;
+        if pixels.len() != w * h || w == 0 {
+            return;
+        }
+        for (row, source) in (y..y + h).zip(pixels.chunks(w)) {
+            let start = self.xy_to_i(x, row);
+            self.buffer[start..start + w].copy_from_slice(source);
+        }
+    }
+
+    // Where the pixel at `x`, `y` in the viewport is in `buffer`, if it is in the viewport.
+    #[inline]
+    fn viewport_i(&self, x: usize, y: usize) -> Option<usize> {
+        let Viewport {
+            x: left,
+            y: top,
+            w,
+            h,
+        } = self.viewport;
+        if x < w && y < h {
+            let i = self.xy_to_i(left + x, top + y);
+            invariant_assert!(i < self.buffer.len());
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the pixel at `x`, `y`, unless it is outside the viewport.
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, colour: u32) {
+        if let Some(i) = self.viewport_i(x, y) {
+            self.buffer[i] = colour;
+        }
     }
 
     pub fn draw_filled_rect(
@@ -118,53 +220,45 @@ impl<D: Dimensions> FramebufferInternal<D> {
         height: usize,
         colour: u32,
     ) {
-        let one_past_right_edge = x + width;
-        let one_past_bottom_edge = y + height;
+        let Viewport {
+            x: left,
+            y: top,
+            w,
+            h,
+        } = self.viewport;
+        let one_past_right_edge = min(x.saturating_add(width), w);
+        let one_past_bottom_edge = min(y.saturating_add(height), h);
+        if x >= one_past_right_edge {
+            return;
+        }
 
         for current_y in y..one_past_bottom_edge {
-            for current_x in x..one_past_right_edge {
-                let i = Self::xy_to_i(current_x, current_y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = colour;
-                }
+            let row_start = self.xy_to_i(left + x, top + current_y);
+            let row_end = row_start + (one_past_right_edge - x);
+            invariant_assert!(row_end <= self.xy_to_i(left + w, top + current_y));
+            for pixel in &mut self.buffer[row_start..row_end] {
+                *pixel = colour;
             }
         }
     }
 
     pub fn draw_rect(&mut self, x: usize, y: usize, width: usize, height: usize, colour: u32) {
-        let one_past_right_edge = x + width;
-        let one_past_bottom_edge = y + height;
+        if width == 0 || height == 0 {
+            return;
+        }
+        let one_past_right_edge = x.saturating_add(width);
+        let one_past_bottom_edge = y.saturating_add(height);
 
         for current_y in y..one_past_bottom_edge {
-            {
-                let i = Self::xy_to_i(x, current_y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = colour;
-                }
-            }
+            self.set(x, current_y, colour);
 
-            {
-                let i = Self::xy_to_i(one_past_right_edge - 1, current_y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = colour;
-                }
-            }
+            self.set(one_past_right_edge - 1, current_y, colour);
         }
 
         for current_x in x..one_past_right_edge {
-            {
-                let i = Self::xy_to_i(current_x, y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = colour;
-                }
-            }
+            self.set(current_x, y, colour);
 
-            {
-                let i = Self::xy_to_i(current_x, one_past_bottom_edge - 1);
-                if i < self.buffer.len() {
-                    self.buffer[i] = colour;
-                }
-            }
+            self.set(current_x, one_past_bottom_edge - 1, colour);
         }
     }
 
@@ -178,52 +272,41 @@ impl<D: Dimensions> FramebufferInternal<D> {
     ) where
         F: Fn(usize, usize, usize, usize) -> u32,
     {
-        let one_past_right_edge = x + width;
-        let one_past_bottom_edge = y + height;
+        if width == 0 || height == 0 {
+            return;
+        }
+        let one_past_right_edge = x.saturating_add(width);
+        let one_past_bottom_edge = y.saturating_add(height);
 
         for current_y in y..one_past_bottom_edge {
-            {
-                let i = Self::xy_to_i(x, current_y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = shader(x, current_y, width, height);
-                }
-            }
+            self.set(x, current_y, shader(x, current_y, width, height));
 
-            {
-                let i = Self::xy_to_i(one_past_right_edge - 1, current_y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = shader(x, current_y, width, height);
-                }
-            }
+            self.set(
+                one_past_right_edge - 1,
+                current_y,
+                shader(x, current_y, width, height),
+            );
         }
 
         for current_x in x..one_past_right_edge {
-            {
-                let i = Self::xy_to_i(current_x, y);
-                if i < self.buffer.len() {
-                    self.buffer[i] = shader(current_x, y, width, height);
-                }
-            }
+            self.set(current_x, y, shader(current_x, y, width, height));
 
-            {
-                let i = Self::xy_to_i(current_x, one_past_bottom_edge - 1);
-                if i < self.buffer.len() {
-                    self.buffer[i] = shader(current_x, y, width, height);
-                }
-            }
+            self.set(
+                current_x,
+                one_past_bottom_edge - 1,
+                shader(current_x, y, width, height),
+            );
         }
     }
 
     pub fn clear(&mut self) {
-        for i in 0..self.buffer.len() {
-            self.buffer[i] = 0;
-        }
+        self.clear_to(0);
     }
 
+    /// Fills the viewport with `colour`.
     pub fn clear_to(&mut self, colour: u32) {
-        for i in 0..self.buffer.len() {
-            self.buffer[i] = colour;
-        }
+        let (w, h) = self.viewport_size();
+        self.draw_filled_rect(0, 0, w, h, colour);
     }
 
     //see http://members.chello.at/~easyfilter/bresenham.html
@@ -236,18 +319,26 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let mut y = 0isize;
         let mut err = 2 - 2 * r; /* II. Quadrant */
         while {
-            self.buffer
-                [Self::xy_to_i((x_mid as isize - x) as usize, (y_mid as isize + y) as usize)] =
-                colour; /*   I. Quadrant */
-            self.buffer
-                [Self::xy_to_i((x_mid as isize - y) as usize, (y_mid as isize - x) as usize)] =
-                colour; /*  II. Quadrant */
-            self.buffer
-                [Self::xy_to_i((x_mid as isize + x) as usize, (y_mid as isize - y) as usize)] =
-                colour; /* III. Quadrant */
-            self.buffer
-                [Self::xy_to_i((x_mid as isize + y) as usize, (y_mid as isize + x) as usize)] =
-                colour; /*  IV. Quadrant */
+            self.set(
+                (x_mid as isize - x) as usize,
+                (y_mid as isize + y) as usize,
+                colour,
+            ); /*   I. Quadrant */
+            self.set(
+                (x_mid as isize - y) as usize,
+                (y_mid as isize - x) as usize,
+                colour,
+            ); /*  II. Quadrant */
+            self.set(
+                (x_mid as isize + x) as usize,
+                (y_mid as isize - y) as usize,
+                colour,
+            ); /* III. Quadrant */
+            self.set(
+                (x_mid as isize + y) as usize,
+                (y_mid as isize + x) as usize,
+                colour,
+            ); /*  IV. Quadrant */
             r = err;
             if r <= y {
                 y += 1;
@@ -278,29 +369,38 @@ impl<D: Dimensions> FramebufferInternal<D> {
 
     #[inline]
     pub fn blend_xy(&mut self, x: usize, y: usize, colour: u32) {
-        self.blend(Self::xy_to_i(x, y), colour);
+        if let Some(i) = self.viewport_i(x, y) {
+            self.blend(i, colour);
+        }
     }
 
     // from https://fgiesen.wordpress.com/2013/02/08/triangle-rasterization-in-practice/
     //"the result of [orient_2d] fits inside a (2p+2)-bit signed integer"
-    // Solving for p = 8 gives 2 * 8 + 2 = 18 so we need at least a 32 bit number.
-    fn orient_2d(ax: u8, ay: u8, bx: u8, by: u8, cx: u8, cy: u8) -> i32 {
-        (bx as i32 - ax as i32) * (cy as i32 - ay as i32)
-            - (by as i32 - ay as i32) * (cx as i32 - ax as i32)
+    // Coordinates are clamped to 30 bits, and 2 * 30 + 2 = 62 fits in an i64.
+    fn orient_2d(ax: i64, ay: i64, bx: i64, by: i64, cx: i64, cy: i64) -> i64 {
+        (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
     }
 
     // see parts 6, 7, and 8 of
     // https://fgiesen.wordpress.com/2013/02/17/optimizing-sw-occlusion-culling-index/
     pub fn draw_filled_triangle(
         &mut self,
-        x0: u8,
-        y0: u8,
-        x1: u8,
-        y1: u8,
-        x2: u8,
-        y2: u8,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+        x2: usize,
+        y2: usize,
         colour: u32,
     ) {
+        let (w, h) = self.viewport_size();
+        if w == 0 || h == 0 {
+            return;
+        }
+        const MAX_COORD: usize = (1 << 30) - 1;
+        let c = |n: usize| min(n, MAX_COORD) as i64;
+        let (x0, y0, x1, y1, x2, y2) = (c(x0), c(y0), c(x1), c(y1), c(x2), c(y2));
+
         //The rest of this function assumes the points are in in counter-clockwise order.
         let is_clockwise = Self::orient_2d(x0, y0, x1, y1, x2, y2) < 0;
         let (x1, y1, x2, y2) = if is_clockwise {
@@ -309,19 +409,19 @@ impl<D: Dimensions> FramebufferInternal<D> {
             (x1, y1, x2, y2)
         };
 
-        // Compute triangle bounding box
+        // Compute triangle bounding box, clipped to the bottom right of the viewport
         let min_x = min(x0, min(x1, x2));
         let min_y = min(y0, min(y1, y2));
-        let max_x = max(x0, max(x1, x2));
-        let max_y = max(y0, max(y1, y2));
+        let max_x = min(max(x0, max(x1, x2)), w as i64 - 1);
+        let max_y = min(max(y0, max(y1, y2)), h as i64 - 1);
 
         // Triangle setup
-        let a_01 = y0 as i32 - y1 as i32;
-        let b_01 = x1 as i32 - x0 as i32;
-        let a_12 = y1 as i32 - y2 as i32;
-        let b_12 = x2 as i32 - x1 as i32;
-        let a_20 = y2 as i32 - y0 as i32;
-        let b_20 = x0 as i32 - x2 as i32;
+        let a_01 = y0 - y1;
+        let b_01 = x1 - x0;
+        let a_12 = y1 - y2;
+        let b_12 = x2 - x1;
+        let a_20 = y2 - y0;
+        let b_20 = x0 - x2;
 
         // Barycentric coordinates at minX/minY corner
         let (px, py) = (min_x, min_y);
@@ -339,7 +439,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
             for px in min_x..=max_x {
                 // If p is on or inside all edges, render pixel.
                 if w0 | w1 | w2 >= 0 {
-                    self.buffer[Self::xy_to_i(px as usize, py as usize)] = colour;
+                    self.set(px as usize, py as usize, colour);
                 }
 
                 // One step to the right
@@ -357,25 +457,61 @@ impl<D: Dimensions> FramebufferInternal<D> {
 
     pub fn draw_filled_quad(
         &mut self,
-        x0: u8,
-        y0: u8,
-        x1: u8,
-        y1: u8,
-        x2: u8,
-        y2: u8,
-        x3: u8,
-        y3: u8,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+        x2: usize,
+        y2: usize,
+        x3: usize,
+        y3: usize,
         colour: u32,
     ) {
         self.draw_filled_triangle(x0, y0, x1, y1, x2, y2, colour);
         self.draw_filled_triangle(x1, y1, x2, y2, x3, y3, colour);
     }
 
+    //see http://members.chello.at/easyfilter/bresenham.html
+    pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, colour: u32) {
+        let (mut x, mut y) = (x0 as isize, y0 as isize);
+        let (x1, y1) = (x1 as isize, y1 as isize);
+
+        let dx = (x1 - x).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let dy = -(y1 - y).abs();
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy; /* error value e_xy */
+
+        loop {
+            self.set(x as usize, y as usize, colour);
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                /* e_xy+e_x > 0 */
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                /* e_xy+e_y < 0 */
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
     //see http://members.chello.at/easyfilter/bresenham.c
     pub fn draw_circle(&mut self, x_mid: usize, y_mid: usize, radius: usize, colour: u32) {
         if x_mid < radius || y_mid < radius {
             return;
         }
+        // The blending below divides by `2 * radius - 1`, which only works from 1 up.
+        if radius == 0 {
+            self.set(x_mid, y_mid, colour);
+            return;
+        }
         let xm = x_mid as isize;
         let ym = y_mid as isize;
 
@@ -452,6 +588,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         if x_mid < radius || y_mid < radius {
             return;
         }
+        // See `draw_circle`.
+        if radius == 0 {
+            self.set(x_mid, y_mid, colour);
+            return;
+        }
         let xm = x_mid as isize;
         let ym = y_mid as isize;
 
@@ -514,7 +655,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 current_x = (xm - x2 - 1) as usize;
                 current_y = (ym + y) as usize;
                 while current_x > x_mid || current_y > y_mid {
-                    self.buffer[Self::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x -= 1;
                     current_y -= 1;
@@ -523,7 +664,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 current_x = (xm + y) as usize;
                 current_y = (ym + x2 + 1) as usize;
                 while current_x > x_mid || current_y < y_mid {
-                    self.buffer[Self::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x -= 1;
                     current_y += 1;
@@ -532,7 +673,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 current_x = (xm - y) as usize;
                 current_y = (ym - x2 - 1) as usize;
                 while current_x < x_mid || current_y > y_mid {
-                    self.buffer[Self::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x += 1;
                     current_y -= 1;
@@ -541,7 +682,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 current_x = (xm + x2 + 1) as usize;
                 current_y = (ym - y) as usize;
                 while current_x < x_mid || current_y < y_mid {
-                    self.buffer[Self::xy_to_i(current_x, current_y)] = colour;
+                    self.set(current_x, current_y, colour);
 
                     current_x += 1;
                     current_y += 1;
@@ -554,7 +695,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
             x < 0
         } {}
 
-        self.buffer[Self::xy_to_i(x_mid, y_mid)] = colour;
+        self.set(x_mid, y_mid, colour);
     }
 
     pub fn sspr(
@@ -563,11 +704,10 @@ impl<D: Dimensions> FramebufferInternal<D> {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
     ) {
         const S_WIDTH: usize = GFX_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
 
         let s_w = sprite_w as usize;
         let s_h = sprite_h as usize;
@@ -575,11 +715,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let s_x = sprite_x as usize;
         let s_y = sprite_y as usize;
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
+        let d_x = display_x;
+        let d_y = display_y;
 
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
+        let d_x_max = d_x.saturating_add(s_w);
+        let d_y_max = d_y.saturating_add(s_h);
 
         let mut current_s_y = s_y;
         for y in d_y..d_y_max {
@@ -588,10 +728,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 let colour = GFX[current_s_x + current_s_y * S_WIDTH] as usize;
                 //make purple transparent
                 if colour != 4 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour];
-                    }
+                    self.set(x, y, PALETTE[colour]);
                 }
                 current_s_x += 1;
             }
@@ -605,11 +742,10 @@ impl<D: Dimensions> FramebufferInternal<D> {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
     ) {
         const S_WIDTH: usize = GFX_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
 
         let s_w = sprite_w as usize;
         let s_h = sprite_h as usize;
@@ -617,11 +753,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let s_x = sprite_x as usize;
         let s_y = sprite_y as usize;
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
+        let d_x = display_x;
+        let d_y = display_y;
 
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
+        let d_x_max = d_x.saturating_add(s_w);
+        let d_y_max = d_y.saturating_add(s_h);
 
         let mut current_s_y = s_y + s_h - 1;
         for y in d_y..d_y_max {
@@ -630,10 +766,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 let colour = GFX[current_s_x + current_s_y * S_WIDTH] as usize;
                 //make purple transparent
                 if colour != 2 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour];
-                    }
+                    self.set(x, y, PALETTE[colour]);
                 }
                 current_s_x -= 1;
             }
@@ -641,46 +774,46 @@ impl<D: Dimensions> FramebufferInternal<D> {
         }
     }
 
-    pub fn spr(&mut self, sprite_number: u8, x: u8, y: u8) {
+    pub fn spr(&mut self, sprite_number: u8, x: usize, y: usize) {
         let (sprite_x, sprite_y) = get_sprite_xy(sprite_number);
         self.sspr(sprite_x, sprite_y, SPRITE_SIZE, SPRITE_SIZE, x, y);
     }
 
-    pub fn spr_flip_both(&mut self, sprite_number: u8, x: u8, y: u8) {
+    pub fn spr_flip_both(&mut self, sprite_number: u8, x: usize, y: usize) {
         let (sprite_x, sprite_y) = get_sprite_xy(sprite_number);
         self.sspr_flip_both(sprite_x, sprite_y, SPRITE_SIZE, SPRITE_SIZE, x, y);
     }
 
-    pub fn print(&mut self, bytes: &[u8], x: u8, mut y: u8, colour: u8) {
+    pub fn print(&mut self, bytes: &[u8], x: usize, mut y: usize, colour: u8) {
         for line in bytes_lines(bytes) {
             self.print_line(line, x, y, colour);
-            y = y.saturating_add(FONT_SIZE);
+            y = y.saturating_add(FONT_SIZE as usize);
         }
     }
 
-    pub fn print_line(&mut self, bytes: &[u8], mut x: u8, y: u8, colour: u8) {
+    pub fn print_line(&mut self, bytes: &[u8], mut x: usize, y: usize, colour: u8) {
         let mut bytes_iter = bytes.iter();
 
         while let Some(&c) = bytes_iter.next() {
             let (sprite_x, sprite_y) = get_char_xy(c);
             self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
-            x = x.saturating_add(FONT_ADVANCE);
+            x = x.saturating_add(FONT_ADVANCE as usize);
         }
     }
 
-    pub fn print_line_raw(&mut self, bytes: &[u8], mut x: u8, y: u8, colour: u8) {
+    pub fn print_line_raw(&mut self, bytes: &[u8], mut x: usize, y: usize, colour: u8) {
         for &c in bytes {
             let (sprite_x, sprite_y) = get_char_xy(c);
             self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
-            x = x.saturating_add(FONT_ADVANCE);
+            x = x.saturating_add(FONT_ADVANCE as usize);
         }
     }
 
-    pub fn print_single_line_number(&mut self, number: usize, x: u8, y: u8, colour: u8) {
+    pub fn print_single_line_number(&mut self, number: usize, x: usize, y: usize, colour: u8) {
         self.print_line_raw(number.to_string().as_bytes(), x, y, colour);
     }
 
-    pub fn print_char(&mut self, character: u8, x: u8, y: u8, colour: u8) {
+    pub fn print_char(&mut self, character: u8, x: usize, y: usize, colour: u8) {
         let (sprite_x, sprite_y) = get_char_xy(character);
         self.print_char_raw(sprite_x, sprite_y, FONT_SIZE, FONT_SIZE, x, y, colour);
     }
@@ -691,12 +824,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         sprite_y: u8,
         sprite_w: u8,
         sprite_h: u8,
-        display_x: u8,
-        display_y: u8,
+        display_x: usize,
+        display_y: usize,
         colour: u8,
     ) {
         const S_WIDTH: usize = FONT_WIDTH as usize;
-        const D_WIDTH: usize = SCREEN_WIDTH as usize;
 
         let s_w = sprite_w as usize;
         let s_h = sprite_h as usize;
@@ -704,11 +836,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let s_x = sprite_x as usize;
         let s_y = sprite_y as usize;
 
-        let d_x = display_x as usize;
-        let d_y = display_y as usize;
+        let d_x = display_x;
+        let d_y = display_y;
 
-        let d_x_max = d_x + s_w;
-        let d_y_max = d_y + s_h;
+        let d_x_max = d_x.saturating_add(s_w);
+        let d_y_max = d_y.saturating_add(s_h);
 
         let mut current_s_y = s_y;
         for y in d_y..d_y_max {
@@ -717,10 +849,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
                 let foxt_pixel_colour = FONT[current_s_x + current_s_y * S_WIDTH] as usize;
                 //make black transparent
                 if foxt_pixel_colour != 0 {
-                    let index = x + y * D_WIDTH;
-                    if index < self.buffer.len() {
-                        self.buffer[index] = PALETTE[colour as usize & 15];
-                    }
+                    self.set(x, y, PALETTE[colour as usize & 15]);
                 }
                 current_s_x += 1;
             }
@@ -729,36 +858,33 @@ impl<D: Dimensions> FramebufferInternal<D> {
     }
 
     pub fn full_window(&mut self) {
-        self.window(0, 0, SCREEN_WIDTH as u8, SCREEN_HEIGHT as u8);
+        let (w, h) = self.viewport_size();
+        self.window(0, 0, w, h);
     }
 
     pub fn center_half_window(&mut self) {
-        self.window(
-            SCREEN_WIDTH as u8 / 4,
-            SCREEN_HEIGHT as u8 / 4,
-            SCREEN_WIDTH as u8 / 2,
-            SCREEN_HEIGHT as u8 / 2,
-        );
+        let (w, h) = self.viewport_size();
+        self.window(w / 4, h / 4, w / 2, h / 2);
     }
 
-    pub fn window(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn window(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(WINDOW_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button_hot(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button_hot(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_HOT_TOP_LEFT, x, y, w, h);
     }
 
-    pub fn button_pressed(&mut self, x: u8, y: u8, w: u8, h: u8) {
+    pub fn button_pressed(&mut self, x: usize, y: usize, w: usize, h: usize) {
         self.nine_slice(BUTTON_PRESSED_TOP_LEFT, x, y, w, h);
     }
 
     #[allow(non_snake_case)]
-    pub fn nine_slice(&mut self, top_left: u8, x: u8, y: u8, w: u8, h: u8) {
+    pub fn nine_slice(&mut self, top_left: u8, x: usize, y: usize, w: usize, h: usize) {
         let TOP_LEFT: u8 = top_left;
         let TOP: u8 = TOP_LEFT + 1;
         let TOP_RIGHT: u8 = TOP + 1;
@@ -771,11 +897,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let BOTTOM: u8 = MIDDLE + SPRITES_PER_ROW;
         let BOTTOM_RIGHT: u8 = MIDDLE_RIGHT + SPRITES_PER_ROW;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x.saturating_add(SPRITE_SIZE as usize);
+        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE as usize);
 
-        let below_top_corner = y.saturating_add(SPRITE_SIZE);
-        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE);
+        let below_top_corner = y.saturating_add(SPRITE_SIZE as usize);
+        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE as usize);
 
         for fill_y in (below_top_corner..above_bottom_corner).step_by(SPRITE_SIZE as _) {
             for fill_x in (after_left_corner..before_right_corner).step_by(SPRITE_SIZE as _) {
@@ -800,7 +926,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
     }
 
     #[allow(non_snake_case)]
-    pub fn bottom_six_slice(&mut self, top_left: u8, x: u8, y: u8, w: u8, h: u8) {
+    pub fn bottom_six_slice(&mut self, top_left: u8, x: usize, y: usize, w: usize, h: usize) {
         let TOP_LEFT: u8 = top_left;
         let TOP: u8 = TOP_LEFT + 1;
         let TOP_RIGHT: u8 = TOP + 1;
@@ -813,11 +939,11 @@ impl<D: Dimensions> FramebufferInternal<D> {
         let BOTTOM: u8 = MIDDLE + SPRITES_PER_ROW;
         let BOTTOM_RIGHT: u8 = MIDDLE_RIGHT + SPRITES_PER_ROW;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x.saturating_add(SPRITE_SIZE as usize);
+        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE as usize);
 
-        let below_top_corner = y.saturating_add(SPRITE_SIZE);
-        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE);
+        let below_top_corner = y.saturating_add(SPRITE_SIZE as usize);
+        let above_bottom_corner = y.saturating_add(h).saturating_sub(SPRITE_SIZE as usize);
 
         for fill_y in (below_top_corner..above_bottom_corner).step_by(SPRITE_SIZE as _) {
             for fill_x in (after_left_corner..before_right_corner).step_by(SPRITE_SIZE as _) {
@@ -842,13 +968,13 @@ impl<D: Dimensions> FramebufferInternal<D> {
     }
 
     #[allow(non_snake_case)]
-    fn three_slice(&mut self, left_edge: u8, x: u8, y: u8, w: u8) {
+    fn three_slice(&mut self, left_edge: u8, x: usize, y: usize, w: usize) {
         let LEFT: u8 = left_edge;
         let MIDDLE: u8 = LEFT + 1;
         let RIGHT: u8 = MIDDLE + 1;
 
-        let after_left_corner = x.saturating_add(SPRITE_SIZE);
-        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE);
+        let after_left_corner = x.saturating_add(SPRITE_SIZE as usize);
+        let before_right_corner = x.saturating_add(w).saturating_sub(SPRITE_SIZE as usize);
 
         self.spr(LEFT, x, y);
 
@@ -859,23 +985,23 @@ impl<D: Dimensions> FramebufferInternal<D> {
         self.spr(RIGHT, before_right_corner, y);
     }
 
-    pub fn row(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_hot(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_hot(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_HOT_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_pressed(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_pressed(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_PRESSED_LEFT_EDGE, x, y, w);
     }
 
-    pub fn row_marker(&mut self, x: u8, y: u8, w: u8) {
+    pub fn row_marker(&mut self, x: usize, y: usize, w: usize) {
         self.three_slice(ROW_MARKER_LEFT_EDGE, x, y, w);
     }
 
-    pub fn checkbox(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::CHECKED
@@ -887,7 +1013,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
         );
     }
 
-    pub fn checkbox_hot(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox_hot(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::HOT_CHECKED
@@ -899,7 +1025,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
         );
     }
 
-    pub fn checkbox_pressed(&mut self, x: u8, y: u8, checked: bool) {
+    pub fn checkbox_pressed(&mut self, x: usize, y: usize, checked: bool) {
         self.spr(
             if checked {
                 checkbox::PRESSED_CHECKED
@@ -911,7 +1037,7 @@ impl<D: Dimensions> FramebufferInternal<D> {
         );
     }
 
-    pub fn hexagon(&mut self, x: u8, y: u8, interior: u32, outline: u32) {
+    pub fn hexagon(&mut self, x: usize, y: usize, interior: u32, outline: u32) {
         for hex_y in 0..8 {
             for hex_x in 0..8 {
                 self.hexagon_set_pixel(x, y, hex_x, hex_y, interior, outline);
@@ -919,14 +1045,14 @@ impl<D: Dimensions> FramebufferInternal<D> {
         }
     }
 
-    pub fn hexagon_left(&mut self, x: u8, y: u8, interior: u32, outline: u32) {
+    pub fn hexagon_left(&mut self, x: usize, y: usize, interior: u32, outline: u32) {
         for hex_y in 0..8 {
             for hex_x in 0..4 {
                 self.hexagon_set_pixel(x, y, hex_x, hex_y, interior, outline);
             }
         }
     }
-    pub fn hexagon_right(&mut self, x: u8, y: u8, interior: u32, outline: u32) {
+    pub fn hexagon_right(&mut self, x: usize, y: usize, interior: u32, outline: u32) {
         for hex_y in 0..8 {
             for hex_x in 4..8 {
                 self.hexagon_set_pixel(x, y, hex_x, hex_y, interior, outline);
@@ -936,8 +1062,8 @@ impl<D: Dimensions> FramebufferInternal<D> {
 
     pub fn hexagon_set_pixel(
         &mut self,
-        x: u8,
-        y: u8,
+        x: usize,
+        y: usize,
         hex_x: u8,
         hex_y: u8,
         interior: u32,
@@ -945,12 +1071,13 @@ impl<D: Dimensions> FramebufferInternal<D> {
     ) {
         let c = Self::hexagon_match(HEXAGON[(hex_y * 8 + hex_x) as usize], interior, outline);
         if c > 0 {
-            self.buffer[Self::xy_to_i(
+            self.set(
                 //if we don't `& 0b11` here then the hexagon is drawn 4 to the right of `x`
                 //when the right half of the hexagon is drawn.
-                x.wrapping_add(hex_x & 0b11),
-                y.wrapping_add(hex_y),
-            )] = c;
+                x + (hex_x & 0b11) as usize,
+                y + hex_y as usize,
+                c,
+            );
         }
     }
 
@@ -1045,26 +1172,188 @@ mod tests {
     #[allow(unused_imports)]
     use quickcheck::*;
 
-    struct TinyDim;
+    const TINY: (usize, usize) = (8, 8);
 
-    impl_dimensions!((8, 8): TinyDim);
-
-    type Framebuffer = FramebufferInternal<TinyDim>;
+    fn tiny() -> Framebuffer {
+        Framebuffer::with_size(TINY.0, TINY.1)
+    }
 
     #[test]
     fn a_full_filled_quad_renders_correctly() {
-        let mut framebuffer = Framebuffer::new();
+        let mut framebuffer = tiny();
         const C: u32 = PALETTE[1];
         assert_ne!(framebuffer.buffer[0], C); //precondiion
 
-        let (w, h) = TinyDim::get();
-        let (max_x, max_y) = (w as u8 - 1, h as u8 - 1);
+        let (w, h) = TINY;
+        let (max_x, max_y) = (w - 1, h - 1);
 
         framebuffer.draw_filled_quad(0, 0, 0, max_y, max_x, 0, max_x, max_y, C);
 
         assert_eq!(framebuffer.buffer, vec![C; w * h]);
     }
 
+    #[test]
+    fn drawing_off_the_edge_is_clipped_instead_of_wrapping() {
+        let mut framebuffer = tiny();
+        const C: u32 = PALETTE[1];
+
+        framebuffer.draw_line(6, 1, 12, 1, C);
+        framebuffer.draw_filled_triangle(0, 6, 20, 6, 0, 20, C);
+        framebuffer.print_char(b'A', 6, 6, 1);
+
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(7, 1)], C);
+        // The pixels that would have wrapped around onto the next row.
+        assert_ne!(framebuffer.buffer[framebuffer.xy_to_i(0, 2)], C);
+        assert_ne!(framebuffer.buffer[framebuffer.xy_to_i(3, 2)], C);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(7, 7)], C);
+    }
+
+    #[test]
+    fn primitives_reach_past_the_first_256_pixels_of_a_large_framebuffer() {
+        let mut framebuffer = Framebuffer::with_size(1024, 300);
+        const C: u32 = PALETTE[1];
+
+        framebuffer.draw_line(1000, 290, 1010, 290, C);
+        framebuffer.draw_filled_quad(600, 280, 620, 280, 600, 299, 620, 299, C);
+
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(1005, 290)], C);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(610, 290)], C);
+        assert_ne!(framebuffer.buffer[framebuffer.xy_to_i(610, 279)], C);
+    }
+
+    #[test]
+    fn drawing_in_a_viewport_is_offset_and_clipped_to_it() {
+        let mut framebuffer = tiny();
+        const C: u32 = PALETTE[1];
+        let background = framebuffer.buffer[0];
+
+        framebuffer.with_viewport(2, 3, 4, 2, |framebuffer| {
+            framebuffer.draw_line(0, 0, 20, 0, C);
+            framebuffer.draw_filled_triangle(0, 1, 20, 1, 0, 20, C);
+        });
+
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(2, 3)], C);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(5, 4)], C);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(1, 3)], background);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(6, 3)], background);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(2, 5)], background);
+    }
+
+    #[test]
+    fn nested_viewports_are_clipped_to_the_outer_one_and_popped_in_order() {
+        let mut framebuffer = tiny();
+        let whole = framebuffer.viewport();
+
+        framebuffer.push_viewport(4, 4, 10, 10);
+        assert_eq!(
+            framebuffer.viewport(),
+            Viewport {
+                x: 4,
+                y: 4,
+                w: 4,
+                h: 4
+            }
+        );
+
+        framebuffer.push_viewport(2, 0, 10, 1);
+        assert_eq!(
+            framebuffer.viewport(),
+            Viewport {
+                x: 6,
+                y: 4,
+                w: 2,
+                h: 1
+            }
+        );
+
+        framebuffer.push_viewport(20, 20, 1, 1);
+        assert_eq!(framebuffer.viewport_size(), (0, 0));
+        framebuffer.clear_to(PALETTE[1]);
+        assert!(!framebuffer.buffer.contains(&PALETTE[1]));
+
+        framebuffer.pop_viewport();
+        framebuffer.pop_viewport();
+        assert_eq!(
+            framebuffer.viewport(),
+            Viewport {
+                x: 4,
+                y: 4,
+                w: 4,
+                h: 4
+            }
+        );
+        framebuffer.pop_viewport();
+        assert_eq!(framebuffer.viewport(), whole);
+    }
+
+    // A viewport, then the coordinates for the primitives.
+    type ViewportAndCoords = ((u8, u8, u8, u8), (u8, u8, u8, u8, u8, u8));
+
+    #[test]
+    fn viewport_pixels_round_trip_without_touching_the_rest() {
+        let mut framebuffer = tiny();
+        let background = framebuffer.buffer[0];
+
+        framebuffer.push_viewport(1, 2, 3, 2);
+        framebuffer.set(2, 1, PALETTE[1]);
+        let pixels = framebuffer.viewport_pixels();
+        assert_eq!(pixels.len(), 6);
+        assert_eq!(pixels[5], PALETTE[1]);
+
+        framebuffer.clear_to(PALETTE[2]);
+        framebuffer.set_viewport_pixels(&pixels);
+        framebuffer.pop_viewport();
+
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(3, 3)], PALETTE[1]);
+        assert_eq!(framebuffer.buffer[framebuffer.xy_to_i(1, 2)], background);
+        assert!(!framebuffer.buffer.contains(&PALETTE[2]));
+    }
+
+    #[test]
+    fn test_primitives_never_draw_outside_the_viewport() {
+        quickcheck(primitives_never_draw_outside_the_viewport as fn(ViewportAndCoords) -> bool)
+    }
+    fn primitives_never_draw_outside_the_viewport(
+        ((x, y, w, h), (a, b, c, d, e, f)): ViewportAndCoords,
+    ) -> bool {
+        const BACKGROUND: u32 = 0x1234_5678;
+        const C: u32 = PALETTE[1];
+        let mut framebuffer = Framebuffer::with_size(64, 48);
+        framebuffer.clear_to(BACKGROUND);
+        let (x, y, w, h) = (
+            x as usize / 3,
+            y as usize / 4,
+            w as usize / 3,
+            h as usize / 4,
+        );
+        let (a, b, c, d, e, f) = (
+            a as usize, b as usize, c as usize, d as usize, e as usize, f as usize,
+        );
+
+        let viewport = framebuffer.with_viewport(x, y, w, h, |framebuffer| {
+            framebuffer.draw_filled_triangle(a, b, c, d, e, f, C);
+            framebuffer.draw_line(a, b, c, d, C);
+            framebuffer.draw_rect(a, b, c, d, C);
+            framebuffer.draw_filled_rect(e, f, a, b, C);
+            framebuffer.draw_circle(c, d, e / 4, C);
+            framebuffer.draw_filled_circle(e, f, a / 4, C);
+            framebuffer.print_char(b'#', a / 4, b / 4, 1);
+            framebuffer.spr(1, c / 4, d / 4);
+            framebuffer.window(e / 4, f / 4, a / 2, b / 2);
+            framebuffer.viewport()
+        });
+
+        (0..framebuffer.height).all(|py| {
+            (0..framebuffer.width).all(|px| {
+                let inside = px >= viewport.x
+                    && px < viewport.x + viewport.w
+                    && py >= viewport.y
+                    && py < viewport.y + viewport.h;
+                inside || framebuffer.buffer[framebuffer.xy_to_i(px, py)] == BACKGROUND
+            })
+        })
+    }
+
     macro_rules! all_perms_quad_check {
         ($bytes: expr) => {
             //by hand
@@ -1178,8 +1467,8 @@ mod tests {
 
     #[test]
     fn all_diamond_quads_render_correctly() {
-        let (w, h) = TinyDim::get();
-        let (max_x, max_y) = (w as u8 - 1, h as u8 - 1);
+        let (w, h) = TINY;
+        let (max_x, max_y) = (w - 1, h - 1);
         let bytes = [
             max_x / 2,
             0,
@@ -1196,8 +1485,8 @@ mod tests {
 
     #[test]
     fn a_quad_that_was_incorrectly_rendered_as_a_triangle_renders_correctly() {
-        let (w, h) = TinyDim::get();
-        let (max_x, max_y) = (w as u8 - 1, h as u8 - 1);
+        let (w, h) = TINY;
+        let (max_x, max_y) = (w - 1, h - 1);
 
         let bytes = [max_x, 0, max_x, max_y / 4, 0, max_y / 2, max_x / 2, max_y];
 
@@ -1206,8 +1495,8 @@ mod tests {
 
     #[test]
     fn concave_crescent_quads_render_correctly() {
-        let (w, h) = TinyDim::get();
-        let (max_x, max_y) = (w as u8 - 1, h as u8 - 1);
+        let (w, h) = TINY;
+        let (max_x, max_y) = (w - 1, h - 1);
 
         let bytes = [
             max_x / 2,
@@ -1223,8 +1512,8 @@ mod tests {
         all_perms_quad_check!(bytes);
     }
 
-    fn quad_check(bytes: &[u8]) {
-        let mut framebuffer = Framebuffer::new();
+    fn quad_check(bytes: &[usize]) {
+        let mut framebuffer = tiny();
         const C: u32 = PALETTE[1];
         assert_ne!(framebuffer.buffer[0], C); //precondiions
         assert!(bytes.len() >= 8);
@@ -1234,28 +1523,28 @@ mod tests {
         );
 
         assert_eq!(
-            framebuffer.buffer[Framebuffer::xy_to_i(bytes[0], bytes[1])],
+            framebuffer.buffer[framebuffer.xy_to_i(bytes[0], bytes[1])],
             C,
             "bytes: {:?} at: {:?}",
             bytes,
             (bytes[0], bytes[1])
         );
         assert_eq!(
-            framebuffer.buffer[Framebuffer::xy_to_i(bytes[2], bytes[3])],
+            framebuffer.buffer[framebuffer.xy_to_i(bytes[2], bytes[3])],
             C,
             "bytes: {:?} at: {:?}",
             bytes,
             (bytes[2], bytes[3])
         );
         assert_eq!(
-            framebuffer.buffer[Framebuffer::xy_to_i(bytes[4], bytes[5])],
+            framebuffer.buffer[framebuffer.xy_to_i(bytes[4], bytes[5])],
             C,
             "bytes: {:?} at: {:?}",
             bytes,
             (bytes[4], bytes[5])
         );
         assert_eq!(
-            framebuffer.buffer[Framebuffer::xy_to_i(bytes[6], bytes[7])],
+            framebuffer.buffer[framebuffer.xy_to_i(bytes[6], bytes[7])],
             C,
             "bytes: {:?} at: {:?}",
             bytes,
@@ -0,0 +1,166 @@
+/// A file format recognised by the magic number at the start of the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Elf,
+    /// A Windows executable: an MZ header pointing at a PE header.
+    Pe,
+    /// An MZ executable without a PE header, so most likely for MS-DOS.
+    Mz,
+    MachO,
+    Png,
+    Jpeg,
+    Gif,
+    Zip,
+    Gzip,
+    Pdf,
+    Wav,
+    /// Any RIFF file other than WAV, for example AVI or WebP.
+    Riff,
+    INes,
+    Sqlite,
+}
+
+impl FileType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileType::Elf => "ELF",
+            FileType::Pe => "PE",
+            FileType::Mz => "MZ",
+            FileType::MachO => "Mach-O",
+            FileType::Png => "PNG",
+            FileType::Jpeg => "JPEG",
+            FileType::Gif => "GIF",
+            FileType::Zip => "ZIP",
+            FileType::Gzip => "gzip",
+            FileType::Pdf => "PDF",
+            FileType::Wav => "WAV",
+            FileType::Riff => "RIFF",
+            FileType::INes => "iNES",
+            FileType::Sqlite => "SQLite",
+        }
+    }
+}
+
+const PREFIXES: &[(&[u8], FileType)] = &[
+    (b"\x7FELF", FileType::Elf),
+    (b"\xFE\xED\xFA\xCE", FileType::MachO),
+    (b"\xFE\xED\xFA\xCF", FileType::MachO),
+    (b"\xCE\xFA\xED\xFE", FileType::MachO),
+    (b"\xCF\xFA\xED\xFE", FileType::MachO),
+    (b"\x89PNG\r\n\x1A\n", FileType::Png),
+    (b"\xFF\xD8\xFF", FileType::Jpeg),
+    (b"GIF87a", FileType::Gif),
+    (b"GIF89a", FileType::Gif),
+    (b"PK\x03\x04", FileType::Zip),
+    // An empty archive, which is just the end of central directory record.
+    (b"PK\x05\x06", FileType::Zip),
+    (b"\x1F\x8B", FileType::Gzip),
+    (b"%PDF-", FileType::Pdf),
+    (b"NES\x1A", FileType::INes),
+    (b"SQLite format 3\0", FileType::Sqlite),
+];
+
+/// Returns the type of file `bytes` appears to be, going by its leading bytes.
+pub fn detect(bytes: &[u8]) -> Option<FileType> {
+    if let Some(&(_, file_type)) = PREFIXES.iter().find(|(p, _)| bytes.starts_with(p)) {
+        return Some(file_type);
+    }
+
+    if bytes.starts_with(b"MZ") {
+        return Some(if has_pe_header(bytes) {
+            FileType::Pe
+        } else {
+            FileType::Mz
+        });
+    }
+
+    if bytes.starts_with(b"RIFF") {
+        return Some(if bytes.get(8..12) == Some(b"WAVE") {
+            FileType::Wav
+        } else {
+            FileType::Riff
+        });
+    }
+
+    // Java class files use the same magic number as universal Mach-O binaries, but they
+    // have a version number where the Mach-O files have a count of architectures, and the
+    // version numbers are all much larger than any reasonable count.
+    let count = read_u32_be(bytes, 4);
+    if bytes.starts_with(b"\xCA\xFE\xBA\xBE") && matches!(count, Some(n) if n < 32) {
+        return Some(FileType::MachO);
+    }
+
+    None
+}
+
+// The offset of the PE header is stored at 0x3C in the MZ header.
+fn has_pe_header(bytes: &[u8]) -> bool {
+    read_u32_le(bytes, 0x3C)
+        .and_then(|offset| {
+            bytes
+                .get(offset as usize..)
+                .map(|rest| rest.starts_with(b"PE\0\0"))
+        })
+        .unwrap_or(false)
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24)
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from(b[0]) << 24 | u32::from(b[1]) << 16 | u32::from(b[2]) << 8 | u32::from(b[3]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_recognises_simple_prefixes() {
+        assert_eq!(detect(b"\x7FELF\x02\x01\x01"), Some(FileType::Elf));
+        assert_eq!(
+            detect(b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR"),
+            Some(FileType::Png)
+        );
+        assert_eq!(detect(b"SQLite format 3\0\x10\0"), Some(FileType::Sqlite));
+        assert_eq!(detect(b"NES\x1A\x02\x01"), Some(FileType::INes));
+    }
+
+    #[test]
+    fn detect_tells_pe_files_from_plain_mz_files() {
+        let mut bytes = vec![0; 0x48];
+        bytes[..2].copy_from_slice(b"MZ");
+        assert_eq!(detect(&bytes), Some(FileType::Mz));
+
+        bytes[0x3C] = 0x40;
+        bytes[0x40..0x44].copy_from_slice(b"PE\0\0");
+        assert_eq!(detect(&bytes), Some(FileType::Pe));
+
+        // An offset pointing past the end shouldn't panic.
+        bytes[0x3C] = 0xFF;
+        assert_eq!(detect(&bytes), Some(FileType::Mz));
+    }
+
+    #[test]
+    fn detect_tells_wav_files_from_other_riff_files() {
+        assert_eq!(detect(b"RIFF\x24\0\0\0WAVEfmt "), Some(FileType::Wav));
+        assert_eq!(detect(b"RIFF\x24\0\0\0WEBPVP8 "), Some(FileType::Riff));
+        assert_eq!(detect(b"RIFF"), Some(FileType::Riff));
+    }
+
+    #[test]
+    fn detect_does_not_mistake_java_classes_for_mach_o() {
+        assert_eq!(detect(b"\xCA\xFE\xBA\xBE\0\0\0\x02"), Some(FileType::MachO));
+        assert_eq!(detect(b"\xCA\xFE\xBA\xBE\0\0\0\x34"), None);
+    }
+
+    #[test]
+    fn detect_returns_none_for_short_or_unknown_input() {
+        assert_eq!(detect(b""), None);
+        assert_eq!(detect(b"\x7FEL"), None);
+        assert_eq!(detect(b"hello"), None);
+    }
+}
@@ -0,0 +1,399 @@
+//! Describes binary layouts in a small text format, and decodes bytes with them.
+//!
+//! A template is a list of structs, the first of which is the one that gets applied:
+//!
+//! ```text
+//! # Everything after a `#` is a comment.
+//! endian be
+//!
+//! struct header {
+//!     magic: u8[4]
+//!     version: u16le
+//!     count: u32
+//!     entries: entry[count]
+//! }
+//!
+//! struct entry {
+//!     id: u16
+//!     scale: f32
+//! }
+//! ```
+//!
+//! Fields are `u8` to `u64`, `i8` to `i64`, `f32`, `f64` or the name of another struct.
+//! Number types are little endian unless `endian be` is given, and `le` or `be` on the
+//! end of the type overrides that for one field. An array's length is either a number
+//! or the name of an integer field that comes earlier in the same struct, or in one of
+//! the structs containing it.
+
+use std::fmt;
+
+mod parse;
+pub use parse::{ParseError, ParseErrorKind};
+
+// Deeper than this is almost certainly a struct containing itself.
+const MAX_DEPTH: usize = 64;
+
+// Keeps a bad count from decoding the whole file one byte at a time.
+pub const MAX_FIELDS: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    endian: Endian,
+    structs: Vec<StructDef>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct StructDef {
+    name: String,
+    fields: Vec<FieldDef>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct FieldDef {
+    name: String,
+    ty: Type,
+    count: Option<Count>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Type {
+    Unsigned(usize, Option<Endian>),
+    Signed(usize, Option<Endian>),
+    Float(usize, Option<Endian>),
+    /// An index into `Template::structs`.
+    Struct(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Count {
+    Fixed(usize),
+    Field(String),
+}
+
+/// A decoded field, and the bytes it was decoded from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub start: usize,
+    pub len: usize,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    F32(f32),
+    F64(f64),
+    /// The elements are named by their index, like `[0]`.
+    Array(Vec<Field>),
+    Struct(Vec<Field>),
+}
+
+impl Value {
+    /// The fields inside an array or struct, which is none for numbers.
+    pub fn children(&self) -> &[Field] {
+        match *self {
+            Value::Array(ref fields) | Value::Struct(ref fields) => fields,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A field ran past the end of the bytes.
+    Truncated {
+        offset: usize,
+    },
+    /// An array's length names a field that hasn't been decoded.
+    UnknownCount(String),
+    /// An array's length names a field that isn't a non-negative integer.
+    BadCount(String),
+    TooDeep,
+    TooManyFields,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Truncated { offset } => write!(f, "truncated at offset {:#X}", offset),
+            Error::UnknownCount(ref name) => write!(f, "no field named {:?} before here", name),
+            Error::BadCount(ref name) => write!(f, "{:?} can't be used as a count", name),
+            Error::TooDeep => write!(f, "structs nested more than {} deep", MAX_DEPTH),
+            Error::TooManyFields => write!(f, "more than {} fields", MAX_FIELDS),
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        parse::parse(source)
+    }
+
+    /// The name of the struct that `apply` decodes.
+    pub fn root_name(&self) -> &str {
+        &self.structs[0].name
+    }
+
+    /// Decodes the first struct in the template from `bytes`, starting at `offset`.
+    pub fn apply(&self, bytes: &[u8], offset: usize) -> Result<Field, Error> {
+        let mut decoder = Decoder {
+            template: self,
+            bytes,
+            scopes: Vec::new(),
+            field_count: 0,
+        };
+
+        let root = &self.structs[0];
+        decoder.decode(&root.name, Type::Struct(0), offset)
+    }
+}
+
+struct Decoder<'a> {
+    template: &'a Template,
+    bytes: &'a [u8],
+    // The number fields decoded so far in each struct that is being decoded, innermost
+    // last, so counts can refer to them.
+    scopes: Vec<Vec<(&'a str, Value)>>,
+    field_count: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn decode(&mut self, name: &str, ty: Type, offset: usize) -> Result<Field, Error> {
+        self.field_count += 1;
+        if self.field_count > MAX_FIELDS {
+            return Err(Error::TooManyFields);
+        }
+
+        let value = match ty {
+            Type::Unsigned(size, endian) => Value::Unsigned(self.uint(offset, size, endian)?),
+            Type::Signed(size, endian) => {
+                let n = self.uint(offset, size, endian)?;
+                // Shift the sign bit up to the top, then back down with sign extension.
+                let unused_bits = 64 - size as u32 * 8;
+                Value::Signed((n << unused_bits) as i64 >> unused_bits)
+            }
+            Type::Float(4, endian) => {
+                Value::F32(f32::from_bits(self.uint(offset, 4, endian)? as u32))
+            }
+            Type::Float(_, endian) => Value::F64(f64::from_bits(self.uint(offset, 8, endian)?)),
+            Type::Struct(index) => return self.decode_struct(name, index, offset),
+        };
+
+        Ok(Field {
+            name: name.to_string(),
+            start: offset,
+            len: ty.size().unwrap_or(0),
+            value,
+        })
+    }
+
+    fn decode_struct(&mut self, name: &str, index: usize, offset: usize) -> Result<Field, Error> {
+        if self.scopes.len() >= MAX_DEPTH {
+            return Err(Error::TooDeep);
+        }
+        self.scopes.push(Vec::new());
+
+        let template = self.template;
+        let mut fields = Vec::with_capacity(template.structs[index].fields.len());
+        let mut end = offset;
+        for def in template.structs[index].fields.iter() {
+            let field = match def.count {
+                None => self.decode(&def.name, def.ty, end)?,
+                Some(ref count) => self.decode_array(def, count, end)?,
+            };
+
+            match field.value {
+                Value::Array(_) | Value::Struct(_) => {}
+                _ => {
+                    let scope = self.scopes.last_mut().expect("pushed above");
+                    scope.push((&def.name, field.value.clone()));
+                }
+            }
+
+            end += field.len;
+            fields.push(field);
+        }
+
+        self.scopes.pop();
+        Ok(Field {
+            name: name.to_string(),
+            start: offset,
+            len: end - offset,
+            value: Value::Struct(fields),
+        })
+    }
+
+    fn decode_array(
+        &mut self,
+        def: &FieldDef,
+        count: &Count,
+        offset: usize,
+    ) -> Result<Field, Error> {
+        let count = match *count {
+            Count::Fixed(count) => count,
+            Count::Field(ref name) => self.lookup_count(name)?,
+        };
+
+        let mut elements = Vec::new();
+        let mut end = offset;
+        for i in 0..count {
+            let element = self.decode(&format!("[{}]", i), def.ty, end)?;
+            end += element.len;
+            elements.push(element);
+        }
+
+        Ok(Field {
+            name: def.name.clone(),
+            start: offset,
+            len: end - offset,
+            value: Value::Array(elements),
+        })
+    }
+
+    fn lookup_count(&self, name: &str) -> Result<usize, Error> {
+        let value = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|&&(field_name, _)| field_name == name)
+            .map(|(_, value)| value);
+
+        match value {
+            Some(&Value::Unsigned(n)) => Ok(n as usize),
+            Some(&Value::Signed(n)) if n >= 0 => Ok(n as usize),
+            Some(_) => Err(Error::BadCount(name.to_string())),
+            None => Err(Error::UnknownCount(name.to_string())),
+        }
+    }
+
+    fn uint(&self, offset: usize, size: usize, endian: Option<Endian>) -> Result<u64, Error> {
+        let bytes = offset
+            .checked_add(size)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or(Error::Truncated { offset })?;
+
+        let fold = |acc: u64, &b: &u8| acc << 8 | u64::from(b);
+        Ok(match endian.unwrap_or(self.template.endian) {
+            Endian::Big => bytes.iter().fold(0, fold),
+            Endian::Little => bytes.iter().rev().fold(0, fold),
+        })
+    }
+}
+
+impl Type {
+    // The size of the number types. Structs can contain arrays, so their size isn't known
+    // until they have been decoded.
+    fn size(self) -> Option<usize> {
+        match self {
+            Type::Unsigned(size, _) | Type::Signed(size, _) | Type::Float(size, _) => Some(size),
+            Type::Struct(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn children(field: &Field) -> Vec<(&str, usize, usize)> {
+        field
+            .value
+            .children()
+            .iter()
+            .map(|f| (f.name.as_str(), f.start, f.len))
+            .collect()
+    }
+
+    #[test]
+    fn apply_decodes_counted_arrays_of_structs() {
+        let template = Template::parse(
+            "struct file { magic: u8[2] count: u16be entries: entry[count] }
+             struct entry { id: u8 value: i16 }",
+        )
+        .unwrap();
+        let bytes = [b'A', b'B', 0, 2, 1, 0xFE, 0xFF, 2, 3, 0];
+
+        let file = template.apply(&bytes, 0).unwrap();
+
+        assert_eq!(
+            children(&file),
+            vec![("magic", 0, 2), ("count", 2, 2), ("entries", 4, 6)]
+        );
+        let entries = &file.value.children()[2];
+        assert_eq!(children(entries), vec![("[0]", 4, 3), ("[1]", 7, 3)]);
+        assert_eq!(
+            entries.value.children()[0].value.children()[1].value,
+            Value::Signed(-2)
+        );
+    }
+
+    #[test]
+    fn apply_reads_counts_from_enclosing_structs() {
+        let template = Template::parse(
+            "struct outer { n: u8 inner: inner }
+             struct inner { items: u8[n] }",
+        )
+        .unwrap();
+
+        let outer = template.apply(&[9, 2, 1, 2], 1).unwrap();
+
+        assert_eq!(outer.len, 3);
+        assert_eq!(children(&outer), vec![("n", 1, 1), ("inner", 2, 2)]);
+    }
+
+    #[test]
+    fn apply_honours_the_default_and_per_field_endianness() {
+        let template = Template::parse("endian be struct s { a: u16 b: u16le c: f32 }").unwrap();
+        let mut bytes = vec![1, 2, 1, 2];
+        bytes.extend_from_slice(&1.5f32.to_be_bytes());
+
+        let values: Vec<_> = template
+            .apply(&bytes, 0)
+            .unwrap()
+            .value
+            .children()
+            .iter()
+            .map(|f| f.value.clone())
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                Value::Unsigned(0x0102),
+                Value::Unsigned(0x0201),
+                Value::F32(1.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_reports_bad_input() {
+        let template = Template::parse("struct s { a: u32 }").unwrap();
+        assert_eq!(
+            template.apply(&[0; 5], 2),
+            Err(Error::Truncated { offset: 2 })
+        );
+
+        let template = Template::parse("struct s { a: f32 b: u8[a] }").unwrap();
+        assert_eq!(
+            template.apply(&[0; 5], 0),
+            Err(Error::BadCount("a".to_string()))
+        );
+
+        let template = Template::parse("struct s { t: s }").unwrap();
+        assert_eq!(template.apply(&[], 0), Err(Error::TooDeep));
+
+        let template =
+            Template::parse("struct s { e: empty[0xFFFFFFFF] } struct empty {}").unwrap();
+        assert_eq!(template.apply(&[], 0), Err(Error::TooManyFields));
+    }
+}
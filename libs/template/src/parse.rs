@@ -0,0 +1,382 @@
+use std::fmt;
+
+use crate::{Count, Endian, FieldDef, StructDef, Template, Type};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Counting from 1, like text editors do.
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidCharacter(char),
+    Expected {
+        expected: &'static str,
+        found: String,
+    },
+    UnknownType(String),
+    DuplicateStruct(String),
+    NoStructs,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match self.kind {
+            ParseErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            ParseErrorKind::Expected {
+                expected,
+                ref found,
+            } => write!(f, "expected {} but found {}", expected, found),
+            ParseErrorKind::UnknownType(ref name) => write!(f, "unknown type {:?}", name),
+            ParseErrorKind::DuplicateStruct(ref name) => {
+                write!(f, "struct {:?} is defined twice", name)
+            }
+            ParseErrorKind::NoStructs => write!(f, "no structs"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Number(usize),
+    Symbol(char),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Word(ref word) => write!(f, "{:?}", word),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Symbol(c) => write!(f, "`{}`", c),
+            Token::End => write!(f, "the end"),
+        }
+    }
+}
+
+// Each token along with the line it is on.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let token = if c.is_whitespace() {
+                continue;
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+
+                let word = &line[start..end];
+                if c.is_ascii_digit() {
+                    let number = if word.starts_with("0x") || word.starts_with("0X") {
+                        usize::from_str_radix(&word[2..], 16)
+                    } else {
+                        word.parse()
+                    };
+                    match number {
+                        Ok(n) => Token::Number(n),
+                        Err(_) => {
+                            return Err(ParseError {
+                                line: line_number,
+                                kind: ParseErrorKind::Expected {
+                                    expected: "a number",
+                                    found: format!("{:?}", word),
+                                },
+                            })
+                        }
+                    }
+                } else {
+                    Token::Word(word.to_string())
+                }
+            } else if "{}[]:;".contains(c) {
+                Token::Symbol(c)
+            } else {
+                return Err(ParseError {
+                    line: line_number,
+                    kind: ParseErrorKind::InvalidCharacter(c),
+                });
+            };
+
+            tokens.push((token, line_number));
+        }
+    }
+
+    let last_line = std::cmp::max(source.lines().count(), 1);
+    tokens.push((Token::End, last_line));
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error(&self, expected: &'static str) -> ParseError {
+        ParseError {
+            line: self.line(),
+            kind: ParseErrorKind::Expected {
+                expected,
+                found: self.peek().to_string(),
+            },
+        }
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<String, ParseError> {
+        match self.peek().clone() {
+            Token::Word(word) => {
+                self.next();
+                Ok(word)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn symbol(&mut self, symbol: char, expected: &'static str) -> Result<(), ParseError> {
+        if *self.peek() == Token::Symbol(symbol) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+}
+
+// A field's type before the struct names have all been seen.
+struct UnresolvedField {
+    name: String,
+    type_name: String,
+    count: Option<Count>,
+    line: usize,
+}
+
+pub fn parse(source: &str) -> Result<Template, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+
+    let mut endian = Endian::Little;
+    let mut structs: Vec<(String, Vec<UnresolvedField>)> = Vec::new();
+    loop {
+        let line = parser.line();
+        match parser.next() {
+            Token::End => break,
+            Token::Word(ref word) if word == "endian" => {
+                endian = match parser.word("`le` or `be`")?.as_str() {
+                    "le" => Endian::Little,
+                    "be" => Endian::Big,
+                    _ => {
+                        parser.position -= 1;
+                        return Err(parser.error("`le` or `be`"));
+                    }
+                };
+            }
+            Token::Word(ref word) if word == "struct" => {
+                let name = parser.word("a struct name")?;
+                if structs.iter().any(|(existing, _)| *existing == name) {
+                    return Err(ParseError {
+                        line,
+                        kind: ParseErrorKind::DuplicateStruct(name),
+                    });
+                }
+                let fields = parse_fields(&mut parser)?;
+                structs.push((name, fields));
+            }
+            _ => {
+                parser.position -= 1;
+                return Err(parser.error("`struct` or `endian`"));
+            }
+        }
+    }
+
+    if structs.is_empty() {
+        return Err(ParseError {
+            line: 1,
+            kind: ParseErrorKind::NoStructs,
+        });
+    }
+
+    let names: Vec<String> = structs.iter().map(|(name, _)| name.clone()).collect();
+    let mut resolved = Vec::with_capacity(structs.len());
+    for (name, fields) in structs {
+        let mut defs = Vec::with_capacity(fields.len());
+        for field in fields {
+            let ty = match resolve_type(&field.type_name, &names) {
+                Some(ty) => ty,
+                None => {
+                    return Err(ParseError {
+                        line: field.line,
+                        kind: ParseErrorKind::UnknownType(field.type_name),
+                    })
+                }
+            };
+            defs.push(FieldDef {
+                name: field.name,
+                ty,
+                count: field.count,
+            });
+        }
+        resolved.push(StructDef { name, fields: defs });
+    }
+
+    Ok(Template {
+        endian,
+        structs: resolved,
+    })
+}
+
+// Parses `{ name: type[count] ... }`, where the count is optional and fields can be
+// separated by `;` as well as whitespace.
+fn parse_fields(parser: &mut Parser) -> Result<Vec<UnresolvedField>, ParseError> {
+    parser.symbol('{', "`{`")?;
+
+    let mut fields = Vec::new();
+    loop {
+        match *parser.peek() {
+            Token::Symbol('}') => {
+                parser.next();
+                return Ok(fields);
+            }
+            Token::Symbol(';') => {
+                parser.next();
+                continue;
+            }
+            _ => {}
+        }
+
+        let line = parser.line();
+        let name = parser.word("a field name or `}`")?;
+        parser.symbol(':', "`:`")?;
+        let type_name = parser.word("a type")?;
+
+        let count = if *parser.peek() == Token::Symbol('[') {
+            parser.next();
+            let count = match parser.peek().clone() {
+                Token::Number(n) => Count::Fixed(n),
+                Token::Word(word) => Count::Field(word),
+                _ => return Err(parser.error("a number or a field name")),
+            };
+            parser.next();
+            parser.symbol(']', "`]`")?;
+            Some(count)
+        } else {
+            None
+        };
+
+        fields.push(UnresolvedField {
+            name,
+            type_name,
+            count,
+            line,
+        });
+    }
+}
+
+fn resolve_type(name: &str, struct_names: &[String]) -> Option<Type> {
+    if let Some(index) = struct_names.iter().position(|n| n == name) {
+        return Some(Type::Struct(index));
+    }
+
+    let (base, endian) = if let Some(base) = name.strip_suffix("le") {
+        (base, Some(Endian::Little))
+    } else if let Some(base) = name.strip_suffix("be") {
+        (base, Some(Endian::Big))
+    } else {
+        (name, None)
+    };
+
+    let size = match base.get(1..).and_then(|bits| bits.parse::<usize>().ok()) {
+        Some(bits @ 8) | Some(bits @ 16) | Some(bits @ 32) | Some(bits @ 64) => bits / 8,
+        _ => return None,
+    };
+
+    match &base[..1] {
+        "u" => Some(Type::Unsigned(size, endian)),
+        "i" => Some(Type::Signed(size, endian)),
+        "f" if size >= 4 => Some(Type::Float(size, endian)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_type_knows_the_number_types() {
+        let structs = ["entry".to_string()];
+
+        assert_eq!(
+            resolve_type("u16be", &structs),
+            Some(Type::Unsigned(2, Some(Endian::Big)))
+        );
+        assert_eq!(resolve_type("i8", &structs), Some(Type::Signed(1, None)));
+        assert_eq!(
+            resolve_type("f64le", &structs),
+            Some(Type::Float(8, Some(Endian::Little)))
+        );
+        assert_eq!(resolve_type("entry", &structs), Some(Type::Struct(0)));
+        assert_eq!(resolve_type("f16", &structs), None);
+        assert_eq!(resolve_type("u24", &structs), None);
+    }
+
+    #[test]
+    fn parse_reports_the_line_of_errors() {
+        let error = |source| parse(source).unwrap_err();
+
+        assert_eq!(
+            error("struct a {\n  b: u8\n  c: thing\n}"),
+            ParseError {
+                line: 3,
+                kind: ParseErrorKind::UnknownType("thing".to_string()),
+            }
+        );
+        assert_eq!(
+            error("struct a {\n  b u8\n}"),
+            ParseError {
+                line: 2,
+                kind: ParseErrorKind::Expected {
+                    expected: "`:`",
+                    found: "\"u8\"".to_string(),
+                },
+            }
+        );
+        assert_eq!(error("# nothing here").kind, ParseErrorKind::NoStructs);
+        assert_eq!(
+            error("struct a {} struct a {}").kind,
+            ParseErrorKind::DuplicateStruct("a".to_string())
+        );
+    }
+}
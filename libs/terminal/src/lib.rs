@@ -0,0 +1,667 @@
+//! Runs a `State` in a terminal, for machines without a browser, like the ones we reach
+//! over SSH. Each character cell shows two pixels: an upper half block whose foreground
+//! is the top pixel and whose background is the bottom one, both in 24 bit colour.
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, style, terminal};
+use platform_types::{
+    h, w, Action, Button, EditKey, KeyBindings, PointerButton, State, StateParams, SFX,
+};
+use std::cmp::{max, min};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / 60);
+
+// What fills in the bottom half of the last row when the screen height isn't a multiple of
+// the rows it is drawn with.
+const OFF_SCREEN: u32 = 0xFF00_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    top: u32,
+    bottom: u32,
+}
+
+// How many pixels along each side of a square are shown by one half of a cell. The screen
+// is shrunk by skipping pixels when the terminal is too small to show all of it.
+fn scale_step((width, height): (usize, usize), columns: usize, rows: usize) -> usize {
+    max(
+        1,
+        max(
+            width.div_ceil(max(columns, 1)),
+            height.div_ceil(max(rows * 2, 1)),
+        ),
+    )
+}
+
+/// The terminal's contents, so that each frame only sends the cells that changed. Over a
+/// slow connection redrawing the whole screen every frame would not keep up.
+struct Screen {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Screen {
+    fn new(columns: usize, rows: usize) -> Self {
+        Screen {
+            columns,
+            rows,
+            cells: vec![None; columns * rows],
+        }
+    }
+
+    // Appends the escape codes that bring the terminal up to date with `framebuffer`, which
+    // is `width` by `height`.
+    fn draw(&mut self, framebuffer: &[u32], (width, height): (usize, usize), out: &mut Vec<u8>) {
+        let step = scale_step((width, height), self.columns, self.rows);
+        let visible_columns = min(self.columns, width.div_ceil(step));
+        let visible_rows = min(self.rows, height.div_ceil(step * 2));
+
+        let pixel = |x: usize, y: usize| {
+            if y < height {
+                framebuffer[y * width + x]
+            } else {
+                OFF_SCREEN
+            }
+        };
+
+        // The terminal's cursor, and the colours it is set to, as of the escape codes
+        // written so far.
+        let mut at = None;
+        let (mut foreground, mut background) = (None, None);
+        for row in 0..visible_rows {
+            for column in 0..visible_columns {
+                let (x, y) = (column * step, row * step * 2);
+                let cell = Cell {
+                    top: pixel(x, y),
+                    bottom: pixel(x, y + step),
+                };
+                let drawn = &mut self.cells[row * self.columns + column];
+                if *drawn == Some(cell) {
+                    continue;
+                }
+                *drawn = Some(cell);
+
+                if at != Some((column, row)) {
+                    write!(out, "\x1b[{};{}H", row + 1, column + 1).unwrap();
+                }
+                if foreground != Some(cell.top) {
+                    let (r, g, b) = rgb(cell.top);
+                    write!(out, "\x1b[38;2;{};{};{}m", r, g, b).unwrap();
+                    foreground = Some(cell.top);
+                }
+                if background != Some(cell.bottom) {
+                    let (r, g, b) = rgb(cell.bottom);
+                    write!(out, "\x1b[48;2;{};{};{}m", r, g, b).unwrap();
+                    background = Some(cell.bottom);
+                }
+                out.extend_from_slice("▀".as_bytes());
+                at = Some((column + 1, row));
+            }
+        }
+    }
+}
+
+// Framebuffer pixels are 0xAABBGGRR.
+fn rgb(pixel: u32) -> (u8, u8, u8) {
+    (pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8)
+}
+
+fn handle_sound(_request: SFX) {
+    // Terminals can only beep, which would get old fast.
+}
+
+// The name the DOM's `KeyboardEvent.code` gives the key, which is what the key bindings go
+// by. Terminals only say which character was typed, so this is the key it is on in the US
+// layout.
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            return Some(format!("Key{}", c.to_ascii_uppercase()))
+        }
+        KeyCode::Char(c) if c.is_ascii_digit() => return Some(format!("Digit{}", c)),
+        KeyCode::F(n) => return Some(format!("F{}", n)),
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(';') => "Semicolon",
+        KeyCode::Char('\'') => "Quote",
+        KeyCode::Char(',') => "Comma",
+        KeyCode::Char('.') => "Period",
+        KeyCode::Char('/') => "Slash",
+        KeyCode::Char('-') => "Minus",
+        KeyCode::Char('=') => "Equal",
+        KeyCode::Char('[') => "BracketLeft",
+        KeyCode::Char(']') => "BracketRight",
+        KeyCode::Char('\\') => "Backslash",
+        KeyCode::Char('`') => "Backquote",
+        KeyCode::Enter => "Enter",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Delete => "Delete",
+        KeyCode::Insert => "Insert",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Up => "ArrowUp",
+        KeyCode::Down => "ArrowDown",
+        KeyCode::Left => "ArrowLeft",
+        KeyCode::Right => "ArrowRight",
+        KeyCode::Esc => "Escape",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+fn edit_key_for(code: KeyCode) -> Option<EditKey> {
+    Some(match code {
+        KeyCode::Backspace => EditKey::Backspace,
+        KeyCode::Delete => EditKey::Delete,
+        KeyCode::Left => EditKey::Left,
+        KeyCode::Right => EditKey::Right,
+        KeyCode::Home => EditKey::Home,
+        KeyCode::End => EditKey::End,
+        KeyCode::Enter => EditKey::Enter,
+        KeyCode::Esc => EditKey::Escape,
+        _ => return None,
+    })
+}
+
+// Esc closes the text field, or stops binding a key, instead of quitting while the state
+// wants it.
+fn is_quit(key: &KeyEvent, wants_escape: bool) -> bool {
+    key.code == KeyCode::Esc && !wants_escape
+        || key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+struct PinkyTerminal<S: State> {
+    state: S,
+    // The size of the state's frame buffer.
+    size: (usize, usize),
+    screen: Screen,
+    reports_releases: bool,
+    // Most terminals only report key presses, so buttons are released again after the
+    // frame that sees them. Key repeat then arrives as more presses.
+    to_release: Button::Ty,
+    // Where the Export action saves the session.
+    session: Option<PathBuf>,
+}
+
+impl<S: State> State for PinkyTerminal<S> {
+    fn frame(&mut self, handle_sound: fn(SFX)) {
+        self.state.frame(handle_sound);
+
+        self.state.release(self.to_release);
+        self.to_release = Button::Ty::empty();
+    }
+
+    fn press(&mut self, button: Button::Ty) {
+        self.state.press(button);
+    }
+
+    fn release(&mut self, button: Button::Ty) {
+        self.state.release(button);
+    }
+
+    fn get_frame_buffer(&self) -> &[u32] {
+        self.state.get_frame_buffer()
+    }
+
+    fn update_bytes(&mut self, bytes: Vec<u8>) {
+        self.state.update_bytes(bytes);
+    }
+
+    fn update_comparison_bytes(&mut self, bytes: Vec<u8>) {
+        self.state.update_comparison_bytes(bytes);
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        self.size = (width, height);
+        self.state.resize(width, height);
+    }
+
+    fn pointer_moved(&mut self, position: Option<(usize, usize)>) {
+        self.state.pointer_moved(position);
+    }
+
+    fn pointer_pressed(&mut self, button: PointerButton::Ty) {
+        self.state.pointer_pressed(button);
+    }
+
+    fn pointer_released(&mut self, button: PointerButton::Ty) {
+        self.state.pointer_released(button);
+    }
+
+    fn wheel(&mut self, lines: i32) {
+        self.state.wheel(lines);
+    }
+
+    fn wants_text_input(&self) -> bool {
+        self.state.wants_text_input()
+    }
+
+    fn text_input(&mut self, ch: char) {
+        self.state.text_input(ch);
+    }
+
+    fn edit_key(&mut self, key: EditKey) {
+        self.state.edit_key(key);
+    }
+
+    fn key_bindings(&self) -> &KeyBindings {
+        self.state.key_bindings()
+    }
+
+    fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.state.set_key_bindings(bindings);
+    }
+
+    fn act(&mut self, action: Action) {
+        self.state.act(action);
+    }
+
+    fn binding_key(&self) -> bool {
+        self.state.binding_key()
+    }
+
+    fn bind_key(&mut self, key: &str) {
+        self.state.bind_key(key);
+    }
+
+    fn session(&self) -> String {
+        self.state.session()
+    }
+
+    fn restore_session(&mut self, json: &str) -> Result<(), String> {
+        self.state.restore_session(json)
+    }
+
+    fn link(&self) -> String {
+        self.state.link()
+    }
+
+    fn follow_link(&mut self, link: &str) -> Result<(), String> {
+        self.state.follow_link(link)
+    }
+}
+
+// Terminals only see the characters typed rather than the keys, so for those using the
+// Dvorak layout these are the keys where z and x are in the US layout.
+const DVORAK_KEYS: [(&str, Action); 2] = [("Semicolon", Action::A), ("KeyQ", Action::B)];
+
+impl<S: State> PinkyTerminal<S> {
+    fn new(mut state: S, reports_releases: bool) -> Self {
+        let mut bindings = state.key_bindings().clone();
+        for &(key, action) in DVORAK_KEYS.iter() {
+            if bindings.lookup(key).is_none() {
+                bindings.bind(key, action);
+            }
+        }
+        state.set_key_bindings(bindings);
+
+        PinkyTerminal {
+            state,
+            size: (w!(), h!()),
+            screen: Screen::new(0, 0),
+            reports_releases,
+            to_release: Button::Ty::empty(),
+            session: None,
+        }
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> bool {
+        // Releases still go to the buttons, so one held down when the text field opened
+        // doesn't stay down.
+        if self.state.wants_text_input() && key.kind != KeyEventKind::Release {
+            if let Some(edit) = edit_key_for(key.code) {
+                self.state.edit_key(edit);
+                return true;
+            }
+            if let KeyCode::Char(ch) = key.code {
+                self.state.text_input(ch);
+                return true;
+            }
+        }
+
+        let name = match key_name(key.code) {
+            Some(name) => name,
+            None => return false,
+        };
+        let pressed = key.kind != KeyEventKind::Release;
+        if self.state.binding_key() {
+            if pressed {
+                self.state.bind_key(&name);
+            }
+            return true;
+        }
+
+        let action = match self.state.key_bindings().lookup(&name) {
+            Some(action) => action,
+            None => return false,
+        };
+        match action.button() {
+            Some(button) if pressed => {
+                self.state.press(button);
+                if !self.reports_releases {
+                    self.to_release.insert(button);
+                }
+            }
+            Some(button) => self.state.release(button),
+            None if pressed && action == Action::Export => self.export(),
+            None if pressed => self.state.act(action),
+            None => {}
+        }
+        true
+    }
+
+    // The screen is taken up by the framebuffer, so there is nowhere to say that saving
+    // failed. Quitting saves again, and reports it then.
+    fn export(&mut self) {
+        if let Some(ref session) = self.session {
+            let _ = fs::write(session, self.state.session());
+        }
+    }
+
+    fn resize_terminal(&mut self, columns: u16, rows: u16, out: &mut Vec<u8>) {
+        self.screen = Screen::new(columns as usize, rows as usize);
+        out.extend_from_slice(b"\x1b[0m\x1b[2J");
+    }
+
+    fn draw(&mut self, out: &mut Vec<u8>) {
+        self.screen
+            .draw(self.state.get_frame_buffer(), self.size, out);
+    }
+}
+
+// Puts the terminal back the way it was when this is dropped, including on a panic.
+struct RawTerminal {
+    reports_releases: bool,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        let raw = RawTerminal { reports_releases };
+
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+        if reports_releases {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(raw)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.reports_releases {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(
+            stdout,
+            style::ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs `state` until Esc or Ctrl+C is pressed. While the state wants text, the keys type
+/// it instead, and only Ctrl+C quits. Hands the state back after quitting. The Export
+/// action saves the session to `session` along the way.
+pub fn run<S: State>(state: S, session: &Path) -> io::Result<S> {
+    let raw = RawTerminal::enter()?;
+    let mut pinky = PinkyTerminal::new(state, raw.reports_releases);
+    pinky.session = Some(session.to_path_buf());
+
+    let mut stdout = io::stdout();
+    let mut out = Vec::new();
+    let (columns, rows) = terminal::size()?;
+    pinky.resize_terminal(columns, rows, &mut out);
+
+    let mut next_frame = Instant::now();
+    loop {
+        let timeout = next_frame.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key)
+                    if is_quit(&key, pinky.wants_text_input() || pinky.binding_key()) =>
+                {
+                    return Ok(pinky.state)
+                }
+                Event::Key(key) => {
+                    pinky.on_key(key);
+                }
+                Event::Resize(columns, rows) => pinky.resize_terminal(columns, rows, &mut out),
+                _ => {}
+            }
+            continue;
+        }
+
+        // If we're running too slowly there is no point in trying to catch up.
+        next_frame = max(next_frame + FRAME_TIME, Instant::now());
+
+        pinky.frame(handle_sound);
+        pinky.draw(&mut out);
+        if !out.is_empty() {
+            stdout.write_all(&out)?;
+            stdout.flush()?;
+            out.clear();
+        }
+    }
+}
+
+pub fn get_state_params() -> StateParams {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+
+    // The screen is taken up by the framebuffer, so there is nowhere to show logs.
+    (time.to_le_bytes(), None, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: (usize, usize) = (w!(), h!());
+
+    #[test]
+    fn scale_step_fits_the_screen_in_the_terminal() {
+        assert_eq!(scale_step(SIZE, 256, 128), 1);
+        assert_eq!(scale_step(SIZE, 300, 200), 1);
+        assert_eq!(scale_step(SIZE, 255, 128), 2);
+        assert_eq!(scale_step(SIZE, 256, 64), 2);
+        assert_eq!(scale_step(SIZE, 80, 24), 6);
+        assert_eq!(scale_step(SIZE, 0, 0), 256);
+    }
+
+    #[test]
+    fn draw_only_sends_the_cells_that_changed() {
+        let mut framebuffer = vec![0xFF00_0000; w!() * h!()];
+        framebuffer[0] = 0xFF00_00FF;
+        framebuffer[w!()] = 0xFF00_FF00;
+        let mut screen = Screen::new(256, 128);
+
+        let mut out = Vec::new();
+        screen.draw(&framebuffer, SIZE, &mut out);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1b[1;1H\x1b[38;2;255;0;0m\x1b[48;2;0;255;0m▀"));
+        assert_eq!(out.matches('▀').count(), 256 * 128);
+
+        framebuffer[w!() * 2 + 3] = 0xFFFF_0000;
+        let mut out = Vec::new();
+        screen.draw(&framebuffer, SIZE, &mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[2;4H\x1b[38;2;0;0;255m\x1b[48;2;0;0;0m▀"
+        );
+    }
+
+    // Records the buttons held down, the text typed so far while a text field is open, and
+    // the other actions done.
+    #[derive(Default)]
+    struct Buttons {
+        held: Button::Ty,
+        text: Option<String>,
+        bindings: KeyBindings,
+        acted: Vec<Action>,
+    }
+
+    impl State for Buttons {
+        fn frame(&mut self, _: fn(SFX)) {}
+        fn press(&mut self, button: Button::Ty) {
+            self.held.insert(button);
+        }
+        fn release(&mut self, button: Button::Ty) {
+            self.held.remove(button);
+        }
+        fn get_frame_buffer(&self) -> &[u32] {
+            &[]
+        }
+        fn update_bytes(&mut self, _: Vec<u8>) {}
+        fn update_comparison_bytes(&mut self, _: Vec<u8>) {}
+        fn resize(&mut self, _: usize, _: usize) {}
+        fn pointer_moved(&mut self, _: Option<(usize, usize)>) {}
+        fn pointer_pressed(&mut self, _: PointerButton::Ty) {}
+        fn pointer_released(&mut self, _: PointerButton::Ty) {}
+        fn wheel(&mut self, _: i32) {}
+        fn wants_text_input(&self) -> bool {
+            self.text.is_some()
+        }
+        fn text_input(&mut self, ch: char) {
+            self.text.as_mut().unwrap().push(ch);
+        }
+        fn edit_key(&mut self, key: EditKey) {
+            if key == EditKey::Enter {
+                self.text = None;
+            }
+        }
+        fn key_bindings(&self) -> &KeyBindings {
+            &self.bindings
+        }
+        fn set_key_bindings(&mut self, bindings: KeyBindings) {
+            self.bindings = bindings;
+        }
+        fn act(&mut self, action: Action) {
+            self.acted.push(action);
+        }
+        fn binding_key(&self) -> bool {
+            false
+        }
+        fn bind_key(&mut self, _: &str) {}
+        fn session(&self) -> String {
+            String::new()
+        }
+        fn restore_session(&mut self, _: &str) -> Result<(), String> {
+            Ok(())
+        }
+        fn link(&self) -> String {
+            String::new()
+        }
+        fn follow_link(&mut self, _: &str) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keys_are_released_after_the_next_frame_unless_the_terminal_reports_it() {
+        let mut pinky = PinkyTerminal::new(Buttons::default(), false);
+        assert!(pinky.on_key(KeyEvent::from(KeyCode::Char('z'))));
+        assert!(!pinky.on_key(KeyEvent::from(KeyCode::Char('w'))));
+        assert_eq!(pinky.state.held, Button::A);
+        pinky.frame(handle_sound);
+        assert_eq!(pinky.state.held, Button::Ty::empty());
+
+        let mut pinky = PinkyTerminal::new(Buttons::default(), true);
+        pinky.on_key(KeyEvent::from(KeyCode::Up));
+        pinky.frame(handle_sound);
+        assert_eq!(pinky.state.held, Button::Up);
+    }
+
+    #[test]
+    fn keys_are_looked_up_by_the_name_of_the_key_they_are_on() {
+        assert_eq!(key_name(KeyCode::Char('z')).unwrap(), "KeyZ");
+        assert_eq!(key_name(KeyCode::Char('Z')).unwrap(), "KeyZ");
+        assert_eq!(key_name(KeyCode::Char('/')).unwrap(), "Slash");
+        assert_eq!(key_name(KeyCode::Up).unwrap(), "ArrowUp");
+
+        let mut pinky = PinkyTerminal::new(Buttons::default(), true);
+        let mut bindings = KeyBindings::default();
+        bindings.bind("KeyW", Action::A);
+        pinky.set_key_bindings(bindings);
+        pinky.on_key(KeyEvent::from(KeyCode::Char('w')));
+        pinky.on_key(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(pinky.state.held, Button::A);
+        assert_eq!(pinky.state.acted, [Action::NextMode]);
+    }
+
+    #[test]
+    fn the_dvorak_keys_press_a_and_b_unless_they_are_bound() {
+        let mut pinky = PinkyTerminal::new(Buttons::default(), true);
+        pinky.on_key(KeyEvent::from(KeyCode::Char(';')));
+        pinky.on_key(KeyEvent::from(KeyCode::Char('q')));
+        assert_eq!(pinky.state.held, Button::A | Button::B);
+
+        let mut bindings = KeyBindings::default();
+        bindings.bind("KeyQ", Action::Search);
+        let pinky = PinkyTerminal::new(
+            Buttons {
+                bindings,
+                ..Buttons::default()
+            },
+            true,
+        );
+        assert_eq!(pinky.key_bindings().lookup("KeyQ"), Some(Action::Search));
+        assert_eq!(pinky.key_bindings().lookup("Semicolon"), Some(Action::A));
+    }
+
+    #[test]
+    fn export_saves_the_session_instead_of_acting() {
+        let session = std::env::temp_dir().join("arbitrary-bytes-terminal-export-test.json");
+        let _ = fs::remove_file(&session);
+
+        let mut pinky = PinkyTerminal::new(Buttons::default(), true);
+        pinky.session = Some(session.clone());
+        assert!(pinky.on_key(KeyEvent::from(KeyCode::Char('e'))));
+        assert_eq!(pinky.state.acted, []);
+        assert_eq!(fs::read_to_string(&session).unwrap(), "");
+
+        fs::remove_file(&session).unwrap();
+    }
+
+    #[test]
+    fn keys_type_text_while_the_state_wants_it() {
+        let mut pinky = PinkyTerminal::new(
+            Buttons {
+                text: Some(String::new()),
+                ..Buttons::default()
+            },
+            true,
+        );
+        let escape = KeyEvent::from(KeyCode::Esc);
+        assert!(!is_quit(&escape, pinky.wants_text_input()));
+
+        pinky.on_key(KeyEvent::from(KeyCode::Char('z')));
+        pinky.on_key(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(pinky.state.text.as_deref(), Some("zx"));
+        assert_eq!(pinky.state.held, Button::Ty::empty());
+
+        pinky.on_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(pinky.state.text, None);
+        assert!(is_quit(&escape, pinky.wants_text_input()));
+    }
+}
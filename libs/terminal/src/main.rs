@@ -0,0 +1,86 @@
+//! Shows a file in the terminal, with the same modes and controls as the web version.
+
+use platform_types::State;
+use std::path::Path;
+use std::{fs, process};
+
+const USAGE: &str = "\
+Usage: arbitrary-bytes-terminal [--session SESSION] [FILE] [OTHER]
+
+Shows FILE, or some example bytes if it is left out. Given OTHER too, it starts in
+the comparison mode, showing where FILE and OTHER differ. The terminal needs to
+support 24 bit colour, and 256 columns by 128 rows shows every pixel.
+
+Given a SESSION file, the modes, offsets, streams and settings saved in it are
+restored at the start, as long as it was saved with the same FILE, and the session
+is saved back there on quitting. Pressing e saves it there too, or to session.json
+without a SESSION file.
+
+Keys:
+  Arrow keys    Up, Down, Left and Right
+  z or ;        A
+  x or q        B
+  Enter         Start
+  Tab           Select
+  e             Export session
+  Esc, Ctrl+C   Quit";
+
+fn main() {
+    let mut session = None;
+    let mut paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--session" => match args.next() {
+                Some(path) => session = Some(path),
+                None => fail("--session needs a file"),
+            },
+            _ if paths.len() < 2 => paths.push(arg),
+            _ => fail(&format!("unexpected argument {}\n\n{}", arg, USAGE)),
+        }
+    }
+    let mut paths = paths.into_iter();
+    let path = paths.next();
+    let other = paths.next();
+
+    let params = terminal::get_state_params();
+    let mut state = player::EntireState::new(params);
+
+    if let Some(path) = path {
+        state.update_bytes(read(&path));
+    }
+    if let Some(other) = other {
+        state.update_comparison_bytes(read(&other));
+    }
+
+    // A session file that isn't there yet is made on quitting.
+    if let Some(ref session) = session {
+        if let Ok(json) = fs::read_to_string(session) {
+            if let Err(e) = state.restore_session(&json) {
+                fail(&format!("could not restore {}: {}", session, e));
+            }
+        }
+    }
+
+    let export = Path::new(session.as_deref().unwrap_or("session.json"));
+    let state = terminal::run(state, export).unwrap_or_else(|e| fail(&e.to_string()));
+
+    if let Some(session) = session {
+        if let Err(e) = fs::write(&session, state.session()) {
+            fail(&format!("could not save {}: {}", session, e));
+        }
+    }
+}
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| fail(&format!("could not read {}: {}", path, e)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
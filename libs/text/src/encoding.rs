@@ -0,0 +1,201 @@
+/// The text encodings `byte_roles` can decode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+        }
+    }
+}
+
+/// What a byte is doing, as far as decoding it as text goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRole {
+    /// A character below 0x80 on its own, or in UTF-16 either byte of one.
+    Ascii,
+    /// A UTF-8 lead byte without enough continuation bytes after it.
+    Lead,
+    /// A UTF-8 continuation byte that isn't part of a complete sequence.
+    Continuation,
+    /// Part of a valid character that takes more than one byte.
+    Multibyte,
+    /// Part of an overlong sequence, or one past U+10FFFF, or a byte that can't start a
+    /// UTF-8 sequence at all. In UTF-16, an odd byte left over at the end.
+    Invalid,
+    /// Part of a surrogate encoded in UTF-8, or an unpaired one in UTF-16.
+    Surrogate,
+}
+
+impl ByteRole {
+    pub fn name(self) -> &'static str {
+        match self {
+            ByteRole::Ascii => "ASCII",
+            ByteRole::Lead => "lead",
+            ByteRole::Continuation => "continuation",
+            ByteRole::Multibyte => "multi-byte",
+            ByteRole::Invalid => "invalid",
+            ByteRole::Surrogate => "surrogate",
+        }
+    }
+}
+
+/// Decodes `bytes` as `encoding`, and returns the role of each byte.
+///
+/// A UTF-8 sequence's length comes from its lead byte alone, so a complete sequence that
+/// is overlong or a surrogate is marked as a whole, rather than split into a lone lead
+/// byte and stray continuation bytes the way a decoder recovering from errors would.
+pub fn byte_roles(bytes: &[u8], encoding: Encoding) -> Vec<ByteRole> {
+    match encoding {
+        Encoding::Utf8 => utf8_roles(bytes),
+        Encoding::Utf16Le => utf16_roles(bytes, |pair| u16::from_le_bytes([pair[0], pair[1]])),
+        Encoding::Utf16Be => utf16_roles(bytes, |pair| u16::from_be_bytes([pair[0], pair[1]])),
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+fn utf8_roles(bytes: &[u8]) -> Vec<ByteRole> {
+    let mut roles = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        // The sequence length, the lead byte's payload, and the smallest code point that
+        // needs this many bytes.
+        let (len, mut code_point, min) = match lead {
+            0x00..=0x7F => {
+                roles.push(ByteRole::Ascii);
+                i += 1;
+                continue;
+            }
+            0x80..=0xBF => {
+                roles.push(ByteRole::Continuation);
+                i += 1;
+                continue;
+            }
+            0xC0..=0xDF => (2, u32::from(lead & 0x1F), 0x80),
+            0xE0..=0xEF => (3, u32::from(lead & 0x0F), 0x800),
+            0xF0..=0xF7 => (4, u32::from(lead & 0x07), 0x1_0000),
+            0xF8..=0xFF => {
+                roles.push(ByteRole::Invalid);
+                i += 1;
+                continue;
+            }
+        };
+
+        let continuations = bytes[i + 1..]
+            .iter()
+            .take(len - 1)
+            .take_while(|&&b| is_continuation(b))
+            .count();
+        if continuations < len - 1 {
+            roles.push(ByteRole::Lead);
+            roles.extend((0..continuations).map(|_| ByteRole::Continuation));
+            i += 1 + continuations;
+            continue;
+        }
+
+        for &byte in &bytes[i + 1..i + len] {
+            code_point = code_point << 6 | u32::from(byte & 0x3F);
+        }
+        let role = if code_point < min || code_point > 0x10_FFFF {
+            ByteRole::Invalid
+        } else if (0xD800..=0xDFFF).contains(&code_point) {
+            ByteRole::Surrogate
+        } else {
+            ByteRole::Multibyte
+        };
+        roles.extend((0..len).map(|_| role));
+        i += len;
+    }
+
+    roles
+}
+
+fn utf16_roles(bytes: &[u8], unit: fn(&[u8]) -> u16) -> Vec<ByteRole> {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(unit).collect();
+    let mut roles = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < units.len() {
+        let (role, len) = match units[i] {
+            0x0000..=0x007F => (ByteRole::Ascii, 1),
+            0xD800..=0xDBFF => match units.get(i + 1) {
+                Some(0xDC00..=0xDFFF) => (ByteRole::Multibyte, 2),
+                _ => (ByteRole::Surrogate, 1),
+            },
+            0xDC00..=0xDFFF => (ByteRole::Surrogate, 1),
+            _ => (ByteRole::Multibyte, 1),
+        };
+        roles.extend((0..len * 2).map(|_| role));
+        i += len;
+    }
+
+    if bytes.len() % 2 == 1 {
+        roles.push(ByteRole::Invalid);
+    }
+
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ByteRole::*;
+
+    #[test]
+    fn utf8_roles_mark_each_kind_of_byte() {
+        assert_eq!(
+            byte_roles("aé€".as_bytes(), Encoding::Utf8),
+            [Ascii, Multibyte, Multibyte, Multibyte, Multibyte, Multibyte]
+        );
+        // A lead byte cut short by an ASCII byte, then a stray continuation byte.
+        assert_eq!(
+            byte_roles(&[0xE2, 0x82, b'x', 0x82, 0xFF], Encoding::Utf8),
+            [Lead, Continuation, Ascii, Continuation, Invalid]
+        );
+        // An overlong '/', a surrogate, and a code point past U+10FFFF.
+        assert_eq!(
+            byte_roles(&[0xC0, 0xAF], Encoding::Utf8),
+            [Invalid, Invalid]
+        );
+        assert_eq!(
+            byte_roles(&[0xED, 0xA0, 0x80], Encoding::Utf8),
+            [Surrogate, Surrogate, Surrogate]
+        );
+        assert_eq!(
+            byte_roles(&[0xF4, 0x90, 0x80, 0x80], Encoding::Utf8),
+            [Invalid; 4]
+        );
+    }
+
+    #[test]
+    fn utf16_roles_pair_up_surrogates() {
+        let bytes = [b'a', 0, 0xE9, 0, 0x3D, 0xD8, 0x00, 0xDE, 0x00, 0xDC, 0xFF];
+
+        assert_eq!(
+            byte_roles(&bytes, Encoding::Utf16Le),
+            [
+                Ascii, Ascii, Multibyte, Multibyte, Multibyte, Multibyte, Multibyte, Multibyte,
+                Surrogate, Surrogate, Invalid,
+            ]
+        );
+        assert_eq!(
+            byte_roles(&[0, b'a', 0xD8, 0x3D], Encoding::Utf16Be),
+            [Ascii, Ascii, Surrogate, Surrogate]
+        );
+    }
+}
@@ -1,3 +1,7 @@
 mod text;
 
 pub use self::text::*;
+
+mod encoding;
+
+pub use self::encoding::*;
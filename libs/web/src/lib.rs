@@ -6,18 +6,33 @@
 extern crate stdweb;
 
 use std::cell::RefCell;
+use std::cmp::{max, min};
 use std::error::Error;
 use std::rc::Rc;
 
-use stdweb::web::event::{IEvent, IKeyboardEvent, KeyDownEvent, KeyUpEvent, KeyboardLocation};
+use stdweb::unstable::TryInto;
+use stdweb::web::event::{
+    ContextMenuEvent, IEvent, IKeyboardEvent, IMouseEvent, KeyDownEvent, KeyUpEvent, MouseButton,
+    MouseDownEvent, MouseLeaveEvent, MouseMoveEvent, MouseUpEvent, MouseWheelDeltaMode,
+    MouseWheelEvent, ResizeEvent,
+};
 use stdweb::web::{
     self, Element, FileReader, FileReaderResult, IElement, IEventTarget, INode,
     INonElementParentNode,
 };
 
+use stdweb::serde::Serde;
 use stdweb::{UnsafeTypedArray, Value};
 
-use platform_types::{h, w, Button, State, StateParams, SFX};
+use platform_types::{
+    h, w, Action, Button, EditKey, KeyBindings, PointerButton, State, StateParams, SFX,
+};
+
+// Where the key bindings are kept in `localStorage` between visits.
+const KEY_BINDINGS_STORAGE_KEY: &str = "keyBindings";
+
+// How much of the window's height the page's style gives the canvas.
+const CANVAS_HEIGHT_FRACTION: f64 = 0.9;
 
 macro_rules! enclose {
     ( [$( $x:ident ),*] $y:expr ) => {
@@ -30,7 +45,7 @@ macro_rules! enclose {
 
 // This creates a really basic WebGL context for blitting a single texture.
 // On some web browsers this is faster than using a 2d canvas.
-fn setup_webgl(canvas: &Element) -> Value {
+fn setup_webgl(canvas: &Element, width: usize, height: usize) -> Value {
     const FRAGMENT_SHADER: &'static str = r#"
         precision mediump float;
         varying vec2 v_texcoord;
@@ -125,7 +140,7 @@ fn setup_webgl(canvas: &Element) -> Value {
         var sampler_uniform = gl.getUniformLocation( program, "u_sampler" );
         gl.uniform1i( sampler_uniform, 0 );
 
-        var matrix = @{ortho( 0.0, w!(.0), h!(.0), 0.0 )};
+        var matrix = @{ortho( 0.0, width as f64, height as f64, 0.0 )};
         var matrix_uniform = gl.getUniformLocation( program, "u_matrix" );
         gl.uniformMatrix4fv( matrix_uniform, false, matrix );
 
@@ -135,12 +150,12 @@ fn setup_webgl(canvas: &Element) -> Value {
             gl.TEXTURE_2D,
             0,
             gl.RGBA,
-            @{w!()},
-            @{h!()},
+            @{width as u32},
+            @{height as u32},
             0,
             gl.RGBA,
             gl.UNSIGNED_BYTE,
-            new Uint8Array(@{w!() * h!() * 4 })
+            new Uint8Array(@{(width * height * 4) as u32})
           );
         gl.texParameteri( gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.NEAREST );
         gl.texParameteri( gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.NEAREST );
@@ -149,9 +164,9 @@ fn setup_webgl(canvas: &Element) -> Value {
         gl.bindBuffer( gl.ARRAY_BUFFER, vertex_buffer );
         var vertices = [
             0.0, 0.0,
-            0.0, @{h!(.0)},
-            @{w!(.0)}, 0.0,
-            @{w!(.0)}, @{h!(.0)}
+            0.0, @{height as f64},
+            @{width as f64}, 0.0,
+            @{width as f64}, @{height as f64}
         ];
         gl.bufferData( gl.ARRAY_BUFFER, new Float32Array( vertices ), gl.STATIC_DRAW );
         gl.vertexAttribPointer( vertex_attr, 2, gl.FLOAT, false, 0, 0 );
@@ -160,9 +175,9 @@ fn setup_webgl(canvas: &Element) -> Value {
         gl.bindBuffer( gl.ARRAY_BUFFER, texcoord_buffer );
         var texcoords = [
             0.0, 0.0,
-            0.0, @{w!(.0) / h!(.0)},
+            0.0, 1.0,
             1.0, 0.0,
-            1.0, @{w!(.0) / h!(.0)}
+            1.0, 1.0
         ];
         gl.bufferData( gl.ARRAY_BUFFER, new Float32Array( texcoords ), gl.STATIC_DRAW );
         gl.vertexAttribPointer( texcoord_attr, 2, gl.FLOAT, false, 0, 0 );
@@ -177,7 +192,7 @@ fn setup_webgl(canvas: &Element) -> Value {
 
         gl.clearColor( 0.0, 0.0, 0.0, 1.0 );
         gl.enable( gl.DEPTH_TEST );
-        gl.viewport( 0, 0, @{w!()}, @{h!()});
+        gl.viewport( 0, 0, @{width as u32}, @{height as u32});
 
         return gl;
     )
@@ -198,6 +213,17 @@ struct PinkyWeb<S: State> {
     busy: bool,
     js_ctx: Value,
     state: S,
+    // The size the framebuffer was last given, which the texture or image data matches.
+    size: (usize, usize),
+    // The part of a line the wheel has scrolled that hasn't been passed on yet, since
+    // touchpads scroll a few pixels at a time.
+    wheel_remainder: f64,
+    // The key bindings as they were last saved, so they are only saved again once changed.
+    saved_bindings: KeyBindings,
+    // A link from the page's URL, to follow again once the file it is about has been loaded.
+    pending_link: Option<String>,
+    // The link last put in the page's URL, so it is only replaced once the view changes.
+    shown_link: String,
 }
 
 impl<S: State> State for PinkyWeb<S> {
@@ -219,12 +245,107 @@ impl<S: State> State for PinkyWeb<S> {
 
     fn update_bytes(&mut self, bytes: Vec<u8>) {
         self.state.update_bytes(bytes);
+        // Loading a file starts it in its own mode at the top, so the link has to be
+        // followed again. It was already checked when the page loaded.
+        if let Some(link) = self.pending_link.take() {
+            let _ = self.state.follow_link(&link);
+        }
+    }
+
+    fn update_comparison_bytes(&mut self, bytes: Vec<u8>) {
+        self.state.update_comparison_bytes(bytes);
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        self.state.resize(width, height);
+        self.size = (width, height);
+
+        js! {
+            var h = @{&self.js_ctx};
+            var width = @{width as u32};
+            var height = @{height as u32};
+            h.canvas.width = width;
+            h.canvas.height = height;
+            if( h.gl ) {
+                h.gl.texImage2D( h.gl.TEXTURE_2D, 0, h.gl.RGBA, width, height, 0,
+                    h.gl.RGBA, h.gl.UNSIGNED_BYTE, new Uint8Array( width * height * 4 ) );
+                h.gl.viewport( 0, 0, width, height );
+            } else {
+                h.img = h.ctx.createImageData( width, height );
+                h.buffer = new Uint32Array( h.img.data.buffer );
+            }
+        }
+    }
+
+    fn pointer_moved(&mut self, position: Option<(usize, usize)>) {
+        self.state.pointer_moved(position);
+    }
+
+    fn pointer_pressed(&mut self, button: PointerButton::Ty) {
+        self.state.pointer_pressed(button);
+    }
+
+    fn pointer_released(&mut self, button: PointerButton::Ty) {
+        self.state.pointer_released(button);
+    }
+
+    fn wheel(&mut self, lines: i32) {
+        self.state.wheel(lines);
+    }
+
+    fn wants_text_input(&self) -> bool {
+        self.state.wants_text_input()
+    }
+
+    fn text_input(&mut self, ch: char) {
+        self.state.text_input(ch);
+    }
+
+    fn edit_key(&mut self, key: EditKey) {
+        self.state.edit_key(key);
+    }
+
+    fn key_bindings(&self) -> &KeyBindings {
+        self.state.key_bindings()
+    }
+
+    fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.state.set_key_bindings(bindings);
+    }
+
+    fn act(&mut self, action: Action) {
+        self.state.act(action);
+    }
+
+    fn binding_key(&self) -> bool {
+        self.state.binding_key()
+    }
+
+    fn bind_key(&mut self, key: &str) {
+        self.state.bind_key(key);
+    }
+
+    fn session(&self) -> String {
+        self.state.session()
+    }
+
+    fn restore_session(&mut self, json: &str) -> Result<(), String> {
+        self.state.restore_session(json)
+    }
+
+    fn link(&self) -> String {
+        self.state.link()
+    }
+
+    fn follow_link(&mut self, link: &str) -> Result<(), String> {
+        self.state.follow_link(link)
     }
 }
 
 impl<S: State> PinkyWeb<S> {
     fn new(canvas: &Element, state: S) -> Self {
-        let gl = setup_webgl(&canvas);
+        let size = (w!(), h!());
+        let gl = setup_webgl(&canvas, size.0, size.1);
 
         let js_ctx = js!(
             var h = {};
@@ -244,18 +365,25 @@ impl<S: State> PinkyWeb<S> {
                 canvas = new_canvas;
 
                 h.ctx = canvas.getContext( "2d" );
-                h.img = h.ctx.createImageData( @{w!()}, @{h!()} );
+                h.img = h.ctx.createImageData( @{size.0 as u32}, @{size.1 as u32} );
                 h.buffer = new Uint32Array( h.img.data.buffer );
             }
 
+            h.canvas = canvas;
+
             return h;
         );
 
         PinkyWeb {
-            state,
             paused: true,
             busy: false,
             js_ctx,
+            size,
+            wheel_remainder: 0.0,
+            saved_bindings: state.key_bindings().clone(),
+            pending_link: None,
+            shown_link: String::new(),
+            state,
         }
     }
 
@@ -271,6 +399,21 @@ impl<S: State> PinkyWeb<S> {
     fn execute_cycle(&mut self) -> Result<bool, Box<dyn Error>> {
         self.state.frame(handle_sound);
 
+        if *self.state.key_bindings() != self.saved_bindings {
+            save_key_bindings(self.state.key_bindings());
+            self.saved_bindings = self.state.key_bindings().clone();
+        }
+
+        // Keep the URL pointing at what is shown so it can be copied and shared, but leave
+        // a link that hasn't been followed for its file yet alone.
+        if self.pending_link.is_none() {
+            let link = self.state.link();
+            if link != self.shown_link {
+                show_link(&link);
+                self.shown_link = link;
+            }
+        }
+
         Ok(true)
     }
 
@@ -298,6 +441,7 @@ impl<S: State> PinkyWeb<S> {
     }
 
     fn draw(&mut self) {
+        let (width, height) = (self.size.0 as u32, self.size.1 as u32);
         if !self.paused {
             js! {
                 var h = @{&self.js_ctx};
@@ -311,7 +455,7 @@ impl<S: State> PinkyWeb<S> {
                         framebuffer.byteLength
                     );
                     h.gl.texSubImage2D( h.gl.TEXTURE_2D,
-                         0, 0, 0, @{w!()}, @{h!()}, h.gl.RGBA, h.gl.UNSIGNED_BYTE, data );
+                         0, 0, 0, @{width}, @{height}, h.gl.RGBA, h.gl.UNSIGNED_BYTE, data );
                     h.gl.drawElements( h.gl.TRIANGLES, 6, h.gl.UNSIGNED_SHORT, 0 );
                 } else {
                     h.buffer.set( framebuffer );
@@ -321,43 +465,118 @@ impl<S: State> PinkyWeb<S> {
         }
     }
 
-    fn on_key(&mut self, key: &str, location: KeyboardLocation, is_pressed: bool) -> bool {
-        let button = match (key, location) {
-            ("Enter", _) => Button::Start,
-            ("Shift", KeyboardLocation::Right) => Button::Select,
-            ("ArrowUp", _) => Button::Up,
-            ("ArrowLeft", _) => Button::Left,
-            ("ArrowRight", _) => Button::Right,
-            ("ArrowDown", _) => Button::Down,
-
-            // On Edge the arrows have different names
-            // for some reason.
-            ("Up", _) => Button::Up,
-            ("Left", _) => Button::Left,
-            ("Right", _) => Button::Right,
-            ("Down", _) => Button::Down,
-
-            ("z", _) => Button::A,
-            ("x", _) => Button::B,
-
-            // For those using the Dvorak layout.
-            (";", _) => Button::A,
-            ("q", _) => Button::B,
-
-            // For those using the Dvorak layout **and** Microsoft Edge.
-            //
-            // On `keydown` we get ";" as we should, but on `keyup`
-            // we get "Unidentified". Seriously Microsoft, how buggy can
-            // your browser be?
-            ("Unidentified", _) if is_pressed == false => Button::A,
+    // Fits the framebuffer to the window, unless it already does.
+    fn fit_window(&mut self) {
+        let window = web::window();
+        let (width, height) = size_for_window(window.inner_width(), window.inner_height());
+        if (width, height) != self.size {
+            self.resize(width, height);
+        }
+    }
+
+    // Presses or releases whatever `code`, the name of the key from
+    // `KeyboardEvent.code`, is bound to. Actions that aren't buttons happen on the press.
+    fn on_key(&mut self, code: &str, is_pressed: bool) -> bool {
+        let action = match self.state.key_bindings().lookup(code) {
+            Some(action) => action,
+            None => return false,
+        };
+
+        match action.button() {
+            Some(button) => PinkyWeb::set_button_state(self, button, is_pressed),
+            None if is_pressed && action == Action::Export => {
+                download_session(&self.state.session())
+            }
+            None if is_pressed => self.state.act(action),
+            None => {}
+        }
+        true
+    }
 
+    // Types with `key` while the state wants text. Returns false for keys that are neither
+    // a single character nor one of the editing keys.
+    fn on_text_key(&mut self, key: &str) -> bool {
+        let edit = match key {
+            "Backspace" => EditKey::Backspace,
+            "Delete" | "Del" => EditKey::Delete,
+            "ArrowLeft" | "Left" => EditKey::Left,
+            "ArrowRight" | "Right" => EditKey::Right,
+            "Home" => EditKey::Home,
+            "End" => EditKey::End,
+            "Enter" => EditKey::Enter,
+            "Escape" | "Esc" => EditKey::Escape,
+            _ => {
+                let mut chars = key.chars();
+                return match (chars.next(), chars.next()) {
+                    (Some(ch), None) => {
+                        self.state.text_input(ch);
+                        true
+                    }
+                    _ => false,
+                };
+            }
+        };
+        self.state.edit_key(edit);
+        true
+    }
+
+    // Where the page coordinates `(x, y)` are on the canvas, in framebuffer pixels. CSS can
+    // stretch the canvas, so this goes by the size it is shown at.
+    fn framebuffer_position(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let rect: Vec<f64> = js!(
+            var rect = @{&self.js_ctx}.canvas.getBoundingClientRect();
+            return [ rect.left, rect.top, rect.width, rect.height ];
+        )
+        .try_into()
+        .ok()?;
+        let (left, top, width, height) = (rect[0], rect[1], rect[2], rect[3]);
+
+        let x = (f64::from(x) - left) / width;
+        let y = (f64::from(y) - top) / height;
+        if x >= 0.0 && x < 1.0 && y >= 0.0 && y < 1.0 {
+            Some((
+                (x * self.size.0 as f64) as usize,
+                (y * self.size.1 as f64) as usize,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn on_mouse_button(&mut self, button: MouseButton, is_pressed: bool) -> bool {
+        let button = match button {
+            MouseButton::Left => PointerButton::Primary,
+            MouseButton::Right => PointerButton::Secondary,
+            MouseButton::Wheel => PointerButton::Middle,
             _ => return false,
         };
 
-        PinkyWeb::set_button_state(self, button, is_pressed);
+        if is_pressed {
+            self.state.pointer_pressed(button);
+        } else {
+            self.state.pointer_released(button);
+        }
         return true;
     }
 
+    fn on_wheel(&mut self, delta: f64, mode: MouseWheelDeltaMode) {
+        // Roughly what browsers scroll a page by for each line and each page.
+        const PIXELS_PER_LINE: f64 = 40.0;
+        const LINES_PER_PAGE: f64 = 16.0;
+
+        let lines = match mode {
+            MouseWheelDeltaMode::Pixel => delta / PIXELS_PER_LINE,
+            MouseWheelDeltaMode::Line => delta,
+            MouseWheelDeltaMode::Page => delta * LINES_PER_PAGE,
+        } + self.wheel_remainder;
+
+        let whole_lines = lines.trunc();
+        self.wheel_remainder = lines - whole_lines;
+        if whole_lines != 0.0 {
+            self.state.wheel(whole_lines as i32);
+        }
+    }
+
     fn set_button_state(&mut self, button: Button::Ty, is_pressed: bool) {
         if is_pressed {
             self.state.press(button);
@@ -437,18 +656,131 @@ fn hide(id: &str) {
 
 fn support_input<S: State + 'static>(pinky: Rc<RefCell<PinkyWeb<S>>>) {
     web::window().add_event_listener(enclose!( [pinky] move |event: KeyDownEvent| {
-        let handled = pinky.borrow_mut().on_key( &event.key(), event.location(), true );
+        let mut pinky = pinky.borrow_mut();
+        // Shortcuts like copy and paste are left to the browser.
+        let handled = if pinky.binding_key() {
+            pinky.bind_key( &event.code() );
+            true
+        } else if pinky.wants_text_input() {
+            !event.ctrl_key() && !event.meta_key() && pinky.on_text_key( &event.key() )
+        } else {
+            pinky.on_key( &event.code(), true )
+        };
         if handled {
             event.prevent_default();
         }
     }));
 
     web::window().add_event_listener(enclose!( [pinky] move |event: KeyUpEvent| {
-        let handled = pinky.borrow_mut().on_key( &event.key(), event.location(), false );
+        let handled = pinky.borrow_mut().on_key( &event.code(), false );
         if handled {
             event.prevent_default();
         }
     }));
+
+    web::window().add_event_listener(enclose!( [pinky] move |_: ResizeEvent| {
+        pinky.borrow_mut().fit_window();
+    }));
+
+    // Looked up again since the canvas is replaced when WebGL isn't available.
+    let canvas = web::document().get_element_by_id("viewport").unwrap();
+
+    canvas.add_event_listener(enclose!( [pinky] move |event: MouseMoveEvent| {
+        let mut pinky = pinky.borrow_mut();
+        let position = pinky.framebuffer_position( event.client_x(), event.client_y() );
+        pinky.pointer_moved( position );
+    }));
+
+    canvas.add_event_listener(enclose!( [pinky] move |_: MouseLeaveEvent| {
+        pinky.borrow_mut().pointer_moved( None );
+    }));
+
+    canvas.add_event_listener(enclose!( [pinky] move |event: MouseDownEvent| {
+        let mut pinky = pinky.borrow_mut();
+        let position = pinky.framebuffer_position( event.client_x(), event.client_y() );
+        pinky.pointer_moved( position );
+        if pinky.on_mouse_button( event.button(), true ) {
+            event.prevent_default();
+        }
+    }));
+
+    // On the window, so letting go of a button after dragging off the canvas still counts.
+    web::window().add_event_listener(enclose!( [pinky] move |event: MouseUpEvent| {
+        pinky.borrow_mut().on_mouse_button( event.button(), false );
+    }));
+
+    // Otherwise the browser's menu would open on top of the canvas.
+    canvas.add_event_listener(|event: ContextMenuEvent| {
+        event.prevent_default();
+    });
+
+    canvas.add_event_listener(enclose!( [pinky] move |event: MouseWheelEvent| {
+        pinky.borrow_mut().on_wheel( event.delta_y(), event.delta_mode() );
+        event.prevent_default();
+    }));
+}
+
+// The key bindings saved by an earlier visit, if there are any and they can still be read.
+fn load_key_bindings() -> Option<KeyBindings> {
+    let saved = js!(
+        try {
+            var saved = localStorage.getItem( @{KEY_BINDINGS_STORAGE_KEY} );
+            return saved === null ? null : JSON.parse( saved );
+        } catch( error ) {
+            return null;
+        }
+    );
+    let bindings: Serde<KeyBindings> = saved.try_into().ok()?;
+    Some(bindings.0)
+}
+
+fn save_key_bindings(bindings: &KeyBindings) {
+    js! { @(no_return)
+        try {
+            localStorage.setItem( @{KEY_BINDINGS_STORAGE_KEY}, JSON.stringify( @{Serde(bindings)} ) );
+        } catch( error ) {
+            console.error( "Could not save the key bindings:", error );
+        }
+    }
+}
+
+// The link in the page's URL fragment, if there is one.
+fn link_in_url() -> Option<String> {
+    let link = js!(
+        var hash = window.location.hash;
+        return hash.length > 1 ? decodeURIComponent( hash.slice( 1 ) ) : null;
+    );
+    link.try_into().ok()
+}
+
+// The framebuffer size that fills a `width` by `height` window. Each pixel is drawn as big
+// as the default size would be, so a bigger window shows more bytes rather than bigger
+// pixels.
+fn size_for_window(width: i32, height: i32) -> (usize, usize) {
+    let width = max(width, 1) as usize;
+    let height = max((f64::from(height) * CANVAS_HEIGHT_FRACTION) as usize, 1);
+    let scale = max(min(width / w!(), height / h!()), 1);
+    (width / scale, height / scale)
+}
+
+// Saves `json` as session.json, the same as the page's save button.
+fn download_session(json: &str) {
+    js! { @(no_return)
+        var blob = new Blob( [ @{json} ], { type: "application/json" } );
+        var link = document.createElement( "a" );
+        link.href = URL.createObjectURL( blob );
+        link.download = "session.json";
+        link.click();
+        URL.revokeObjectURL( link.href );
+    }
+}
+
+// Puts `link` in the page's URL fragment, replacing the history entry rather than adding
+// one each time the view changes.
+fn show_link(link: &str) {
+    js! { @(no_return)
+        history.replaceState( null, "", "#" + @{link} );
+    }
 }
 
 fn handle_error<E: Into<Box<dyn Error>>>(error: E) {
@@ -469,6 +801,48 @@ use stdweb::web::TypedArray;
 
 #[js_export]
 fn update_bytes(file_reader: FileReader) {
+    read_bytes(file_reader, |pinky, bytes| pinky.update_bytes(bytes));
+}
+
+#[js_export]
+fn update_comparison_bytes(file_reader: FileReader) {
+    read_bytes(file_reader, |pinky, bytes| {
+        pinky.update_comparison_bytes(bytes)
+    });
+}
+
+/// The viewing session as JSON, for the page to save as a file.
+#[js_export]
+fn session() -> String {
+    with_pinky(|pinky| pinky.session()).unwrap_or_default()
+}
+
+/// Restores a session saved by `session`, returning why it couldn't be if it couldn't.
+#[js_export]
+fn restore_session(json: String) -> Option<String> {
+    with_pinky(|pinky| pinky.restore_session(&json).err())
+        .unwrap_or_else(|| Some(String::from("not running yet")))
+}
+
+// Calls `f` with the state, unless it isn't there or is already borrowed.
+fn with_pinky<T>(f: impl FnOnce(&mut (State + 'static)) -> T) -> Option<T> {
+    match unsafe { PINKY.as_ref() } {
+        Some(pinky) => match pinky.try_borrow_mut() {
+            Ok(mut pinky) => Some(f(&mut *pinky)),
+            Err(e) => {
+                console!(log, format!("Could not borrow PINKY! {:?}", e));
+                None
+            }
+        },
+        None => {
+            console!(log, "PINKY was None!");
+            None
+        }
+    }
+}
+
+// Passes the bytes `file_reader` has read to `pass`, along with the state.
+fn read_bytes(file_reader: FileReader, pass: fn(&mut (State + 'static), Vec<u8>)) {
     let s = match file_reader.result() {
         Some(value) => match value {
             FileReaderResult::ArrayBuffer(value) => {
@@ -476,7 +850,7 @@ fn update_bytes(file_reader: FileReader) {
                 match unsafe { PINKY.as_ref() } {
                     Some(pinky) => match pinky.try_borrow_mut() {
                         Ok(mut pinky) => {
-                            pinky.update_bytes(typed.to_vec());
+                            pass(&mut *pinky, typed.to_vec());
                         }
                         Err(e) => {
                             console!(log, format!("Could not borrow PINKY! {:?}", e));
@@ -509,6 +883,20 @@ pub fn run<S: State + 'static>(state: S) {
     let canvas = web::document().get_element_by_id("viewport").unwrap();
 
     let pinky = Rc::new(RefCell::new(PinkyWeb::new(&canvas, state)));
+    pinky.borrow_mut().fit_window();
+    if let Some(bindings) = load_key_bindings() {
+        pinky.borrow_mut().set_key_bindings(bindings);
+    }
+    if let Some(link) = link_in_url() {
+        let mut pinky = pinky.borrow_mut();
+        match pinky.follow_link(&link) {
+            Ok(()) => pinky.pending_link = Some(link),
+            Err(e) => console!(
+                error,
+                format!("Could not follow the link {:?}: {}", link, e)
+            ),
+        }
+    }
 
     unsafe {
         PINKY = Some(pinky.clone());